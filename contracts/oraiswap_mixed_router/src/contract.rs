@@ -2,28 +2,44 @@
 use cosmwasm_std::entry_point;
 
 use cosmwasm_std::{
-    attr, from_json, to_json_binary, Addr, Attribute, Binary, CosmosMsg, Deps, DepsMut, Env,
-    MessageInfo, Response, StdError, StdResult, Uint128,
+    attr, from_json, to_json_binary, Addr, Attribute, Binary, CosmosMsg, Decimal256, Deps, DepsMut,
+    Env, MessageInfo, Order, Reply, Response, StdError, StdResult, SubMsg, SubMsgResult, Uint128,
 };
 use oraiswap::error::ContractError;
 use oraiswap_v3::interface::QuoteResult;
 use oraiswap_v3::msg::QueryMsg as SwapV3QueryMsg;
 use oraiswap_v3::sqrt_price::SqrtPrice;
 use oraiswap_v3::token_amount::TokenAmount;
-use oraiswap_v3::{MAX_TICK, MIN_TICK};
+use oraiswap_v3::Pool;
 
-use crate::operations::{execute_swap_operation, execute_swap_operations};
-use crate::state::{Config, CONFIG};
+use crate::operations::{
+    default_sqrt_price_limit, execute_memo, execute_memo_routes, execute_swap_operation,
+    execute_swap_operations, get_offer_asset_info, get_swap_v3_asset_info, is_swap_by_amount_out,
+    load_converter, post_swap_action_msg,
+};
+use crate::state::{
+    BalanceSnapshot, Config, MemoRecovery, AFFILIATES, AFFILIATE_FEES, AFFILIATE_FEE_TOTALS,
+    BALANCE_SNAPSHOTS, CONFIG, MEMO_RECOVERIES, MEMO_REPLY_ID, POST_SWAP_ACTION_RECOVERIES,
+    POST_SWAP_ACTION_REPLY_ID,
+};
 
 use cw20::Cw20ReceiveMsg;
-use oraiswap::asset::{Asset, AssetInfo, PairInfo};
+use cw_storage_plus::Bound;
+use oraiswap::asset::{Asset, AssetInfo};
+use oraiswap::math::compute_price_impact;
 use oraiswap::mixed_router::{
-    Affiliate, ConfigResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg,
-    SimulateSwapOperationsResponse, SwapOperation,
+    Affiliate, AffiliateFeesResponse, AffiliatesResponse, ConfigResponse, Cw20HookMsg, ExecuteMsg,
+    HopSimulation, InstantiateMsg, MigrateMsg, PostSwapAction, QueryMsg,
+    SimulateSwapOperationsDetailedResponse, SimulateSwapOperationsResponse, SwapOperation,
+    SwapVenue,
 };
 use oraiswap::oracle::OracleContract;
 use oraiswap::pair::{QueryMsg as PairQueryMsg, SimulationResponse};
-use oraiswap::querier::{query_pair_config, query_pair_info};
+use oraiswap::querier::{query_pair_config, query_pair_info, query_pool};
+
+// SqrtPrice and Percentage of oraiswap v3 are fixed point numbers
+const SQRT_PRICE_DECIMALS: u32 = 24;
+const PERCENTAGE_DENOMINATOR: u64 = 1_000_000_000_000;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -38,6 +54,12 @@ pub fn instantiate(
             factory_addr: deps.api.addr_canonicalize(msg.factory_addr.as_str())?,
             factory_addr_v2: deps.api.addr_canonicalize(msg.factory_addr_v2.as_str())?,
             oraiswap_v3: deps.api.addr_canonicalize(msg.oraiswap_v3.as_str())?,
+            converter: msg
+                .converter
+                .map(|converter| deps.api.addr_canonicalize(converter.as_str()))
+                .transpose()?,
+            ics20_contract: None,
+            max_affiliate_fee_bps: None,
             owner: deps.api.addr_canonicalize(info.sender.as_str())?,
         },
     )?;
@@ -64,38 +86,95 @@ pub fn execute(
             minimum_receive,
             to,
             affiliates,
-        } => execute_swap_operations(
-            deps,
-            env,
-            info.sender,
-            operations,
-            minimum_receive,
-            to,
-            affiliates.unwrap_or_default(),
-        ),
+            post_swap_action,
+        } => {
+            // the offer of native swaps is paid with the funds
+            let offer_info = operations
+                .first()
+                .map(|operation| get_offer_asset_info(deps.api, operation))
+                .ok_or(ContractError::NoSwapOperation {})?;
+            let amount = match &offer_info {
+                AssetInfo::NativeToken { denom } => info
+                    .funds
+                    .iter()
+                    .filter(|fund| fund.denom.eq(denom))
+                    .map(|fund| fund.amount)
+                    .sum(),
+                AssetInfo::Token { .. } => Uint128::zero(),
+            };
+            execute_swap_operations(
+                deps,
+                env,
+                info.sender,
+                Asset {
+                    info: offer_info,
+                    amount,
+                },
+                operations,
+                minimum_receive,
+                to,
+                affiliates.unwrap_or_default(),
+                post_swap_action,
+            )
+        }
+        ExecuteMsg::ExecuteMemo { memo } => {
+            let [fund] = info.funds.as_slice() else {
+                return Err(ContractError::InvalidFunds {});
+            };
+            let offer_asset = Asset {
+                info: AssetInfo::NativeToken {
+                    denom: fund.denom.clone(),
+                },
+                amount: fund.amount,
+            };
+            execute_memo(deps, env, info.sender, offer_asset, memo)
+        }
         ExecuteMsg::ExecuteSwapOperation {
             operation,
             to,
             sender,
-        } => execute_swap_operation(deps, env, info, operation, to, sender),
+            amount,
+        } => execute_swap_operation(deps, env, info, operation, to, sender, amount),
+        ExecuteMsg::ExecuteMemoRoutes {
+            routes,
+            minimum_receive,
+            post_swap_action,
+            sender,
+            recovery_addr,
+        } => execute_memo_routes(
+            deps,
+            env,
+            info,
+            routes,
+            minimum_receive,
+            post_swap_action,
+            sender,
+            recovery_addr,
+        ),
 
         ExecuteMsg::AssertMinimumReceiveAndTransfer {
             asset_info,
             minimum_receive,
             receiver,
             affiliates,
+            post_swap_action,
         } => assert_minium_receive_and_transfer(
-            deps.as_ref(),
+            deps,
             env,
+            info,
             asset_info,
             minimum_receive,
             receiver,
             affiliates,
+            post_swap_action,
         ),
         ExecuteMsg::UpdateConfig {
             factory_addr,
             factory_addr_v2,
             oraiswap_v3,
+            converter,
+            ics20_contract,
+            max_affiliate_fee_bps,
             owner,
         } => execute_update_config(
             deps,
@@ -103,8 +182,30 @@ pub fn execute(
             factory_addr,
             factory_addr_v2,
             oraiswap_v3,
+            converter,
+            ics20_contract,
+            max_affiliate_fee_bps,
             owner,
         ),
+        ExecuteMsg::SweepDust {
+            asset_infos,
+            recipient,
+        } => execute_sweep_dust(deps, env, info, asset_infos, recipient),
+        ExecuteMsg::RegisterAffiliate {} => {
+            AFFILIATES.save(deps.storage, &info.sender, &true)?;
+            Ok(Response::new().add_attributes(vec![
+                attr("action", "register_affiliate"),
+                attr("affiliate", info.sender),
+            ]))
+        }
+        ExecuteMsg::DeregisterAffiliate {} => {
+            AFFILIATES.remove(deps.storage, &info.sender);
+            Ok(Response::new().add_attributes(vec![
+                attr("action", "deregister_affiliate"),
+                attr("affiliate", info.sender),
+            ]))
+        }
+        ExecuteMsg::ClaimAffiliateFees {} => execute_claim_affiliate_fees(deps, info),
     }
 }
 
@@ -114,6 +215,9 @@ pub fn execute_update_config(
     factory_addr: Option<String>,
     factory_addr_v2: Option<String>,
     oraiswap_v3: Option<String>,
+    converter: Option<String>,
+    ics20_contract: Option<String>,
+    max_affiliate_fee_bps: Option<Uint128>,
     owner: Option<String>,
 ) -> Result<Response, ContractError> {
     let mut config = CONFIG.load(deps.storage)?;
@@ -134,6 +238,15 @@ pub fn execute_update_config(
     if let Some(oraiswap_v3) = oraiswap_v3 {
         config.oraiswap_v3 = deps.api.addr_canonicalize(&oraiswap_v3)?;
     }
+    if let Some(converter) = converter {
+        config.converter = Some(deps.api.addr_canonicalize(&converter)?);
+    }
+    if let Some(ics20_contract) = ics20_contract {
+        config.ics20_contract = Some(deps.api.addr_canonicalize(&ics20_contract)?);
+    }
+    if let Some(max_affiliate_fee_bps) = max_affiliate_fee_bps {
+        config.max_affiliate_fee_bps = Some(max_affiliate_fee_bps);
+    }
     if let Some(owner) = owner {
         config.owner = deps.api.addr_canonicalize(&owner)?;
     }
@@ -142,10 +255,80 @@ pub fn execute_update_config(
     Ok(Response::new().add_attributes(vec![("action", "execute_update_config")]))
 }
 
+/// send the balances left by past swaps, none of them is held during a swap,
+/// the accrued affiliate fees are kept
+pub fn execute_sweep_dust(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    asset_infos: Vec<AssetInfo>,
+    recipient: Option<String>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if config
+        .owner
+        .ne(&deps.api.addr_canonicalize(info.sender.as_str())?)
+    {
+        return Err(ContractError::Unauthorized {});
+    }
+    let recipient = match recipient {
+        Some(recipient) => deps.api.addr_validate(&recipient)?,
+        None => info.sender,
+    };
+
+    let mut msgs: Vec<CosmosMsg> = vec![];
+    let mut attrs = vec![attr("action", "sweep_dust"), attr("recipient", &recipient)];
+    for asset_info in asset_infos {
+        let accrued_fees = AFFILIATE_FEE_TOTALS
+            .may_load(deps.storage, &asset_info.to_string())?
+            .unwrap_or_default();
+        let amount = asset_info
+            .query_pool(&deps.querier, env.contract.address.clone())?
+            .saturating_sub(accrued_fees);
+        if amount.is_zero() {
+            continue;
+        }
+        let asset = Asset {
+            info: asset_info,
+            amount,
+        };
+        attrs.push(attr("swept", asset.to_string()));
+        msgs.push(asset.into_msg(None, &deps.querier, recipient.clone())?);
+    }
+
+    Ok(Response::new().add_messages(msgs).add_attributes(attrs))
+}
+
+pub fn execute_claim_affiliate_fees(
+    deps: DepsMut,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let fees = AFFILIATE_FEES
+        .prefix(&info.sender)
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut msgs: Vec<CosmosMsg> = vec![];
+    let mut attrs = vec![
+        attr("action", "claim_affiliate_fees"),
+        attr("affiliate", &info.sender),
+    ];
+    for (asset_key, fee) in fees {
+        AFFILIATE_FEES.remove(deps.storage, (&info.sender, asset_key.as_str()));
+        AFFILIATE_FEE_TOTALS.update(deps.storage, &asset_key, |total| -> StdResult<_> {
+            Ok(total.unwrap_or_default().checked_sub(fee.amount)?)
+        })?;
+        attrs.push(attr("claimed", fee.to_string()));
+        msgs.push(fee.into_msg(None, &deps.querier, info.sender.clone())?);
+    }
+
+    Ok(Response::new().add_messages(msgs).add_attributes(attrs))
+}
+
 pub fn receive_cw20(
     deps: DepsMut,
     env: Env,
-    _info: MessageInfo,
+    info: MessageInfo,
     cw20_msg: Cw20ReceiveMsg,
 ) -> Result<Response, ContractError> {
     let sender = deps.api.addr_validate(&cw20_msg.sender)?;
@@ -157,35 +340,116 @@ pub fn receive_cw20(
             minimum_receive,
             to,
             affiliates,
+            post_swap_action,
         } => {
             let receiver = to.and_then(|addr| deps.api.addr_validate(addr.as_str()).ok());
+            let offer_asset = Asset {
+                info: AssetInfo::Token {
+                    contract_addr: info.sender,
+                },
+                amount: cw20_msg.amount,
+            };
             execute_swap_operations(
                 deps,
                 env,
                 sender,
+                offer_asset,
                 operations,
                 minimum_receive,
                 receiver,
                 affiliates.unwrap_or_default(),
+                post_swap_action,
             )
         }
+        Cw20HookMsg::ExecuteMemo { memo } => {
+            let offer_asset = Asset {
+                info: AssetInfo::Token {
+                    contract_addr: info.sender,
+                },
+                amount: cw20_msg.amount,
+            };
+            execute_memo(deps, env, sender, offer_asset, memo)
+        }
     }
 }
 
 fn assert_minium_receive_and_transfer(
-    deps: Deps,
+    deps: DepsMut,
     env: Env,
+    info: MessageInfo,
     asset_info: AssetInfo,
     minium_receive: Uint128,
     receiver: Addr,
     affiliates: Vec<Affiliate>,
+    post_swap_action: Option<PostSwapAction>,
 ) -> Result<Response, ContractError> {
-    let mut curr_balance = asset_info.query_pool(&deps.querier, env.contract.address)?;
+    if env.contract.address != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    // only the balances above the snapshot taken at entry belong to the swap
+    let mut snapshots = BALANCE_SNAPSHOTS
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    let snapshot = snapshots.pop().unwrap_or(BalanceSnapshot {
+        refund_addr: receiver.clone(),
+        balances: vec![],
+    });
+    BALANCE_SNAPSHOTS.save(deps.storage, &snapshots)?;
+    let swap_balance = |asset_info: &AssetInfo| -> StdResult<Uint128> {
+        let balance = asset_info.query_pool(&deps.querier, env.contract.address.clone())?;
+        let snapshot_amount = snapshot
+            .balances
+            .iter()
+            .find(|balance| balance.info == *asset_info)
+            .map(|balance| balance.amount)
+            .unwrap_or_default();
+        Ok(balance.saturating_sub(snapshot_amount))
+    };
+
+    let curr_balance = swap_balance(&asset_info)?;
+
+    let mut msgs: Vec<CosmosMsg> = vec![];
+    let mut sub_msgs: Vec<SubMsg> = vec![];
+    let mut attrs: Vec<Attribute> = vec![];
+
+    // refund what the hops left of the other assets of the swap
+    for balance in snapshot.balances.iter() {
+        if balance.info == asset_info {
+            continue;
+        }
+        let residual = Asset {
+            info: balance.info.clone(),
+            amount: swap_balance(&balance.info)?,
+        };
+        if !residual.amount.is_zero() {
+            attrs.push(attr("refund_residual", residual.to_string()));
+            msgs.push(residual.into_msg(None, &deps.querier, snapshot.refund_addr.clone())?);
+        }
+    }
 
-    if curr_balance < minium_receive {
+    // Get the affiliate fee amounts by multiplying the output
+    // amount by the affiliate basis points fee divided by 10000
+    let affiliate_fees = affiliates
+        .into_iter()
+        .map(|affiliate| {
+            let amount =
+                curr_balance.multiply_ratio(affiliate.basis_points_fee, Uint128::new(10000));
+            (affiliate.address, amount)
+        })
+        .collect::<Vec<_>>();
+    let total_affiliate_fee_amount = affiliate_fees
+        .iter()
+        .try_fold(Uint128::zero(), |total, (_, amount)| {
+            total.checked_add(*amount)
+        })?;
+
+    // the minimum receive is what is left to the user after the fees
+    let receive_amount = curr_balance.saturating_sub(total_affiliate_fee_amount);
+    if receive_amount < minium_receive {
         return Err(ContractError::SwapAssertionFailure {
             minium_receive,
-            swap_amount: curr_balance,
+            swap_amount: receive_amount,
         });
     }
 
@@ -194,54 +458,127 @@ fn assert_minium_receive_and_transfer(
         amount: Uint128::zero(),
     };
 
-    // Create affiliate response and total affiliate fee amount
-    let mut msgs: Vec<CosmosMsg> = vec![];
-    let mut attrs: Vec<Attribute> = vec![];
-    let mut total_affiliate_fee_amount: Uint128 = Uint128::zero();
-
-    // If affiliates exist, create the affiliate fee messages and attributes and
-    // add them to the affiliate response, updating the total affiliate fee amount
-    for affiliate in affiliates.iter() {
-        // Get the affiliate fee amount by multiplying the min_asset
-        // amount by the affiliate basis points fee divided by 10000
-        let affiliate_fee_amount =
-            curr_balance.multiply_ratio(affiliate.basis_points_fee, Uint128::new(10000));
-
-        if affiliate_fee_amount > Uint128::zero() {
-            // Add the affiliate fee amount to the total affiliate fee amount
-            total_affiliate_fee_amount =
-                total_affiliate_fee_amount.checked_add(affiliate_fee_amount)?;
-
-            // Create the affiliate_fee_asset
-            asset.amount = affiliate_fee_amount;
-
-            // Create the affiliate fee message
-            msgs.push(asset.into_msg(None, &deps.querier, affiliate.address.clone())?);
-
-            // Add the affiliate attributes to the response
-            attrs.push(attr("affiliate_receiver", affiliate.address.as_str()));
-            attrs.push(attr("affiliate_amount", &affiliate_fee_amount.to_string()))
+    // pay the affiliate fees, or accrue them for the registered affiliates
+    for (affiliate, affiliate_fee_amount) in affiliate_fees {
+        if affiliate_fee_amount.is_zero() {
+            continue;
+        }
+        asset.amount = affiliate_fee_amount;
+
+        if AFFILIATES.has(deps.storage, &affiliate) {
+            let asset_key = asset.info.to_string();
+            AFFILIATE_FEES.update(
+                deps.storage,
+                (&affiliate, asset_key.as_str()),
+                |fee| -> StdResult<_> {
+                    let mut fee = fee.unwrap_or(Asset {
+                        info: asset.info.clone(),
+                        amount: Uint128::zero(),
+                    });
+                    fee.amount = fee.amount.checked_add(affiliate_fee_amount)?;
+                    Ok(fee)
+                },
+            )?;
+            AFFILIATE_FEE_TOTALS.update(deps.storage, &asset_key, |total| -> StdResult<_> {
+                Ok(total
+                    .unwrap_or_default()
+                    .checked_add(affiliate_fee_amount)?)
+            })?;
+            attrs.push(attr("affiliate_accrued", affiliate.as_str()));
+        } else {
+            msgs.push(asset.into_msg(None, &deps.querier, affiliate.clone())?);
+            attrs.push(attr("affiliate_receiver", affiliate.as_str()));
         }
+        attrs.push(attr("affiliate_amount", affiliate_fee_amount.to_string()));
     }
 
     // transfer to user
-    if total_affiliate_fee_amount < curr_balance {
-        curr_balance = curr_balance - total_affiliate_fee_amount;
-        asset.amount = curr_balance;
-        msgs.push(asset.into_msg(None, &deps.querier, receiver)?);
+    if !receive_amount.is_zero() {
+        asset.amount = receive_amount;
+        match post_swap_action {
+            Some(post_swap_action) => {
+                sub_msgs.push(SubMsg::reply_always(
+                    post_swap_action_msg(deps.as_ref(), &env, asset.clone(), post_swap_action)?,
+                    POST_SWAP_ACTION_REPLY_ID,
+                ));
+                let mut recoveries = POST_SWAP_ACTION_RECOVERIES
+                    .may_load(deps.storage)?
+                    .unwrap_or_default();
+                recoveries.push(MemoRecovery {
+                    asset,
+                    recovery_addr: receiver,
+                });
+                POST_SWAP_ACTION_RECOVERIES.save(deps.storage, &recoveries)?;
+            }
+            None => msgs.push(asset.into_msg(None, &deps.querier, receiver)?),
+        }
     }
 
-    Ok(Response::new().add_messages(msgs).add_attributes(attrs))
+    Ok(Response::new()
+        .add_messages(msgs)
+        .add_submessages(sub_msgs)
+        .add_attributes(attrs))
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    let (recoveries_item, action) = match msg.id {
+        MEMO_REPLY_ID => (MEMO_RECOVERIES, "refund_memo"),
+        POST_SWAP_ACTION_REPLY_ID => (POST_SWAP_ACTION_RECOVERIES, "recover_post_swap_action"),
+        _ => return Err(StdError::generic_err(format!("unknown reply id {}", msg.id)).into()),
+    };
+
+    let mut recoveries = recoveries_item.load(deps.storage)?;
+    let recovery = recoveries
+        .pop()
+        .ok_or_else(|| StdError::not_found("MemoRecovery"))?;
+    recoveries_item.save(deps.storage, &recoveries)?;
+
+    match msg.result {
+        SubMsgResult::Ok(_) => Ok(Response::new()),
+        // the sub message is reverted, so the asset is still held by the router
+        SubMsgResult::Err(err) => Ok(Response::new()
+            .add_message(recovery.asset.into_msg(
+                None,
+                &deps.querier,
+                recovery.recovery_addr.clone(),
+            )?)
+            .add_attributes(vec![
+                attr("action", action),
+                attr("recovery_addr", recovery.recovery_addr),
+                attr("error", err),
+            ])),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Config {} => to_json_binary(&query_config(deps)?),
         QueryMsg::SimulateSwapOperations {
             offer_amount,
             operations,
-        } => to_json_binary(&simulate_swap_operations(deps, offer_amount, operations)?),
+        } => to_json_binary(&simulate_swap_operations(
+            deps,
+            env,
+            offer_amount,
+            operations,
+        )?),
+        QueryMsg::SimulateSwapOperationsDetailed {
+            offer_amount,
+            operations,
+        } => to_json_binary(&simulate_swap_operations_detailed(
+            deps,
+            env,
+            offer_amount,
+            operations,
+        )?),
+        QueryMsg::AffiliateFees { address } => {
+            to_json_binary(&query_affiliate_fees(deps, address)?)
+        }
+        QueryMsg::Affiliates { start_after, limit } => {
+            to_json_binary(&query_affiliates(deps, start_after, limit)?)
+        }
     }
 }
 
@@ -251,16 +588,121 @@ pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
         factory_addr: deps.api.addr_humanize(&state.factory_addr)?,
         factory_addr_v2: deps.api.addr_humanize(&state.factory_addr_v2)?,
         oraiswap_v3: deps.api.addr_humanize(&state.oraiswap_v3)?,
+        converter: state
+            .converter
+            .map(|converter| deps.api.addr_humanize(&converter))
+            .transpose()?,
+        ics20_contract: state
+            .ics20_contract
+            .map(|ics20_contract| deps.api.addr_humanize(&ics20_contract))
+            .transpose()?,
+        max_affiliate_fee_bps: state.max_affiliate_fee_bps(),
     };
 
     Ok(resp)
 }
 
+pub fn query_affiliate_fees(deps: Deps, address: String) -> StdResult<AffiliateFeesResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let fees = AFFILIATE_FEES
+        .prefix(&address)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, fee)| fee))
+        .collect::<StdResult<Vec<Asset>>>()?;
+
+    Ok(AffiliateFeesResponse { fees })
+}
+
+// settings for pagination
+const MAX_LIMIT: u32 = 30;
+const DEFAULT_LIMIT: u32 = 10;
+
+pub fn query_affiliates(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<AffiliatesResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start_after = start_after
+        .map(|address| deps.api.addr_validate(&address))
+        .transpose()?;
+    let affiliates = AFFILIATES
+        .keys(
+            deps.storage,
+            start_after.as_ref().map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .collect::<StdResult<Vec<Addr>>>()?;
+
+    Ok(AffiliatesResponse { affiliates })
+}
+
 fn simulate_swap_operations(
     deps: Deps,
+    env: Env,
     offer_amount: Uint128,
     operations: Vec<SwapOperation>,
 ) -> StdResult<SimulateSwapOperationsResponse> {
+    let hops = simulate_hops(deps, &env, offer_amount, operations)?;
+
+    Ok(SimulateSwapOperationsResponse {
+        amount: hops.last().map(|hop| hop.return_amount).unwrap_or_default(),
+    })
+}
+
+fn simulate_swap_operations_detailed(
+    deps: Deps,
+    env: Env,
+    offer_amount: Uint128,
+    operations: Vec<SwapOperation>,
+) -> StdResult<SimulateSwapOperationsDetailedResponse> {
+    let hops = simulate_hops(deps, &env, offer_amount, operations)?;
+    let amount = hops.last().map(|hop| hop.return_amount).unwrap_or_default();
+
+    // spot price of the route is the product of the spot prices of every hop
+    let spot_price = hops.iter().try_fold(Decimal256::one(), |price, hop| {
+        price.checked_mul(hop.price_before)
+    })?;
+
+    Ok(SimulateSwapOperationsDetailedResponse {
+        amount,
+        price_impact: compute_price_impact(offer_amount, amount, spot_price)?,
+        hops,
+    })
+}
+
+/// price of the offer token in the ask token, from the pools of a pair
+fn pool_price(offer_pool: Uint128, ask_pool: Uint128) -> Decimal256 {
+    if offer_pool.is_zero() {
+        Decimal256::zero()
+    } else {
+        Decimal256::from_ratio(ask_pool, offer_pool)
+    }
+}
+
+/// price of the offer token in the ask token, from the sqrt price of a v3 pool in y per x
+fn sqrt_price_to_price(sqrt_price: SqrtPrice, x_to_y: bool) -> StdResult<Decimal256> {
+    let sqrt_price = Decimal256::from_atomics(sqrt_price.0, SQRT_PRICE_DECIMALS)
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
+    let price = sqrt_price.checked_mul(sqrt_price)?;
+    if x_to_y || price.is_zero() {
+        return Ok(price);
+    }
+
+    Decimal256::one()
+        .checked_div(price)
+        .map_err(|err| StdError::generic_err(err.to_string()))
+}
+
+/// simulate the operations one by one, the return amount of a hop is the offer amount of the next
+fn simulate_hops(
+    deps: Deps,
+    env: &Env,
+    offer_amount: Uint128,
+    operations: Vec<SwapOperation>,
+) -> StdResult<Vec<HopSimulation>> {
     let config: Config = CONFIG.load(deps.storage)?;
     let oraiswap_v3 = deps.api.addr_humanize(&config.oraiswap_v3)?;
     let factory_addr = deps.api.addr_humanize(&config.factory_addr)?;
@@ -271,87 +713,171 @@ fn simulate_swap_operations(
             ContractError::NoSwapOperation {}.to_string(),
         ));
     }
+    is_swap_by_amount_out(&operations)?;
 
+    let mut hops = Vec::with_capacity(operations_len);
     let mut offer_amount = offer_amount;
     for operation in operations.into_iter() {
         let pair_config = query_pair_config(&deps.querier, factory_addr.clone())
             .or_else(|_| query_pair_config(&deps.querier, factory_addr_v2.clone()))?;
         let oracle_contract = OracleContract(pair_config.oracle_addr);
-        match operation {
+        let hop = match operation {
             SwapOperation::OraiSwap {
                 offer_asset_info,
                 ask_asset_info,
             } => {
-                let pair_info = query_pair_info(
-                    &deps.querier,
-                    factory_addr.clone(),
-                    &[offer_asset_info.clone(), ask_asset_info.clone()],
-                )
-                .or_else(|_| -> StdResult<PairInfo> {
-                    query_pair_info(
-                        &deps.querier,
-                        factory_addr_v2.clone(),
-                        &[offer_asset_info.clone(), ask_asset_info.clone()],
-                    )
-                })?;
+                let asset_infos = [offer_asset_info.clone(), ask_asset_info.clone()];
+                let (pair_info, from_factory_v2) =
+                    match query_pair_info(&deps.querier, factory_addr.clone(), &asset_infos) {
+                        Ok(pair_info) => (pair_info, false),
+                        Err(_) => (
+                            query_pair_info(&deps.querier, factory_addr_v2.clone(), &asset_infos)?,
+                            true,
+                        ),
+                    };
 
                 let return_asset = Asset {
                     info: offer_asset_info.clone(),
                     amount: offer_amount,
                 };
 
+                let pools = query_pool(&deps.querier, pair_info.contract_addr.clone())?.assets;
+                let (offer_pool, ask_pool) = if pools[0].info.eq(&offer_asset_info) {
+                    (pools[0].amount, pools[1].amount)
+                } else {
+                    (pools[1].amount, pools[0].amount)
+                };
+
                 // Deduct tax before querying simulation, with native token only
-                offer_amount = offer_amount
-                    .checked_sub(return_asset.compute_tax(&oracle_contract, &deps.querier)?)?;
+                let offer_tax = return_asset.compute_tax_to(
+                    &oracle_contract,
+                    &deps.querier,
+                    &pair_info.contract_addr,
+                )?;
 
-                let mut res: SimulationResponse = deps.querier.query_wasm_smart(
-                    pair_info.contract_addr,
+                let pair_offer_amount = offer_amount.checked_sub(offer_tax)?;
+                let res: SimulationResponse = deps.querier.query_wasm_smart(
+                    pair_info.contract_addr.clone(),
                     &PairQueryMsg::Simulation {
                         offer_asset: Asset {
-                            info: offer_asset_info,
-                            amount: offer_amount,
+                            info: offer_asset_info.clone(),
+                            amount: pair_offer_amount,
                         },
                     },
                 )?;
 
                 let return_asset = Asset {
-                    info: ask_asset_info,
+                    info: ask_asset_info.clone(),
                     amount: res.return_amount,
                 };
 
                 // Deduct tax after querying simulation, with native token only
-                res.return_amount = res
-                    .return_amount
-                    .checked_sub(return_asset.compute_tax(&oracle_contract, &deps.querier)?)?;
+                // every hop is returned to the router before the final transfer
+                let return_tax = return_asset.compute_tax_to(
+                    &oracle_contract,
+                    &deps.querier,
+                    &env.contract.address,
+                )?;
 
-                offer_amount = res.return_amount;
+                HopSimulation {
+                    venue: SwapVenue::OraiSwap {
+                        pair_addr: pair_info.contract_addr,
+                        from_factory_v2,
+                    },
+                    offer_asset_info,
+                    ask_asset_info,
+                    offer_amount,
+                    return_amount: res.return_amount.checked_sub(return_tax)?,
+                    spread_amount: res.spread_amount,
+                    commission_amount: res.commission_amount,
+                    tax_amount: offer_tax.checked_add(return_tax)?,
+                    price_before: pool_price(offer_pool, ask_pool),
+                    // the commission stays in the pool
+                    price_after: pool_price(
+                        offer_pool.checked_add(pair_offer_amount)?,
+                        ask_pool.checked_sub(res.return_amount)?,
+                    ),
+                    ticks_crossed: None,
+                }
             }
-            SwapOperation::SwapV3 { pool_key, x_to_y } => {
-                // only support swap by_amount_in
+            SwapOperation::SwapV3 {
+                pool_key,
+                x_to_y,
+                sqrt_price_limit,
+                by_amount_in,
+            } => {
+                // swapping by amount out, offer_amount is the ask amount
+                let (offer_asset_info, ask_asset_info) =
+                    get_swap_v3_asset_info(deps.api, &pool_key, &x_to_y);
 
-                let sqrt_price_limit = if x_to_y {
-                    SqrtPrice::from_tick(MIN_TICK).unwrap()
-                } else {
-                    SqrtPrice::from_tick(MAX_TICK).unwrap()
-                };
+                let pool: Pool = deps.querier.query_wasm_smart(
+                    oraiswap_v3.to_string(),
+                    &SwapV3QueryMsg::Pool {
+                        token_0: pool_key.token_x.clone(),
+                        token_1: pool_key.token_y.clone(),
+                        fee_tier: pool_key.fee_tier,
+                    },
+                )?;
 
                 let res: QuoteResult = deps.querier.query_wasm_smart(
                     oraiswap_v3.to_string(),
                     &SwapV3QueryMsg::Quote {
-                        pool_key,
+                        pool_key: pool_key.clone(),
                         x_to_y,
                         amount: TokenAmount(offer_amount.into()),
-                        by_amount_in: true,
-                        sqrt_price_limit,
+                        by_amount_in,
+                        sqrt_price_limit: sqrt_price_limit
+                            .unwrap_or_else(|| default_sqrt_price_limit(x_to_y)),
                     },
                 )?;
 
-                offer_amount = Uint128::from(res.amount_out.0)
+                // the pool fee is charged on the input
+                let commission_amount = Uint128::from(res.amount_in.0)
+                    .multiply_ratio(pool_key.fee_tier.fee.0, PERCENTAGE_DENOMINATOR);
+
+                HopSimulation {
+                    venue: SwapVenue::SwapV3 { pool_key },
+                    offer_asset_info,
+                    ask_asset_info,
+                    // less than the offer amount when the swap stops at the limit
+                    offer_amount: Uint128::from(res.amount_in.0),
+                    return_amount: Uint128::from(res.amount_out.0),
+                    spread_amount: Uint128::zero(),
+                    commission_amount,
+                    tax_amount: Uint128::zero(),
+                    price_before: sqrt_price_to_price(pool.sqrt_price, x_to_y)?,
+                    price_after: sqrt_price_to_price(res.target_sqrt_price, x_to_y)?,
+                    ticks_crossed: Some(res.ticks.len() as u32),
+                }
             }
-        }
+            SwapOperation::Convert { from, to } => {
+                let converter = load_converter(deps, &config)?;
+                let conversion = converter.query_conversion(&deps.querier, &from, &to)?;
+                let return_amount = conversion.convert(offer_amount)?;
+                let price = Decimal256::from(conversion.price()?);
+
+                HopSimulation {
+                    venue: SwapVenue::Convert {
+                        converter: converter.addr(),
+                    },
+                    offer_asset_info: from,
+                    ask_asset_info: to,
+                    offer_amount,
+                    return_amount,
+                    spread_amount: Uint128::zero(),
+                    commission_amount: Uint128::zero(),
+                    tax_amount: Uint128::zero(),
+                    // conversions have a fixed price
+                    price_before: price,
+                    price_after: price,
+                    ticks_crossed: None,
+                }
+            }
+        };
+
+        offer_amount = hop.return_amount;
+        hops.push(hop);
     }
 
-    Ok(SimulateSwapOperationsResponse {
-        amount: offer_amount,
-    })
+    Ok(hops)
 }
@@ -1,8 +1,9 @@
 use std::collections::HashMap;
+use std::str::FromStr;
 
 use cosmwasm_std::{
-    coin, to_json_binary, Addr, Api, Coin, CosmosMsg, Decimal, Deps, DepsMut, Env, MessageInfo,
-    Response, StdError, StdResult, Uint128, WasmMsg,
+    coin, to_json_binary, Addr, Api, Binary, Coin, CosmosMsg, Decimal, Deps, DepsMut, Env, IbcMsg,
+    MessageInfo, Response, StdError, StdResult, SubMsg, Uint128, WasmMsg,
 };
 use oraiswap::error::ContractError;
 
@@ -10,15 +11,27 @@ use oraiswap_v3::token_amount::TokenAmount;
 use oraiswap_v3::{sqrt_price::SqrtPrice, PoolKey};
 use oraiswap_v3::{MAX_TICK, MIN_TICK};
 
-use crate::state::{Config, CONFIG};
+use crate::state::{
+    BalanceSnapshot, Config, MemoRecovery, BALANCE_SNAPSHOTS, CONFIG, MEMO_RECOVERIES,
+    MEMO_REPLY_ID,
+};
 
 use cw20::Cw20ExecuteMsg;
 use oraiswap::asset::{Asset, AssetInfo, PairInfo};
-use oraiswap::mixed_router::{Affiliate, ExecuteMsg, SwapOperation};
+use oraiswap::converter::ConverterController;
+use oraiswap::mixed_router::{
+    Affiliate, ExecuteMsg, Ics20ExecuteMsg, PostSwapAction, SwapOperation, SwapRoute,
+    TransferBackMsg,
+};
 use oraiswap::oracle::OracleContract;
 use oraiswap::pair::{ExecuteMsg as PairExecuteMsg, PairExecuteMsgCw20, QueryMsg as PairQueryMsg};
-use oraiswap::querier::{query_pair_config, query_pair_info, query_token_balance};
-use oraiswap_v3::msg::ExecuteMsg as OraiswapV3ExecuteMsg;
+use oraiswap::querier::{query_pair_config, query_pair_info};
+use oraiswap::universal_swap_memo::Memo;
+use oraiswap_v3::interface::QuoteResult;
+use oraiswap_v3::msg::{ExecuteMsg as OraiswapV3ExecuteMsg, QueryMsg as SwapV3QueryMsg};
+
+// timeout of an ibc transfer post swap action without one
+const DEFAULT_IBC_TIMEOUT_SECONDS: u64 = 3600;
 
 /// Execute swap operation
 /// swap all offer asset to ask asset
@@ -29,6 +42,7 @@ pub fn execute_swap_operation(
     operation: SwapOperation,
     to: Option<Addr>,
     sender: Addr,
+    amount: Option<Uint128>,
 ) -> Result<Response, ContractError> {
     if env.contract.address != info.sender {
         return Err(ContractError::Unauthorized {});
@@ -73,15 +87,9 @@ pub fn execute_swap_operation(
                 return Err(ContractError::PoolWhitelisted {});
             }
 
-            let amount = match offer_asset_info.clone() {
-                AssetInfo::NativeToken { denom } => {
-                    deps.querier
-                        .query_balance(env.contract.address, denom)?
-                        .amount
-                }
-                AssetInfo::Token { contract_addr } => {
-                    query_token_balance(&deps.querier, contract_addr, env.contract.address)?
-                }
+            let amount = match amount {
+                Some(amount) => amount,
+                None => query_swap_balance(deps.as_ref(), &env, &offer_asset_info)?,
             };
             let offer_asset: Asset = Asset {
                 info: offer_asset_info,
@@ -98,83 +106,120 @@ pub fn execute_swap_operation(
                 to,
             )?]
         }
-        SwapOperation::SwapV3 { pool_key, x_to_y } => {
-            // only support swap by amount in
+        SwapOperation::SwapV3 {
+            pool_key,
+            x_to_y,
+            sqrt_price_limit,
+            by_amount_in,
+        } => {
             let (offer_asset_info, _) = get_swap_v3_asset_info(deps.api, &pool_key, &x_to_y);
-
-            let mut msgs: Vec<CosmosMsg> = vec![];
-
-            let sqrt_price_limit = if x_to_y {
-                SqrtPrice::from_tick(MIN_TICK).unwrap()
-            } else {
-                SqrtPrice::from_tick(MAX_TICK).unwrap()
+            let balance = query_swap_balance(deps.as_ref(), &env, &offer_asset_info)?;
+
+            // swapping by amount out, amount is the ask amount and the whole balance can be spent
+            let (amount, max_amount_in) = match (by_amount_in, amount) {
+                (true, Some(amount)) => (amount, amount),
+                (true, None) => (balance, balance),
+                (false, Some(amount)) => (amount, balance),
+                (false, None) => {
+                    return Err(
+                        StdError::generic_err("swap by amount out requires the ask amount").into(),
+                    )
+                }
             };
 
-            match offer_asset_info.clone() {
-                AssetInfo::NativeToken { denom } => {
-                    let balance = deps
-                        .querier
-                        .query_balance(env.contract.address, denom.clone())?
-                        .amount;
-                    msgs.push(CosmosMsg::Wasm(WasmMsg::Execute {
-                        contract_addr: oraiswap_v3.to_string(),
-                        msg: to_json_binary(&OraiswapV3ExecuteMsg::Swap {
-                            pool_key,
+            // without a limit a swap by amount in spends the whole amount,
+            // otherwise the quote tells the amount spent before reaching the limit
+            let amount_in = match (by_amount_in, sqrt_price_limit) {
+                (true, None) => amount,
+                _ => {
+                    let res: QuoteResult = deps.querier.query_wasm_smart(
+                        oraiswap_v3.to_string(),
+                        &SwapV3QueryMsg::Quote {
+                            pool_key: pool_key.clone(),
                             x_to_y,
-                            amount: TokenAmount(balance.into()),
-                            by_amount_in: true,
-                            sqrt_price_limit,
-                        })
-                        .unwrap(),
-                        funds: vec![coin(balance.into(), denom)],
-                    }))
+                            amount: TokenAmount(amount.into()),
+                            by_amount_in,
+                            sqrt_price_limit: sqrt_price_limit
+                                .unwrap_or_else(|| default_sqrt_price_limit(x_to_y)),
+                        },
+                    )?;
+                    Uint128::from(res.amount_in.0)
                 }
+            };
+            if amount_in > max_amount_in {
+                return Err(StdError::generic_err(format!(
+                    "swap needs {} of the offer asset, only {} is available",
+                    amount_in, max_amount_in
+                ))
+                .into());
+            }
+
+            let swap_msg = to_json_binary(&OraiswapV3ExecuteMsg::Swap {
+                pool_key,
+                x_to_y,
+                amount: TokenAmount(if by_amount_in { amount_in } else { amount }.into()),
+                by_amount_in,
+                sqrt_price_limit: sqrt_price_limit
+                    .unwrap_or_else(|| default_sqrt_price_limit(x_to_y)),
+            })?;
+
+            let mut msgs: Vec<CosmosMsg> = vec![];
+            match offer_asset_info.clone() {
+                AssetInfo::NativeToken { denom } => msgs.push(CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: oraiswap_v3.to_string(),
+                    msg: swap_msg,
+                    funds: vec![coin(amount_in.into(), denom)],
+                })),
                 AssetInfo::Token { contract_addr } => {
-                    let balance = query_token_balance(
-                        &deps.querier,
-                        contract_addr.clone(),
-                        env.contract.address,
-                    )?;
                     // approve first
                     msgs.push(CosmosMsg::Wasm(WasmMsg::Execute {
                         contract_addr: contract_addr.to_string(),
                         msg: to_json_binary(&Cw20ExecuteMsg::IncreaseAllowance {
                             spender: oraiswap_v3.to_string(),
-                            amount: balance.clone(),
+                            amount: amount_in,
                             expires: None,
                         })?,
                         funds: vec![],
                     }));
                     msgs.push(CosmosMsg::Wasm(WasmMsg::Execute {
                         contract_addr: oraiswap_v3.to_string(),
-                        msg: to_json_binary(&OraiswapV3ExecuteMsg::Swap {
-                            pool_key,
-                            x_to_y,
-                            amount: TokenAmount(balance.into()),
-                            by_amount_in: true,
-                            sqrt_price_limit,
-                        })
-                        .unwrap(),
+                        msg: swap_msg,
                         funds: vec![],
                     }))
                 }
             };
 
+            // the input left by a partial fill at the limit or a swap by amount out stays in
+            // the router, it is refunded with the residuals of the swap
+
             msgs
         }
+        SwapOperation::Convert { from, to } => {
+            let converter = load_converter(deps.as_ref(), &config)?;
+            let conversion = converter.query_conversion(&deps.querier, &from, &to)?;
+            let amount = match amount {
+                Some(amount) => amount,
+                None => query_swap_balance(deps.as_ref(), &env, &from)?,
+            };
+
+            // the converter pays the router
+            vec![converter.convert_msg(&conversion, from, to, amount)?]
+        }
     };
 
     Ok(Response::new().add_messages(messages))
 }
 
 pub fn execute_swap_operations(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     sender: Addr,
+    offer_asset: Asset,
     operations: Vec<SwapOperation>,
     minimum_receive: Option<Uint128>,
     to: Option<Addr>,
     affiliates: Vec<Affiliate>,
+    post_swap_action: Option<PostSwapAction>,
 ) -> Result<Response, ContractError> {
     let operations_len = operations.len();
     if operations_len == 0 {
@@ -184,6 +229,38 @@ pub fn execute_swap_operations(
     // Assert the operations are properly set
     assert_operations(deps.api, &operations)?;
 
+    let max_bps = CONFIG.load(deps.storage)?.max_affiliate_fee_bps();
+    let total_bps = affiliates
+        .iter()
+        .try_fold(Uint128::zero(), |total, affiliate| {
+            total.checked_add(affiliate.basis_points_fee)
+        })?;
+    if total_bps > max_bps {
+        return Err(ContractError::AffiliateFeeExceeded { total_bps, max_bps });
+    }
+
+    // the ask amount of a swap by amount out is the minimum receive
+    let ask_amount = match is_swap_by_amount_out(&operations)? {
+        true => match minimum_receive {
+            Some(minimum_receive) if !minimum_receive.is_zero() => Some(minimum_receive),
+            _ => {
+                return Err(StdError::generic_err(
+                    "swap by amount out requires the minimum receive",
+                )
+                .into())
+            }
+        },
+        false => None,
+    };
+
+    push_balance_snapshot(
+        deps.branch(),
+        &env,
+        &offer_asset,
+        operations.iter(),
+        sender.clone(),
+    )?;
+
     let to = to.unwrap_or(sender.clone());
 
     let target_asset_info = operations.last().unwrap().get_target_asset_info(deps.api);
@@ -200,6 +277,7 @@ pub fn execute_swap_operations(
                     operation: op,
                     to: None,
                     sender: sender.clone(),
+                    amount: ask_amount,
                 })?,
             }))
         })
@@ -216,12 +294,286 @@ pub fn execute_swap_operations(
             minimum_receive,
             receiver: to,
             affiliates,
+            post_swap_action,
+        })?,
+    }));
+
+    Ok(Response::new().add_messages(messages))
+}
+
+/// Execute the user swap of a universal swap memo in a sub message,
+/// the offer is refunded to the recovery address in the reply when it fails
+pub fn execute_memo(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    offer_asset: Asset,
+    memo: Binary,
+) -> Result<Response, ContractError> {
+    let memo = Memo::decode_memo(memo)?;
+    let recovery_addr = deps.api.addr_validate(&memo.recovery_addr)?;
+
+    // an expired memo is not swapped
+    if memo.timeout_timestamp != 0 && env.block.time.nanos() > memo.timeout_timestamp {
+        return Ok(Response::new()
+            .add_message(offer_asset.into_msg(None, &deps.querier, recovery_addr)?)
+            .add_attributes(vec![("action", "execute_memo"), ("status", "timeout")]));
+    }
+
+    let user_swap = memo
+        .user_swap
+        .ok_or_else(|| StdError::generic_err("No swap messages"))?;
+    let memo_routes = match (
+        user_swap.swap_exact_asset_in,
+        user_swap.smart_swap_exact_asset_in,
+    ) {
+        (Some(swap), _) => vec![(offer_asset.amount, swap.operations)],
+        (None, Some(smart_swap)) => smart_swap
+            .routes
+            .into_iter()
+            .map(|route| Ok((Uint128::from_str(&route.offer_amount)?, route.operations)))
+            .collect::<StdResult<Vec<_>>>()?,
+        (None, None) => return Err(StdError::generic_err("No swap messages").into()),
+    };
+
+    let mut routes = Vec::with_capacity(memo_routes.len());
+    let mut ask_asset_info: Option<AssetInfo> = None;
+    let mut total_offer_amount = Uint128::zero();
+    for (offer_amount, memo_operations) in memo_routes {
+        let operations = memo_operations
+            .iter()
+            .map(|operation| SwapOperation::from_memo(deps.api, operation))
+            .collect::<StdResult<Vec<_>>>()?;
+        let (Some(first), Some(last)) = (operations.first(), operations.last()) else {
+            return Err(ContractError::NoSwapOperation {});
+        };
+        assert_operations(deps.api, &operations)?;
+
+        // every route spends the offer asset and ends with the same asset
+        let route_ask_asset_info = last.get_target_asset_info(deps.api);
+        if get_offer_asset_info(deps.api, first) != offer_asset.info
+            || ask_asset_info
+                .as_ref()
+                .is_some_and(|info| *info != route_ask_asset_info)
+        {
+            return Err(ContractError::AssetMismatch {});
+        }
+        ask_asset_info = Some(route_ask_asset_info);
+
+        total_offer_amount = total_offer_amount.checked_add(offer_amount)?;
+        routes.push(SwapRoute {
+            offer_amount,
+            operations,
+        });
+    }
+    if total_offer_amount != offer_asset.amount {
+        return Err(ContractError::InvalidSplitRoutes {});
+    }
+
+    let minimum_receive = if memo.minimum_receive.is_empty() {
+        Uint128::zero()
+    } else {
+        Uint128::from_str(&memo.minimum_receive)?
+    };
+
+    // without a post swap action the output goes to the recovery address
+    let post_swap_action = match memo.post_swap_action {
+        Some(action) => PostSwapAction::try_from(action)?,
+        None => PostSwapAction::Transfer {
+            to_address: recovery_addr.to_string(),
+        },
+    };
+
+    let mut recoveries = MEMO_RECOVERIES.may_load(deps.storage)?.unwrap_or_default();
+    recoveries.push(MemoRecovery {
+        asset: offer_asset,
+        recovery_addr: recovery_addr.clone(),
+    });
+    MEMO_RECOVERIES.save(deps.storage, &recoveries)?;
+
+    Ok(Response::new()
+        .add_submessage(SubMsg::reply_always(
+            WasmMsg::Execute {
+                contract_addr: env.contract.address.to_string(),
+                msg: to_json_binary(&ExecuteMsg::ExecuteMemoRoutes {
+                    routes,
+                    minimum_receive,
+                    post_swap_action,
+                    sender,
+                    recovery_addr,
+                })?,
+                funds: vec![],
+            },
+            MEMO_REPLY_ID,
+        ))
+        .add_attribute("action", "execute_memo"))
+}
+
+pub fn execute_memo_routes(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    routes: Vec<SwapRoute>,
+    minimum_receive: Uint128,
+    post_swap_action: PostSwapAction,
+    sender: Addr,
+    recovery_addr: Addr,
+) -> Result<Response, ContractError> {
+    if env.contract.address != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let (Some(first), Some(last)) = (
+        routes.first().and_then(|route| route.operations.first()),
+        routes.first().and_then(|route| route.operations.last()),
+    ) else {
+        return Err(ContractError::NoSwapOperation {});
+    };
+    let ask_asset_info = last.get_target_asset_info(deps.api);
+    let offer_asset = Asset {
+        info: get_offer_asset_info(deps.api, first),
+        amount: routes.iter().try_fold(Uint128::zero(), |total, route| {
+            total.checked_add(route.offer_amount)
+        })?,
+    };
+    push_balance_snapshot(
+        deps,
+        &env,
+        &offer_asset,
+        routes.iter().flat_map(|route| route.operations.iter()),
+        recovery_addr.clone(),
+    )?;
+
+    let mut messages = vec![];
+    for route in routes {
+        // only the first hop is limited to the offer amount of the route
+        for (index, operation) in route.operations.into_iter().enumerate() {
+            messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: env.contract.address.to_string(),
+                funds: vec![],
+                msg: to_json_binary(&ExecuteMsg::ExecuteSwapOperation {
+                    operation,
+                    to: None,
+                    sender: sender.clone(),
+                    amount: (index == 0).then_some(route.offer_amount),
+                })?,
+            }));
+        }
+    }
+
+    messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: env.contract.address.to_string(),
+        funds: vec![],
+        msg: to_json_binary(&ExecuteMsg::AssertMinimumReceiveAndTransfer {
+            asset_info: ask_asset_info,
+            minimum_receive,
+            // the output goes to the recovery address when the post swap action fails
+            receiver: recovery_addr,
+            affiliates: vec![],
+            post_swap_action: Some(post_swap_action),
         })?,
     }));
 
     Ok(Response::new().add_messages(messages))
 }
 
+/// message running the post swap action on asset
+pub fn post_swap_action_msg(
+    deps: Deps,
+    env: &Env,
+    asset: Asset,
+    post_swap_action: PostSwapAction,
+) -> StdResult<CosmosMsg> {
+    match post_swap_action {
+        PostSwapAction::Transfer { to_address } => {
+            asset.into_msg(None, &deps.querier, deps.api.addr_validate(&to_address)?)
+        }
+        PostSwapAction::ContractCall {
+            contract_address,
+            msg,
+        } => {
+            let contract_addr = deps.api.addr_validate(&contract_address)?;
+            Ok(match asset.info {
+                AssetInfo::NativeToken { denom } => WasmMsg::Execute {
+                    contract_addr: contract_addr.to_string(),
+                    msg,
+                    funds: vec![coin(asset.amount.u128(), denom)],
+                },
+                AssetInfo::Token {
+                    contract_addr: token_addr,
+                } => WasmMsg::Execute {
+                    contract_addr: token_addr.to_string(),
+                    msg: to_json_binary(&Cw20ExecuteMsg::Send {
+                        contract: contract_addr.to_string(),
+                        amount: asset.amount,
+                        msg,
+                    })?,
+                    funds: vec![],
+                },
+            }
+            .into())
+        }
+        PostSwapAction::IbcTransfer {
+            channel_id,
+            to_address,
+            timeout,
+        } => {
+            let AssetInfo::NativeToken { denom } = asset.info else {
+                return Err(StdError::generic_err(
+                    "ibc transfer only supports native tokens",
+                ));
+            };
+            Ok(IbcMsg::Transfer {
+                channel_id,
+                to_address,
+                amount: coin(asset.amount.u128(), denom),
+                timeout: env
+                    .block
+                    .time
+                    .plus_seconds(timeout.unwrap_or(DEFAULT_IBC_TIMEOUT_SECONDS))
+                    .into(),
+            }
+            .into())
+        }
+        PostSwapAction::IbcWasmTransfer {
+            local_channel_id,
+            remote_address,
+            remote_denom,
+            timeout,
+            memo,
+        } => {
+            let ics20_contract = match CONFIG.load(deps.storage)?.ics20_contract {
+                Some(ics20_contract) => deps.api.addr_humanize(&ics20_contract)?,
+                None => return Err(StdError::generic_err("ics20 contract is not set")),
+            };
+            let transfer_msg = TransferBackMsg {
+                local_channel_id,
+                remote_address,
+                remote_denom,
+                timeout,
+                memo,
+            };
+            Ok(match asset.info {
+                AssetInfo::NativeToken { denom } => WasmMsg::Execute {
+                    contract_addr: ics20_contract.to_string(),
+                    msg: to_json_binary(&Ics20ExecuteMsg::TransferToRemote(transfer_msg))?,
+                    funds: vec![coin(asset.amount.u128(), denom)],
+                },
+                AssetInfo::Token { contract_addr } => WasmMsg::Execute {
+                    contract_addr: contract_addr.to_string(),
+                    msg: to_json_binary(&Cw20ExecuteMsg::Send {
+                        contract: ics20_contract.to_string(),
+                        amount: asset.amount,
+                        msg: to_json_binary(&transfer_msg)?,
+                    })?,
+                    funds: vec![],
+                },
+            }
+            .into())
+        }
+    }
+}
+
 fn asset_into_swap_msg(
     deps: Deps,
     oracle_contract: &OracleContract,
@@ -240,9 +592,11 @@ fn asset_into_swap_msg(
             };
 
             // deduct tax first
-            let amount = offer_asset
-                .amount
-                .checked_sub(return_asset.compute_tax(oracle_contract, &deps.querier)?)?;
+            let amount = offer_asset.amount.checked_sub(return_asset.compute_tax_to(
+                oracle_contract,
+                &deps.querier,
+                &pair_contract,
+            )?)?;
 
             Ok(CosmosMsg::Wasm(WasmMsg::Execute {
                 contract_addr: pair_contract.to_string(),
@@ -277,15 +631,10 @@ fn asset_into_swap_msg(
 pub fn assert_operations(api: &dyn Api, operations: &[SwapOperation]) -> StdResult<()> {
     let mut ask_asset_map: HashMap<String, bool> = HashMap::new();
     for operation in operations.iter() {
-        let (offer_asset, ask_asset) = match operation {
-            SwapOperation::OraiSwap {
-                offer_asset_info,
-                ask_asset_info,
-            } => (offer_asset_info.clone(), ask_asset_info.clone()),
-            SwapOperation::SwapV3 { pool_key, x_to_y } => {
-                get_swap_v3_asset_info(api, pool_key, x_to_y)
-            }
-        };
+        let (offer_asset, ask_asset) = (
+            get_offer_asset_info(api, operation),
+            operation.get_target_asset_info(api),
+        );
 
         ask_asset_map.remove(&offer_asset.to_string());
         ask_asset_map.insert(ask_asset.to_string(), true);
@@ -300,6 +649,113 @@ pub fn assert_operations(api: &dyn Api, operations: &[SwapOperation]) -> StdResu
     Ok(())
 }
 
+/// snapshot the router balances of the route assets, less the offer received for the swap,
+/// hops only spend the balances above the snapshot and the residuals are refunded to refund_addr
+fn push_balance_snapshot<'a>(
+    deps: DepsMut,
+    env: &Env,
+    offer_asset: &Asset,
+    operations: impl Iterator<Item = &'a SwapOperation>,
+    refund_addr: Addr,
+) -> StdResult<()> {
+    let asset_infos = operations.flat_map(|operation| {
+        [
+            get_offer_asset_info(deps.api, operation),
+            operation.get_target_asset_info(deps.api),
+        ]
+    });
+
+    let mut balances: Vec<Asset> = vec![];
+    for info in std::iter::once(offer_asset.info.clone()).chain(asset_infos) {
+        if balances.iter().any(|balance| balance.info == info) {
+            continue;
+        }
+        let mut amount = info.query_pool(&deps.querier, env.contract.address.clone())?;
+        if info == offer_asset.info {
+            amount = amount.checked_sub(offer_asset.amount)?;
+        }
+        balances.push(Asset { info, amount });
+    }
+
+    let mut snapshots = BALANCE_SNAPSHOTS
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    snapshots.push(BalanceSnapshot {
+        refund_addr,
+        balances,
+    });
+    BALANCE_SNAPSHOTS.save(deps.storage, &snapshots)
+}
+
+/// balance of the router that belongs to the swap in progress
+pub fn query_swap_balance(deps: Deps, env: &Env, asset_info: &AssetInfo) -> StdResult<Uint128> {
+    let balance = asset_info.query_pool(&deps.querier, env.contract.address.clone())?;
+    let snapshots = BALANCE_SNAPSHOTS
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    let snapshot_amount = snapshots
+        .last()
+        .and_then(|snapshot| {
+            snapshot
+                .balances
+                .iter()
+                .find(|balance| balance.info == *asset_info)
+        })
+        .map(|balance| balance.amount)
+        .unwrap_or_default();
+
+    Ok(balance.saturating_sub(snapshot_amount))
+}
+
+/// a v3 swap by amount out can not be chained, so it must be the only operation
+pub fn is_swap_by_amount_out(operations: &[SwapOperation]) -> StdResult<bool> {
+    let by_amount_out = operations.iter().any(|operation| {
+        matches!(
+            operation,
+            SwapOperation::SwapV3 {
+                by_amount_in: false,
+                ..
+            }
+        )
+    });
+    if by_amount_out && operations.len() != 1 {
+        return Err(StdError::generic_err(
+            "swap by amount out is only supported on a single operation",
+        ));
+    }
+
+    Ok(by_amount_out)
+}
+
+/// the limit of a v3 swap without one, at the end of the price range
+pub fn default_sqrt_price_limit(x_to_y: bool) -> SqrtPrice {
+    if x_to_y {
+        SqrtPrice::from_tick(MIN_TICK).unwrap()
+    } else {
+        SqrtPrice::from_tick(MAX_TICK).unwrap()
+    }
+}
+
+pub fn get_offer_asset_info(api: &dyn Api, operation: &SwapOperation) -> AssetInfo {
+    match operation {
+        SwapOperation::OraiSwap {
+            offer_asset_info, ..
+        } => offer_asset_info.clone(),
+        SwapOperation::SwapV3 {
+            pool_key, x_to_y, ..
+        } => get_swap_v3_asset_info(api, pool_key, x_to_y).0,
+        SwapOperation::Convert { from, .. } => from.clone(),
+    }
+}
+
+/// the converter used by convert operations
+pub fn load_converter(deps: Deps, config: &Config) -> StdResult<ConverterController> {
+    match &config.converter {
+        Some(converter) => Ok(ConverterController(deps.api.addr_humanize(converter)?)),
+        None => Err(StdError::generic_err("converter is not set")),
+    }
+}
+
 pub fn get_swap_v3_asset_info(
     api: &dyn Api,
     pool_key: &PoolKey,
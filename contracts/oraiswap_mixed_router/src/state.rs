@@ -1,15 +1,68 @@
 use cosmwasm_schema::cw_serde;
 
-use cosmwasm_std::CanonicalAddr;
-use cw_storage_plus::Item;
+use cosmwasm_std::{Addr, CanonicalAddr, Uint128};
+use cw_storage_plus::{Item, Map};
+use oraiswap::asset::Asset;
 
 #[cw_serde]
 pub struct Config {
     pub factory_addr: CanonicalAddr,
     pub factory_addr_v2: CanonicalAddr,
     pub oraiswap_v3: CanonicalAddr,
+    pub converter: Option<CanonicalAddr>,
+    pub ics20_contract: Option<CanonicalAddr>,
+    /// no cap below the whole output when not set
+    pub max_affiliate_fee_bps: Option<Uint128>,
     pub owner: CanonicalAddr,
 }
 
+// the fees can not take more than the whole output
+const DEFAULT_MAX_AFFILIATE_FEE_BPS: u128 = 10000;
+
+impl Config {
+    pub fn max_affiliate_fee_bps(&self) -> Uint128 {
+        self.max_affiliate_fee_bps
+            .unwrap_or(Uint128::new(DEFAULT_MAX_AFFILIATE_FEE_BPS))
+    }
+}
+
 // put the length bytes at the first for compatibility with legacy singleton store
 pub const CONFIG: Item<Config> = Item::new("\u{0}\u{6}config");
+
+/// offer of a memo being executed, refunded to recovery_addr when the memo fails,
+/// or output of a post swap action, transferred to recovery_addr when the action fails
+#[cw_serde]
+pub struct MemoRecovery {
+    pub asset: Asset,
+    pub recovery_addr: Addr,
+}
+
+pub const MEMO_REPLY_ID: u64 = 1;
+
+// a stack, since a post swap action may execute another memo
+pub const MEMO_RECOVERIES: Item<Vec<MemoRecovery>> = Item::new("memo_recoveries");
+
+pub const POST_SWAP_ACTION_REPLY_ID: u64 = 2;
+
+pub const POST_SWAP_ACTION_RECOVERIES: Item<Vec<MemoRecovery>> =
+    Item::new("post_swap_action_recoveries");
+
+/// router balances before a swap, only the amounts above them belong to the swap
+#[cw_serde]
+pub struct BalanceSnapshot {
+    /// receives the residuals of the swap
+    pub refund_addr: Addr,
+    pub balances: Vec<Asset>,
+}
+
+// a stack, since a post swap action may execute another swap
+pub const BALANCE_SNAPSHOTS: Item<Vec<BalanceSnapshot>> = Item::new("balance_snapshots");
+
+// affiliates accruing their fees in the router
+pub const AFFILIATES: Map<&Addr, bool> = Map::new("affiliates");
+
+// fees accrued by affiliate and asset
+pub const AFFILIATE_FEES: Map<(&Addr, &str), Asset> = Map::new("affiliate_fees");
+
+// fees accrued by asset for all affiliates, kept out of the dust sweep
+pub const AFFILIATE_FEE_TOTALS: Map<&str, Uint128> = Map::new("affiliate_fee_totals");
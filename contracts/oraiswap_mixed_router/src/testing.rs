@@ -1,17 +1,21 @@
-use cosmwasm_std::{to_json_binary, Addr, Coin, Decimal, Uint128};
+use cosmwasm_std::{to_json_binary, Addr, Binary, Coin, Decimal, Uint128};
 use cw20::Cw20ExecuteMsg;
 use oraiswap::asset::{Asset, AssetInfo, ORAI_DENOM};
 use oraiswap::create_entry_points_testing;
 use oraiswap::mixed_router::{
-    Affiliate, ExecuteMsg, InstantiateMsg, QueryMsg, SimulateSwapOperationsResponse, SwapOperation,
+    Affiliate, AffiliateFeesResponse, AffiliatesResponse, Cw20HookMsg, ExecuteMsg, Ics20ExecuteMsg,
+    InstantiateMsg, PostSwapAction, QueryMsg, SimulateSwapOperationsDetailedResponse,
+    SimulateSwapOperationsResponse, SwapOperation, SwapRoute, SwapVenue, TransferBackMsg,
 };
 
 use oraiswap::testing::{MockApp, APP_OWNER, ATOM_DENOM};
+use oraiswap::universal_swap_memo::{memo, Memo};
 use oraiswap_v3::liquidity::Liquidity;
 use oraiswap_v3::msg::ExecuteMsg as OraiswapV3ExecuteMsg;
 use oraiswap_v3::percentage::Percentage;
 use oraiswap_v3::sqrt_price::{calculate_sqrt_price, SqrtPrice};
 use oraiswap_v3::{FeeTier, PoolKey, MAX_TICK, MIN_TICK};
+use prost::Message;
 #[test]
 fn simulate_swap_operations_test() {
     let mut app = MockApp::new(&[(
@@ -107,6 +111,7 @@ fn simulate_swap_operations_test() {
         factory_addr: app.factory_addr.clone(),
         factory_addr_v2: Addr::unchecked("addr0000_v2"),
         oraiswap_v3: Addr::unchecked("oraiswap_v3"),
+        converter: None,
     };
 
     let code_id = app.upload(Box::new(create_entry_points_testing!(crate)));
@@ -291,6 +296,7 @@ fn execute_swap_operations() {
         factory_addr: app.factory_addr.clone(),
         factory_addr_v2: Addr::unchecked("addr0000_v2"),
         oraiswap_v3: Addr::unchecked("oraiswap_v3"),
+        converter: None,
     };
 
     let code_id = app.upload(Box::new(create_entry_points_testing!(crate)));
@@ -305,6 +311,7 @@ fn execute_swap_operations() {
         minimum_receive: None,
         to: None,
         affiliates: None,
+        post_swap_action: None,
     };
 
     let error = app
@@ -337,6 +344,7 @@ fn execute_swap_operations() {
         minimum_receive: None,
         to: None,
         affiliates: None,
+        post_swap_action: None,
     };
 
     let res = app
@@ -669,6 +677,7 @@ fn simulate_mixed_swap() {
         factory_addr: app.factory_addr.clone(),
         factory_addr_v2: Addr::unchecked("addr0000_v2"),
         oraiswap_v3: app.v3_addr.clone(),
+        converter: None,
     };
 
     let code_id = app.upload(Box::new(create_entry_points_testing!(crate)));
@@ -690,15 +699,58 @@ fn simulate_mixed_swap() {
                 },
             },
             SwapOperation::SwapV3 {
-                pool_key,
+                pool_key: pool_key.clone(),
                 x_to_y: false,
+                sqrt_price_limit: None,
+                by_amount_in: true,
             },
         ],
     };
 
-    let res: SimulateSwapOperationsResponse = app.query(router_addr, &msg).unwrap();
+    let res: SimulateSwapOperationsResponse = app.query(router_addr.clone(), &msg).unwrap();
+
+    assert_eq!(res.amount, Uint128::new(99599));
+
+    // the detailed simulation breaks the same amount down by hop
+    let QueryMsg::SimulateSwapOperations {
+        offer_amount,
+        operations,
+    } = msg
+    else {
+        unreachable!()
+    };
+    let res: SimulateSwapOperationsDetailedResponse = app
+        .query(
+            router_addr,
+            &QueryMsg::SimulateSwapOperationsDetailed {
+                offer_amount,
+                operations,
+            },
+        )
+        .unwrap();
 
     assert_eq!(res.amount, Uint128::new(99599));
+    assert_eq!(res.hops.len(), 2);
+    assert_eq!(
+        res.hops[0].venue,
+        SwapVenue::OraiSwap {
+            pair_addr,
+            from_factory_v2: false,
+        }
+    );
+    assert_eq!(res.hops[0].offer_amount, offer_amount);
+    assert_eq!(res.hops[1].venue, SwapVenue::SwapV3 { pool_key });
+    assert_eq!(res.hops[1].offer_amount, res.hops[0].return_amount);
+    assert_eq!(res.hops[1].return_amount, res.amount);
+    assert!(!res.price_impact.is_zero());
+
+    // every hop moves its price against the offer
+    for hop in res.hops.iter() {
+        assert!(hop.price_after < hop.price_before);
+    }
+    assert!(!res.hops[0].commission_amount.is_zero());
+    assert_eq!(res.hops[0].ticks_crossed, None);
+    assert!(res.hops[1].ticks_crossed.is_some());
 }
 
 #[test]
@@ -815,6 +867,7 @@ fn execute_mixed_swap_operations() {
         factory_addr: app.factory_addr.clone(),
         factory_addr_v2: Addr::unchecked("addr0000_v2"),
         oraiswap_v3: app.v3_addr.clone(),
+        converter: None,
     };
 
     let code_id = app.upload(Box::new(create_entry_points_testing!(crate)));
@@ -838,11 +891,14 @@ fn execute_mixed_swap_operations() {
             SwapOperation::SwapV3 {
                 pool_key: pool_key.clone(),
                 x_to_y: true,
+                sqrt_price_limit: None,
+                by_amount_in: true,
             },
         ],
         minimum_receive: None,
         to: None,
         affiliates: None,
+        post_swap_action: None,
     };
 
     let error = app
@@ -873,11 +929,14 @@ fn execute_mixed_swap_operations() {
             SwapOperation::SwapV3 {
                 pool_key,
                 x_to_y: false,
+                sqrt_price_limit: None,
+                by_amount_in: true,
             },
         ],
         minimum_receive: None,
         to: None,
         affiliates: None,
+        post_swap_action: None,
     };
 
     let mut balances_before = app.query_token_balances("addr0000").unwrap();
@@ -1038,6 +1097,7 @@ fn test_affiliates() {
         factory_addr: app.factory_addr.clone(),
         factory_addr_v2: Addr::unchecked("addr0000_v2"),
         oraiswap_v3: app.v3_addr.clone(),
+        converter: None,
     };
 
     let code_id = app.upload(Box::new(create_entry_points_testing!(crate)));
@@ -1069,6 +1129,7 @@ fn test_affiliates() {
                 address: Addr::unchecked("affiliate_2"),
             },
         ]),
+        post_swap_action: None,
     };
 
     app.execute(
@@ -1135,6 +1196,7 @@ fn test_affiliates() {
                 address: Addr::unchecked("affiliate_2"),
             },
         ]),
+        post_swap_action: None,
     };
 
     app.execute(
@@ -1179,3 +1241,1269 @@ fn test_affiliates() {
         ]
     );
 }
+
+#[test]
+fn execute_memo() {
+    let mut app = MockApp::new(&[(
+        "addr0000",
+        &[Coin {
+            denom: ATOM_DENOM.to_string(),
+            amount: Uint128::from(10000000u128),
+        }],
+    )]);
+
+    app.set_oracle_contract(Box::new(create_entry_points_testing!(oraiswap_oracle)));
+
+    app.set_token_contract(Box::new(create_entry_points_testing!(oraiswap_token)));
+
+    app.set_factory_and_pair_contract(
+        Box::new(
+            create_entry_points_testing!(oraiswap_factory)
+                .with_reply_empty(oraiswap_factory::contract::reply),
+        ),
+        Box::new(
+            create_entry_points_testing!(oraiswap_pair)
+                .with_reply_empty(oraiswap_pair::contract::reply),
+        ),
+    );
+
+    let asset_addr = app.create_token("asset");
+
+    app.set_token_balances(&[("asset", &[("addr0000", 1000000u128)])])
+        .unwrap();
+
+    let asset_infos = [
+        AssetInfo::NativeToken {
+            denom: ATOM_DENOM.to_string(),
+        },
+        AssetInfo::Token {
+            contract_addr: asset_addr.clone(),
+        },
+    ];
+
+    // create pair and provide liquidity
+    let pair_addr = app.create_pair(asset_infos.clone()).unwrap();
+
+    app.execute(
+        Addr::unchecked("addr0000"),
+        asset_addr.clone(),
+        &cw20::Cw20ExecuteMsg::IncreaseAllowance {
+            spender: pair_addr.to_string(),
+            amount: Uint128::from(1000000u128),
+            expires: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    app.execute(
+        Addr::unchecked("addr0000"),
+        pair_addr.clone(),
+        &oraiswap::pair::ExecuteMsg::ProvideLiquidity {
+            assets: asset_infos.clone().map(|info| Asset {
+                info,
+                amount: Uint128::from(1000000u128),
+            }),
+            slippage_tolerance: None,
+            receiver: None,
+        },
+        &[Coin {
+            denom: ATOM_DENOM.to_string(),
+            amount: Uint128::from(1000000u128),
+        }],
+    )
+    .unwrap();
+
+    let msg = InstantiateMsg {
+        factory_addr: app.factory_addr.clone(),
+        factory_addr_v2: Addr::unchecked("addr0000_v2"),
+        oraiswap_v3: Addr::unchecked("oraiswap_v3"),
+        converter: None,
+    };
+
+    let code_id = app.upload(Box::new(
+        create_entry_points_testing!(crate).with_reply_empty(crate::contract::reply),
+    ));
+
+    let router_addr = app
+        .instantiate(code_id, Addr::unchecked("addr0000"), &msg, &[], "router")
+        .unwrap();
+
+    let res: SimulateSwapOperationsResponse = app
+        .query(
+            router_addr.clone(),
+            &QueryMsg::SimulateSwapOperations {
+                offer_amount: Uint128::from(1000u128),
+                operations: vec![SwapOperation::OraiSwap {
+                    offer_asset_info: asset_infos[0].clone(),
+                    ask_asset_info: asset_infos[1].clone(),
+                }],
+            },
+        )
+        .unwrap();
+
+    // swap atom to asset on the v2 pair, then transfer the output to addr0001
+    let build_memo = |minimum_receive: String, timeout_timestamp: u64| {
+        Binary::from(
+            Memo {
+                user_swap: Some(memo::UserSwap {
+                    swap_venue_name: "oraidex".to_string(),
+                    swap_exact_asset_in: Some(memo::SwapExactAssetIn {
+                        operations: vec![memo::SwapOperation {
+                            pool_id: pair_addr.to_string(),
+                            denom_in: ATOM_DENOM.to_string(),
+                            denom_out: asset_addr.to_string(),
+                        }],
+                    }),
+                    smart_swap_exact_asset_in: None,
+                }),
+                minimum_receive,
+                timeout_timestamp,
+                post_swap_action: Some(memo::PostAction {
+                    transfer_msg: Some(memo::Transfer {
+                        to_address: "addr0001".to_string(),
+                    }),
+                    ..Default::default()
+                }),
+                recovery_addr: "addr0002".to_string(),
+            }
+            .encode_to_vec(),
+        )
+    };
+    let funds = [Coin {
+        denom: ATOM_DENOM.to_string(),
+        amount: Uint128::from(1000u128),
+    }];
+
+    app.execute(
+        Addr::unchecked("addr0000"),
+        router_addr.clone(),
+        &ExecuteMsg::ExecuteMemo {
+            memo: build_memo(res.amount.to_string(), u64::MAX),
+        },
+        &funds,
+    )
+    .unwrap();
+
+    let balance: cw20::BalanceResponse = app
+        .query(
+            asset_addr.clone(),
+            &cw20::Cw20QueryMsg::Balance {
+                address: "addr0001".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(balance.balance, res.amount);
+
+    // the minimum receive is not met, the offer goes to the recovery address
+    app.execute(
+        Addr::unchecked("addr0000"),
+        router_addr.clone(),
+        &ExecuteMsg::ExecuteMemo {
+            memo: build_memo((res.amount + Uint128::one()).to_string(), u64::MAX),
+        },
+        &funds,
+    )
+    .unwrap();
+
+    let balance = app
+        .query_balance(Addr::unchecked("addr0002"), ATOM_DENOM.to_string())
+        .unwrap();
+    assert_eq!(balance, Uint128::from(1000u128));
+
+    // the memo is expired, the offer goes to the recovery address without swapping
+    app.execute(
+        Addr::unchecked("addr0000"),
+        router_addr.clone(),
+        &ExecuteMsg::ExecuteMemo {
+            memo: build_memo(res.amount.to_string(), 1),
+        },
+        &funds,
+    )
+    .unwrap();
+
+    let balance = app
+        .query_balance(Addr::unchecked("addr0002"), ATOM_DENOM.to_string())
+        .unwrap();
+    assert_eq!(balance, Uint128::from(2000u128));
+    assert!(app.query_all_balances(router_addr).unwrap().is_empty());
+}
+
+// stand-in of an ics20 contract, keeping the transferred tokens
+// and rejecting remote addresses starting with "fail"
+mod ics20_stand_in {
+    pub mod contract {
+        use cosmwasm_schema::cw_serde;
+        use cosmwasm_std::{
+            from_json, to_json_binary, Binary, Deps, DepsMut, Empty, Env, MessageInfo, Response,
+            StdError, StdResult,
+        };
+        use cw20::Cw20ReceiveMsg;
+        use oraiswap::mixed_router::TransferBackMsg;
+
+        #[cw_serde]
+        pub enum ExecuteMsg {
+            TransferToRemote(TransferBackMsg),
+            Receive(Cw20ReceiveMsg),
+        }
+
+        pub fn instantiate(
+            _deps: DepsMut,
+            _env: Env,
+            _info: MessageInfo,
+            _msg: Empty,
+        ) -> StdResult<Response> {
+            Ok(Response::default())
+        }
+
+        pub fn execute(
+            _deps: DepsMut,
+            _env: Env,
+            _info: MessageInfo,
+            msg: ExecuteMsg,
+        ) -> StdResult<Response> {
+            let transfer_msg = match msg {
+                ExecuteMsg::TransferToRemote(transfer_msg) => transfer_msg,
+                ExecuteMsg::Receive(msg) => from_json(&msg.msg)?,
+            };
+            if transfer_msg.remote_address.starts_with("fail") {
+                return Err(StdError::generic_err("transfer failed"));
+            }
+            Ok(Response::new().add_attribute("remote_address", transfer_msg.remote_address))
+        }
+
+        pub fn query(_deps: Deps, _env: Env, _msg: Empty) -> StdResult<Binary> {
+            to_json_binary(&Empty {})
+        }
+    }
+}
+
+#[test]
+fn execute_swap_operations_with_post_swap_action() {
+    let mut app = MockApp::new(&[(
+        "addr0000",
+        &[Coin {
+            denom: ATOM_DENOM.to_string(),
+            amount: Uint128::from(10000000u128),
+        }],
+    )]);
+
+    app.set_oracle_contract(Box::new(create_entry_points_testing!(oraiswap_oracle)));
+
+    app.set_token_contract(Box::new(create_entry_points_testing!(oraiswap_token)));
+
+    app.set_factory_and_pair_contract(
+        Box::new(
+            create_entry_points_testing!(oraiswap_factory)
+                .with_reply_empty(oraiswap_factory::contract::reply),
+        ),
+        Box::new(
+            create_entry_points_testing!(oraiswap_pair)
+                .with_reply_empty(oraiswap_pair::contract::reply),
+        ),
+    );
+
+    let asset_addr = app.create_token("asset");
+
+    app.set_token_balances(&[("asset", &[("addr0000", 2000000u128)])])
+        .unwrap();
+
+    let asset_infos = [
+        AssetInfo::NativeToken {
+            denom: ATOM_DENOM.to_string(),
+        },
+        AssetInfo::Token {
+            contract_addr: asset_addr.clone(),
+        },
+    ];
+
+    // create pair and provide liquidity
+    let pair_addr = app.create_pair(asset_infos.clone()).unwrap();
+
+    app.execute(
+        Addr::unchecked("addr0000"),
+        asset_addr.clone(),
+        &cw20::Cw20ExecuteMsg::IncreaseAllowance {
+            spender: pair_addr.to_string(),
+            amount: Uint128::from(1000000u128),
+            expires: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    app.execute(
+        Addr::unchecked("addr0000"),
+        pair_addr.clone(),
+        &oraiswap::pair::ExecuteMsg::ProvideLiquidity {
+            assets: asset_infos.clone().map(|info| Asset {
+                info,
+                amount: Uint128::from(1000000u128),
+            }),
+            slippage_tolerance: None,
+            receiver: None,
+        },
+        &[Coin {
+            denom: ATOM_DENOM.to_string(),
+            amount: Uint128::from(1000000u128),
+        }],
+    )
+    .unwrap();
+
+    let msg = InstantiateMsg {
+        factory_addr: app.factory_addr.clone(),
+        factory_addr_v2: Addr::unchecked("addr0000_v2"),
+        oraiswap_v3: Addr::unchecked("oraiswap_v3"),
+        converter: None,
+    };
+
+    let code_id = app.upload(Box::new(
+        create_entry_points_testing!(crate).with_reply_empty(crate::contract::reply),
+    ));
+
+    let router_addr = app
+        .instantiate(code_id, Addr::unchecked("addr0000"), &msg, &[], "router")
+        .unwrap();
+
+    let ics20_code_id = app.upload(Box::new(create_entry_points_testing!(ics20_stand_in)));
+    let ics20_addr = app
+        .instantiate(
+            ics20_code_id,
+            Addr::unchecked("addr0000"),
+            &cosmwasm_std::Empty {},
+            &[],
+            "ics20",
+        )
+        .unwrap();
+
+    app.execute(
+        Addr::unchecked("addr0000"),
+        router_addr.clone(),
+        &ExecuteMsg::UpdateConfig {
+            factory_addr: None,
+            factory_addr_v2: None,
+            oraiswap_v3: None,
+            converter: None,
+            ics20_contract: Some(ics20_addr.to_string()),
+            max_affiliate_fee_bps: None,
+            owner: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    let res: SimulateSwapOperationsResponse = app
+        .query(
+            router_addr.clone(),
+            &QueryMsg::SimulateSwapOperations {
+                offer_amount: Uint128::from(1000u128),
+                operations: vec![SwapOperation::OraiSwap {
+                    offer_asset_info: asset_infos[0].clone(),
+                    ask_asset_info: asset_infos[1].clone(),
+                }],
+            },
+        )
+        .unwrap();
+
+    // swap atom to asset, then send the asset to the ics20 contract
+    let ibc_wasm_transfer = |remote_address: &str| ExecuteMsg::ExecuteSwapOperations {
+        operations: vec![SwapOperation::OraiSwap {
+            offer_asset_info: asset_infos[0].clone(),
+            ask_asset_info: asset_infos[1].clone(),
+        }],
+        minimum_receive: None,
+        to: Some(Addr::unchecked("addr0001")),
+        affiliates: None,
+        post_swap_action: Some(PostSwapAction::IbcWasmTransfer {
+            local_channel_id: "channel-0".to_string(),
+            remote_address: remote_address.to_string(),
+            remote_denom: "uatom".to_string(),
+            timeout: None,
+            memo: None,
+        }),
+    };
+    let funds = [Coin {
+        denom: ATOM_DENOM.to_string(),
+        amount: Uint128::from(1000u128),
+    }];
+
+    app.execute(
+        Addr::unchecked("addr0000"),
+        router_addr.clone(),
+        &ibc_wasm_transfer("cosmos1remote"),
+        &funds,
+    )
+    .unwrap();
+
+    let balance: cw20::BalanceResponse = app
+        .query(
+            asset_addr.clone(),
+            &cw20::Cw20QueryMsg::Balance {
+                address: ics20_addr.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(balance.balance, res.amount);
+
+    // the transfer fails, the output goes to the receiver
+    let res: SimulateSwapOperationsResponse = app
+        .query(
+            router_addr.clone(),
+            &QueryMsg::SimulateSwapOperations {
+                offer_amount: Uint128::from(1000u128),
+                operations: vec![SwapOperation::OraiSwap {
+                    offer_asset_info: asset_infos[0].clone(),
+                    ask_asset_info: asset_infos[1].clone(),
+                }],
+            },
+        )
+        .unwrap();
+    app.execute(
+        Addr::unchecked("addr0000"),
+        router_addr.clone(),
+        &ibc_wasm_transfer("fail"),
+        &funds,
+    )
+    .unwrap();
+
+    let balance: cw20::BalanceResponse = app
+        .query(
+            asset_addr.clone(),
+            &cw20::Cw20QueryMsg::Balance {
+                address: "addr0001".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(balance.balance, res.amount);
+
+    // swap asset to atom, then call the ics20 contract with the atom as funds
+    let contract_call = |remote_address: &str| {
+        to_json_binary(&Cw20HookMsg::ExecuteSwapOperations {
+            operations: vec![SwapOperation::OraiSwap {
+                offer_asset_info: asset_infos[1].clone(),
+                ask_asset_info: asset_infos[0].clone(),
+            }],
+            minimum_receive: None,
+            to: Some("addr0001".to_string()),
+            affiliates: None,
+            post_swap_action: Some(PostSwapAction::ContractCall {
+                contract_address: ics20_addr.to_string(),
+                msg: to_json_binary(&Ics20ExecuteMsg::TransferToRemote(TransferBackMsg {
+                    local_channel_id: "channel-0".to_string(),
+                    remote_address: remote_address.to_string(),
+                    remote_denom: "uatom".to_string(),
+                    timeout: None,
+                    memo: None,
+                }))
+                .unwrap(),
+            }),
+        })
+        .unwrap()
+    };
+
+    let res: SimulateSwapOperationsResponse = app
+        .query(
+            router_addr.clone(),
+            &QueryMsg::SimulateSwapOperations {
+                offer_amount: Uint128::from(1000u128),
+                operations: vec![SwapOperation::OraiSwap {
+                    offer_asset_info: asset_infos[1].clone(),
+                    ask_asset_info: asset_infos[0].clone(),
+                }],
+            },
+        )
+        .unwrap();
+    app.execute(
+        Addr::unchecked("addr0000"),
+        asset_addr.clone(),
+        &Cw20ExecuteMsg::Send {
+            contract: router_addr.to_string(),
+            amount: Uint128::from(1000u128),
+            msg: contract_call("cosmos1remote"),
+        },
+        &[],
+    )
+    .unwrap();
+
+    let balance = app
+        .query_balance(ics20_addr.clone(), ATOM_DENOM.to_string())
+        .unwrap();
+    assert_eq!(balance, res.amount);
+
+    // the call fails, the output goes to the receiver
+    let res: SimulateSwapOperationsResponse = app
+        .query(
+            router_addr.clone(),
+            &QueryMsg::SimulateSwapOperations {
+                offer_amount: Uint128::from(1000u128),
+                operations: vec![SwapOperation::OraiSwap {
+                    offer_asset_info: asset_infos[1].clone(),
+                    ask_asset_info: asset_infos[0].clone(),
+                }],
+            },
+        )
+        .unwrap();
+    app.execute(
+        Addr::unchecked("addr0000"),
+        asset_addr.clone(),
+        &Cw20ExecuteMsg::Send {
+            contract: router_addr.to_string(),
+            amount: Uint128::from(1000u128),
+            msg: contract_call("fail"),
+        },
+        &[],
+    )
+    .unwrap();
+
+    let balance = app
+        .query_balance(Addr::unchecked("addr0001"), ATOM_DENOM.to_string())
+        .unwrap();
+    assert_eq!(balance, res.amount);
+    assert!(app.query_all_balances(router_addr).unwrap().is_empty());
+}
+
+#[test]
+fn execute_swap_v3_with_price_limit_and_by_amount_out() {
+    let mut app = MockApp::new(&[]);
+
+    app.set_token_contract(Box::new(create_entry_points_testing!(oraiswap_token)));
+
+    let token_x_name = "tokenx";
+    let token_y_name = "tokeny";
+    let token_x = app.create_token(token_x_name);
+    let token_y = app.create_token(token_y_name);
+
+    app.set_token_balances(&[("tokenx", &[("addr0000", 1000000000000u128)])])
+        .unwrap();
+    app.set_token_balances(&[("tokeny", &[("addr0000", 1000000000000u128)])])
+        .unwrap();
+
+    let pool_key = init_v3(
+        &mut app,
+        token_x.clone(),
+        token_x_name,
+        token_y.clone(),
+        token_y_name,
+    );
+
+    app.set_oracle_contract(Box::new(create_entry_points_testing!(oraiswap_oracle)));
+
+    app.set_factory_and_pair_contract(
+        Box::new(
+            create_entry_points_testing!(oraiswap_factory)
+                .with_reply_empty(oraiswap_factory::contract::reply),
+        ),
+        Box::new(
+            create_entry_points_testing!(oraiswap_pair)
+                .with_reply_empty(oraiswap_pair::contract::reply),
+        ),
+    );
+
+    let msg = InstantiateMsg {
+        factory_addr: app.factory_addr.clone(),
+        factory_addr_v2: Addr::unchecked("addr0000_v2"),
+        oraiswap_v3: app.v3_addr.clone(),
+        converter: None,
+    };
+
+    let code_id = app.upload(Box::new(create_entry_points_testing!(crate)));
+
+    let router_addr = app
+        .instantiate(code_id, Addr::unchecked("addr0000"), &msg, &[], "router")
+        .unwrap();
+
+    let offer_token = Addr::unchecked(pool_key.token_x.clone());
+    let ask_token = Addr::unchecked(pool_key.token_y.clone());
+    let query_balance = |app: &MockApp, token: &Addr| -> Uint128 {
+        let res: cw20::BalanceResponse = app
+            .query(
+                token.clone(),
+                &cw20::Cw20QueryMsg::Balance {
+                    address: "addr0000".to_string(),
+                },
+            )
+            .unwrap();
+        res.balance
+    };
+    let swap = |app: &mut MockApp, operation: SwapOperation, minimum_receive: Option<Uint128>| {
+        app.execute(
+            Addr::unchecked("addr0000"),
+            offer_token.clone(),
+            &Cw20ExecuteMsg::Send {
+                contract: router_addr.to_string(),
+                amount: Uint128::new(1000000000),
+                msg: to_json_binary(&Cw20HookMsg::ExecuteSwapOperations {
+                    operations: vec![operation],
+                    minimum_receive,
+                    to: None,
+                    affiliates: None,
+                    post_swap_action: None,
+                })
+                .unwrap(),
+            },
+            &[],
+        )
+    };
+
+    // swap by amount out, the offer amount of the simulation is the ask amount
+    let by_amount_out = SwapOperation::SwapV3 {
+        pool_key: pool_key.clone(),
+        x_to_y: true,
+        sqrt_price_limit: None,
+        by_amount_in: false,
+    };
+    let res: SimulateSwapOperationsDetailedResponse = app
+        .query(
+            router_addr.clone(),
+            &QueryMsg::SimulateSwapOperationsDetailed {
+                offer_amount: Uint128::new(1000),
+                operations: vec![by_amount_out.clone()],
+            },
+        )
+        .unwrap();
+    assert_eq!(res.amount, Uint128::new(1000));
+    assert!(res.hops[0].offer_amount > res.amount);
+
+    let error = swap(&mut app, by_amount_out.clone(), None).unwrap_err();
+    assert!(error
+        .root_cause()
+        .to_string()
+        .contains("swap by amount out requires the minimum receive"));
+
+    let (offer_before, ask_before) = (
+        query_balance(&app, &offer_token),
+        query_balance(&app, &ask_token),
+    );
+    swap(&mut app, by_amount_out, Some(Uint128::new(1000))).unwrap();
+
+    // only the amount in of the quote is spent, the rest is refunded
+    assert_eq!(
+        query_balance(&app, &offer_token),
+        offer_before - res.hops[0].offer_amount
+    );
+    assert_eq!(query_balance(&app, &ask_token), ask_before + res.amount);
+
+    // the swap stops at the limit before spending the whole offer
+    let limited = SwapOperation::SwapV3 {
+        pool_key: pool_key.clone(),
+        x_to_y: true,
+        sqrt_price_limit: Some(SqrtPrice::from_tick(-5).unwrap()),
+        by_amount_in: true,
+    };
+    let res: SimulateSwapOperationsDetailedResponse = app
+        .query(
+            router_addr.clone(),
+            &QueryMsg::SimulateSwapOperationsDetailed {
+                offer_amount: Uint128::new(1000000000),
+                operations: vec![limited.clone()],
+            },
+        )
+        .unwrap();
+    assert!(res.hops[0].offer_amount < Uint128::new(1000000000));
+
+    let (offer_before, ask_before) = (
+        query_balance(&app, &offer_token),
+        query_balance(&app, &ask_token),
+    );
+    swap(&mut app, limited, None).unwrap();
+
+    assert_eq!(
+        query_balance(&app, &offer_token),
+        offer_before - res.hops[0].offer_amount
+    );
+    assert_eq!(query_balance(&app, &ask_token), ask_before + res.amount);
+
+    // swapping by amount out can not be chained
+    let res: Result<SimulateSwapOperationsResponse, _> = app.query(
+        router_addr.clone(),
+        &QueryMsg::SimulateSwapOperations {
+            offer_amount: Uint128::new(1000),
+            operations: vec![
+                SwapOperation::SwapV3 {
+                    pool_key: pool_key.clone(),
+                    x_to_y: true,
+                    sqrt_price_limit: None,
+                    by_amount_in: false,
+                },
+                SwapOperation::SwapV3 {
+                    pool_key,
+                    x_to_y: false,
+                    sqrt_price_limit: None,
+                    by_amount_in: true,
+                },
+            ],
+        },
+    );
+    assert!(res
+        .unwrap_err()
+        .to_string()
+        .contains("swap by amount out is only supported on a single operation"));
+}
+
+#[test]
+fn execute_memo_routes_refunds_v3_partial_fill_to_recovery_addr() {
+    let mut app = MockApp::new(&[]);
+
+    app.set_token_contract(Box::new(create_entry_points_testing!(oraiswap_token)));
+
+    let token_x_name = "tokenx";
+    let token_y_name = "tokeny";
+    let token_x = app.create_token(token_x_name);
+    let token_y = app.create_token(token_y_name);
+
+    app.set_token_balances(&[("tokenx", &[("addr0000", 1000000000000u128)])])
+        .unwrap();
+    app.set_token_balances(&[("tokeny", &[("addr0000", 1000000000000u128)])])
+        .unwrap();
+
+    let pool_key = init_v3(
+        &mut app,
+        token_x.clone(),
+        token_x_name,
+        token_y.clone(),
+        token_y_name,
+    );
+
+    app.set_oracle_contract(Box::new(create_entry_points_testing!(oraiswap_oracle)));
+
+    app.set_factory_and_pair_contract(
+        Box::new(
+            create_entry_points_testing!(oraiswap_factory)
+                .with_reply_empty(oraiswap_factory::contract::reply),
+        ),
+        Box::new(
+            create_entry_points_testing!(oraiswap_pair)
+                .with_reply_empty(oraiswap_pair::contract::reply),
+        ),
+    );
+
+    let msg = InstantiateMsg {
+        factory_addr: app.factory_addr.clone(),
+        factory_addr_v2: Addr::unchecked("addr0000_v2"),
+        oraiswap_v3: app.v3_addr.clone(),
+        converter: None,
+    };
+
+    let code_id = app.upload(Box::new(
+        create_entry_points_testing!(crate).with_reply_empty(crate::contract::reply),
+    ));
+
+    let router_addr = app
+        .instantiate(code_id, Addr::unchecked("addr0000"), &msg, &[], "router")
+        .unwrap();
+
+    let offer_token = Addr::unchecked(pool_key.token_x.clone());
+    let ask_token = Addr::unchecked(pool_key.token_y.clone());
+    let query_balance = |app: &MockApp, token: &Addr, address: &str| -> Uint128 {
+        let res: cw20::BalanceResponse = app
+            .query(
+                token.clone(),
+                &cw20::Cw20QueryMsg::Balance {
+                    address: address.to_string(),
+                },
+            )
+            .unwrap();
+        res.balance
+    };
+
+    // the swap stops at the limit before spending the whole offer
+    let offer_amount = Uint128::new(1000000000);
+    let limited = SwapOperation::SwapV3 {
+        pool_key: pool_key.clone(),
+        x_to_y: true,
+        sqrt_price_limit: Some(SqrtPrice::from_tick(-5).unwrap()),
+        by_amount_in: true,
+    };
+    let res: SimulateSwapOperationsDetailedResponse = app
+        .query(
+            router_addr.clone(),
+            &QueryMsg::SimulateSwapOperationsDetailed {
+                offer_amount,
+                operations: vec![limited.clone()],
+            },
+        )
+        .unwrap();
+    assert!(res.hops[0].offer_amount < offer_amount);
+
+    // memo operations carry no price limit, so the routes are run the way
+    // ExecuteMemo dispatches them once the relayer has delivered the offer
+    app.execute(
+        Addr::unchecked("addr0000"),
+        offer_token.clone(),
+        &Cw20ExecuteMsg::Transfer {
+            recipient: router_addr.to_string(),
+            amount: offer_amount,
+        },
+        &[],
+    )
+    .unwrap();
+    app.execute(
+        router_addr.clone(),
+        router_addr.clone(),
+        &ExecuteMsg::ExecuteMemoRoutes {
+            routes: vec![SwapRoute {
+                offer_amount,
+                operations: vec![limited],
+            }],
+            minimum_receive: Uint128::zero(),
+            post_swap_action: PostSwapAction::Transfer {
+                to_address: "addr0001".to_string(),
+            },
+            sender: Addr::unchecked("relayer"),
+            recovery_addr: Addr::unchecked("addr0002"),
+        },
+        &[],
+    )
+    .unwrap();
+
+    // the unspent offer goes to the recovery address, not to the relayer
+    assert_eq!(
+        query_balance(&app, &offer_token, "addr0002"),
+        offer_amount - res.hops[0].offer_amount
+    );
+    assert_eq!(
+        query_balance(&app, &offer_token, "relayer"),
+        Uint128::zero()
+    );
+    assert_eq!(query_balance(&app, &ask_token, "addr0001"), res.amount);
+    assert_eq!(
+        query_balance(&app, &offer_token, router_addr.as_str()),
+        Uint128::zero()
+    );
+}
+
+#[test]
+fn execute_swap_operations_keeps_orphaned_balances() {
+    let mut app = MockApp::new(&[(
+        "addr0000",
+        &[Coin {
+            denom: ATOM_DENOM.to_string(),
+            amount: Uint128::from(10000000u128),
+        }],
+    )]);
+
+    app.set_oracle_contract(Box::new(create_entry_points_testing!(oraiswap_oracle)));
+
+    app.set_token_contract(Box::new(create_entry_points_testing!(oraiswap_token)));
+
+    app.set_factory_and_pair_contract(
+        Box::new(
+            create_entry_points_testing!(oraiswap_factory)
+                .with_reply_empty(oraiswap_factory::contract::reply),
+        ),
+        Box::new(
+            create_entry_points_testing!(oraiswap_pair)
+                .with_reply_empty(oraiswap_pair::contract::reply),
+        ),
+    );
+
+    let asset_addr = app.create_token("asset");
+
+    app.set_token_balances(&[("asset", &[("addr0000", 1000000u128)])])
+        .unwrap();
+
+    let asset_infos = [
+        AssetInfo::NativeToken {
+            denom: ATOM_DENOM.to_string(),
+        },
+        AssetInfo::Token {
+            contract_addr: asset_addr.clone(),
+        },
+    ];
+
+    // create pair and provide liquidity
+    let pair_addr = app.create_pair(asset_infos.clone()).unwrap();
+
+    app.execute(
+        Addr::unchecked("addr0000"),
+        asset_addr.clone(),
+        &cw20::Cw20ExecuteMsg::IncreaseAllowance {
+            spender: pair_addr.to_string(),
+            amount: Uint128::from(1000000u128),
+            expires: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    app.execute(
+        Addr::unchecked("addr0000"),
+        pair_addr.clone(),
+        &oraiswap::pair::ExecuteMsg::ProvideLiquidity {
+            assets: asset_infos.clone().map(|info| Asset {
+                info,
+                amount: Uint128::from(1000000u128),
+            }),
+            slippage_tolerance: None,
+            receiver: None,
+        },
+        &[Coin {
+            denom: ATOM_DENOM.to_string(),
+            amount: Uint128::from(1000000u128),
+        }],
+    )
+    .unwrap();
+
+    let msg = InstantiateMsg {
+        factory_addr: app.factory_addr.clone(),
+        factory_addr_v2: Addr::unchecked("addr0000_v2"),
+        oraiswap_v3: Addr::unchecked("oraiswap_v3"),
+        converter: None,
+    };
+
+    let code_id = app.upload(Box::new(create_entry_points_testing!(crate)));
+
+    let router_addr = app
+        .instantiate(code_id, Addr::unchecked("addr0000"), &msg, &[], "router")
+        .unwrap();
+
+    // balances left in the router by past swaps
+    app.set_balances(&[(ATOM_DENOM, &[(router_addr.as_str(), 500u128)])]);
+    app.set_token_balances(&[("asset", &[(router_addr.as_str(), 300u128)])])
+        .unwrap();
+
+    let res: SimulateSwapOperationsResponse = app
+        .query(
+            router_addr.clone(),
+            &QueryMsg::SimulateSwapOperations {
+                offer_amount: Uint128::from(1000u128),
+                operations: vec![SwapOperation::OraiSwap {
+                    offer_asset_info: asset_infos[0].clone(),
+                    ask_asset_info: asset_infos[1].clone(),
+                }],
+            },
+        )
+        .unwrap();
+
+    let query_asset_balance = |app: &MockApp, address: &str| -> Uint128 {
+        let res: cw20::BalanceResponse = app
+            .query(
+                asset_addr.clone(),
+                &cw20::Cw20QueryMsg::Balance {
+                    address: address.to_string(),
+                },
+            )
+            .unwrap();
+        res.balance
+    };
+    let balance_before = query_asset_balance(&app, "addr0000");
+
+    // the swap only spends the offer and only pays out its own output
+    app.execute(
+        Addr::unchecked("addr0000"),
+        router_addr.clone(),
+        &ExecuteMsg::ExecuteSwapOperations {
+            operations: vec![SwapOperation::OraiSwap {
+                offer_asset_info: asset_infos[0].clone(),
+                ask_asset_info: asset_infos[1].clone(),
+            }],
+            minimum_receive: None,
+            to: None,
+            affiliates: None,
+            post_swap_action: None,
+        },
+        &[Coin {
+            denom: ATOM_DENOM.to_string(),
+            amount: Uint128::from(1000u128),
+        }],
+    )
+    .unwrap();
+
+    assert_eq!(
+        query_asset_balance(&app, "addr0000"),
+        balance_before + res.amount
+    );
+    assert_eq!(
+        app.query_balance(router_addr.clone(), ATOM_DENOM.to_string())
+            .unwrap(),
+        Uint128::from(500u128)
+    );
+    assert_eq!(
+        query_asset_balance(&app, router_addr.as_str()),
+        Uint128::from(300u128)
+    );
+
+    // only the owner can sweep the orphaned balances
+    let sweep_dust = ExecuteMsg::SweepDust {
+        asset_infos: asset_infos.to_vec(),
+        recipient: Some("addr0002".to_string()),
+    };
+    let error = app
+        .execute(
+            Addr::unchecked("addr0001"),
+            router_addr.clone(),
+            &sweep_dust,
+            &[],
+        )
+        .unwrap_err();
+    assert!(error.root_cause().to_string().contains("Unauthorized"));
+
+    app.execute(
+        Addr::unchecked("addr0000"),
+        router_addr.clone(),
+        &sweep_dust,
+        &[],
+    )
+    .unwrap();
+
+    assert_eq!(
+        app.query_balance(Addr::unchecked("addr0002"), ATOM_DENOM.to_string())
+            .unwrap(),
+        Uint128::from(500u128)
+    );
+    assert_eq!(
+        query_asset_balance(&app, "addr0002"),
+        Uint128::from(300u128)
+    );
+    assert!(app.query_all_balances(router_addr).unwrap().is_empty());
+}
+
+#[test]
+fn affiliate_fee_cap_and_accrual() {
+    let mut app = MockApp::new(&[(
+        "addr0000",
+        &[Coin {
+            denom: ATOM_DENOM.to_string(),
+            amount: Uint128::from(10000000u128),
+        }],
+    )]);
+
+    app.set_oracle_contract(Box::new(create_entry_points_testing!(oraiswap_oracle)));
+
+    app.set_token_contract(Box::new(create_entry_points_testing!(oraiswap_token)));
+
+    app.set_factory_and_pair_contract(
+        Box::new(
+            create_entry_points_testing!(oraiswap_factory)
+                .with_reply_empty(oraiswap_factory::contract::reply),
+        ),
+        Box::new(
+            create_entry_points_testing!(oraiswap_pair)
+                .with_reply_empty(oraiswap_pair::contract::reply),
+        ),
+    );
+
+    let asset_addr = app.create_token("asset");
+
+    app.set_token_balances(&[("asset", &[("addr0000", 1000000u128)])])
+        .unwrap();
+
+    let asset_infos = [
+        AssetInfo::NativeToken {
+            denom: ATOM_DENOM.to_string(),
+        },
+        AssetInfo::Token {
+            contract_addr: asset_addr.clone(),
+        },
+    ];
+
+    // create pair and provide liquidity
+    let pair_addr = app.create_pair(asset_infos.clone()).unwrap();
+
+    app.execute(
+        Addr::unchecked("addr0000"),
+        asset_addr.clone(),
+        &cw20::Cw20ExecuteMsg::IncreaseAllowance {
+            spender: pair_addr.to_string(),
+            amount: Uint128::from(1000000u128),
+            expires: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    app.execute(
+        Addr::unchecked("addr0000"),
+        pair_addr.clone(),
+        &oraiswap::pair::ExecuteMsg::ProvideLiquidity {
+            assets: asset_infos.clone().map(|info| Asset {
+                info,
+                amount: Uint128::from(1000000u128),
+            }),
+            slippage_tolerance: None,
+            receiver: None,
+        },
+        &[Coin {
+            denom: ATOM_DENOM.to_string(),
+            amount: Uint128::from(1000000u128),
+        }],
+    )
+    .unwrap();
+
+    let msg = InstantiateMsg {
+        factory_addr: app.factory_addr.clone(),
+        factory_addr_v2: Addr::unchecked("addr0000_v2"),
+        oraiswap_v3: Addr::unchecked("oraiswap_v3"),
+        converter: None,
+    };
+
+    let code_id = app.upload(Box::new(create_entry_points_testing!(crate)));
+
+    let router_addr = app
+        .instantiate(code_id, Addr::unchecked("addr0000"), &msg, &[], "router")
+        .unwrap();
+
+    app.execute(
+        Addr::unchecked("addr0000"),
+        router_addr.clone(),
+        &ExecuteMsg::UpdateConfig {
+            factory_addr: None,
+            factory_addr_v2: None,
+            oraiswap_v3: None,
+            converter: None,
+            ics20_contract: None,
+            max_affiliate_fee_bps: Some(Uint128::new(500)),
+            owner: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    // affiliate_1 accrues its fees
+    app.execute(
+        Addr::unchecked("affiliate_1"),
+        router_addr.clone(),
+        &ExecuteMsg::RegisterAffiliate {},
+        &[],
+    )
+    .unwrap();
+    let res: AffiliatesResponse = app
+        .query(
+            router_addr.clone(),
+            &QueryMsg::Affiliates {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(res.affiliates, vec![Addr::unchecked("affiliate_1")]);
+
+    let res: SimulateSwapOperationsResponse = app
+        .query(
+            router_addr.clone(),
+            &QueryMsg::SimulateSwapOperations {
+                offer_amount: Uint128::from(1000u128),
+                operations: vec![SwapOperation::OraiSwap {
+                    offer_asset_info: asset_infos[0].clone(),
+                    ask_asset_info: asset_infos[1].clone(),
+                }],
+            },
+        )
+        .unwrap();
+    let fee_1 = res.amount.multiply_ratio(300u128, 10000u128);
+    let fee_2 = res.amount.multiply_ratio(200u128, 10000u128);
+
+    let swap = |app: &mut MockApp, affiliate_2_bps: u128, minimum_receive: Uint128| {
+        app.execute(
+            Addr::unchecked("addr0000"),
+            router_addr.clone(),
+            &ExecuteMsg::ExecuteSwapOperations {
+                operations: vec![SwapOperation::OraiSwap {
+                    offer_asset_info: asset_infos[0].clone(),
+                    ask_asset_info: asset_infos[1].clone(),
+                }],
+                minimum_receive: Some(minimum_receive),
+                to: None,
+                affiliates: Some(vec![
+                    Affiliate {
+                        basis_points_fee: Uint128::new(300),
+                        address: Addr::unchecked("affiliate_1"),
+                    },
+                    Affiliate {
+                        basis_points_fee: Uint128::new(affiliate_2_bps),
+                        address: Addr::unchecked("affiliate_2"),
+                    },
+                ]),
+                post_swap_action: None,
+            },
+            &[Coin {
+                denom: ATOM_DENOM.to_string(),
+                amount: Uint128::from(1000u128),
+            }],
+        )
+    };
+    let query_asset_balance = |app: &MockApp, address: &str| -> Uint128 {
+        let res: cw20::BalanceResponse = app
+            .query(
+                asset_addr.clone(),
+                &cw20::Cw20QueryMsg::Balance {
+                    address: address.to_string(),
+                },
+            )
+            .unwrap();
+        res.balance
+    };
+
+    // the total fee is above the cap
+    let error = swap(&mut app, 300, Uint128::zero()).unwrap_err();
+    assert!(error
+        .root_cause()
+        .to_string()
+        .contains("Total affiliate fee of 600 bps exceeds the max of 500 bps"));
+
+    // the minimum receive applies to the amount left after the fees
+    let error = swap(&mut app, 200, res.amount).unwrap_err();
+    assert!(error.root_cause().to_string().contains("Assertion failed"));
+
+    let balance_before = query_asset_balance(&app, "addr0000");
+    swap(&mut app, 200, res.amount - fee_1 - fee_2).unwrap();
+
+    assert_eq!(
+        query_asset_balance(&app, "addr0000"),
+        balance_before + res.amount - fee_1 - fee_2
+    );
+    assert_eq!(query_asset_balance(&app, "affiliate_2"), fee_2);
+    assert_eq!(query_asset_balance(&app, "affiliate_1"), Uint128::zero());
+
+    let fees: AffiliateFeesResponse = app
+        .query(
+            router_addr.clone(),
+            &QueryMsg::AffiliateFees {
+                address: "affiliate_1".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        fees.fees,
+        vec![Asset {
+            info: asset_infos[1].clone(),
+            amount: fee_1,
+        }]
+    );
+
+    // the accrued fees are not dust
+    app.execute(
+        Addr::unchecked("addr0000"),
+        router_addr.clone(),
+        &ExecuteMsg::SweepDust {
+            asset_infos: vec![asset_infos[1].clone()],
+            recipient: Some("addr0002".to_string()),
+        },
+        &[],
+    )
+    .unwrap();
+    assert_eq!(query_asset_balance(&app, "addr0002"), Uint128::zero());
+
+    app.execute(
+        Addr::unchecked("affiliate_1"),
+        router_addr.clone(),
+        &ExecuteMsg::ClaimAffiliateFees {},
+        &[],
+    )
+    .unwrap();
+    assert_eq!(query_asset_balance(&app, "affiliate_1"), fee_1);
+    assert_eq!(
+        query_asset_balance(&app, router_addr.as_str()),
+        Uint128::zero()
+    );
+
+    let fees: AffiliateFeesResponse = app
+        .query(
+            router_addr.clone(),
+            &QueryMsg::AffiliateFees {
+                address: "affiliate_1".to_string(),
+            },
+        )
+        .unwrap();
+    assert!(fees.fees.is_empty());
+}
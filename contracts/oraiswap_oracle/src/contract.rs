@@ -1,22 +1,38 @@
 use cosmwasm_std::{entry_point, Coin};
+use cw_storage_plus::Map;
 
 use cosmwasm_std::{
-    to_json_binary, Addr, Binary, Decimal, Deps, DepsMut, Env, MessageInfo, Response, StdError,
-    StdResult, Uint128,
+    attr, to_json_binary, Addr, Binary, CanonicalAddr, Decimal, Decimal256, Deps, DepsMut, Env,
+    MessageInfo, Order, Response, StdError, StdResult, Storage, Uint128,
 };
 
-use oraiswap::asset::ORAI_DENOM;
+use oraiswap::asset::{AssetInfo, ORAI_DENOM};
 use oraiswap::oracle::{
-    ContractInfo, ContractInfoResponse, ExchangeRateItem, ExchangeRateResponse,
-    ExchangeRatesResponse, ExecuteMsg, MigrateMsg, OracleContractQuery, OracleExchangeQuery,
-    OracleTreasuryQuery, QueryMsg, TaxCapResponse, TaxRateResponse,
+    AllExchangeRatesResponse, AllTaxCapsResponse, AssetExchangeRateItem, AssetExchangeRateResponse,
+    ContractInfo, ContractInfoResponse, ExchangeRateHistoryItem, ExchangeRateHistoryResponse,
+    ExchangeRateItem, ExchangeRateResponse, ExchangeRateSource, ExchangeRateTwapResponse,
+    ExchangeRatesResponse, ExecuteMsg, FallbackPrice, FeedConfig, FeedConfigResponse, MigrateMsg,
+    OracleContractQuery, OracleExchangeQuery, OracleTreasuryQuery, PendingExchangeRateResponse,
+    PriceSource, QueryMsg, RateLimit, TaxCapItem, TaxCapResponse, TaxExemptResponse,
+    TaxRateResponse,
 };
 
 use oraiswap::error::ContractError;
 use oraiswap::oracle::InstantiateMsg;
+use oraiswap::querier::{query_cumulative_prices, query_pair_info, query_pool};
 
 // use crate::msg::{ExecuteMsg, InstantiateMsg};
-use crate::state::{CONTRACT_INFO, EXCHANGE_RATES, TAX_CAP, TAX_RATE};
+use crate::state::{
+    read_exchange_rate_asset_info, read_exchange_rate_history, read_exchange_rate_twap,
+    read_exchange_rates, read_fallback_price_observation, read_reference_exchange_rate,
+    read_tax_cap_asset_info, read_tax_caps, remove_exchange_rate,
+    remove_fallback_price_observations, remove_feeder_submissions, store_exchange_rate,
+    store_fallback_price_observation, FeederSubmission, PendingExchangeRate, CONFIRMERS,
+    CONTRACT_INFO, EXCHANGE_RATES, EXCHANGE_RATES_LAST_UPDATED, EXCHANGE_RATE_ASSET_INFOS,
+    FALLBACK_PRICES, FEEDERS, FEEDER_SUBMISSIONS, FEED_CONFIG, MAX_SUBMISSION_AGE,
+    PENDING_EXCHANGE_RATES, RATE_LIMITS, TAX_CAP, TAX_CAP_ASSET_INFOS, TAX_CAP_LAST_UPDATED,
+    TAX_EXEMPT, TAX_RATE, TAX_RATES,
+};
 
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:oraiswap_oracle";
@@ -48,6 +64,7 @@ pub fn instantiate(
         max_rate: msg.max_rate.unwrap_or(Decimal::percent(1)), // 1%
     };
     CONTRACT_INFO.save(deps.storage, &info)?;
+    FEED_CONFIG.save(deps.storage, &FeedConfig::default())?;
 
     // defaul is orai/orai 1:1 (no tax), this is for swap Orai native to Orai token
     EXCHANGE_RATES.save(deps.storage, ORAI_DENOM.as_bytes(), &Decimal::one())?;
@@ -59,26 +76,68 @@ pub fn instantiate(
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     match msg {
         ExecuteMsg::UpdateExchangeRate {
-            denom,
+            asset_info,
             exchange_rate,
-        } => execute_update_exchange_rate(deps, info, denom, exchange_rate),
-        ExecuteMsg::DeleteExchangeRate { denom } => execute_delete_exchange_rate(deps, info, denom),
-        ExecuteMsg::UpdateTaxCap { cap, denom } => execute_update_tax_cap(deps, info, denom, cap),
+        } => execute_update_exchange_rate(deps, env, info, asset_info, exchange_rate),
+        ExecuteMsg::DeleteExchangeRate { asset_info } => {
+            execute_delete_exchange_rate(deps, info, asset_info)
+        }
+        ExecuteMsg::UpdateTaxCap { cap, asset_info } => {
+            execute_update_tax_cap(deps, env, info, asset_info, cap)
+        }
         ExecuteMsg::UpdateTaxRate { rate } => execute_update_tax_rate(deps, info, rate),
+        ExecuteMsg::UpdateDenomTaxRate { asset_info, rate } => {
+            execute_update_denom_tax_rate(deps, info, asset_info, rate)
+        }
+        ExecuteMsg::AddTaxExempt { address } => execute_add_tax_exempt(deps, info, address),
+        ExecuteMsg::RemoveTaxExempt { address } => execute_remove_tax_exempt(deps, info, address),
         ExecuteMsg::UpdateAdmin { admin } => execute_update_admin(deps, info, admin),
+        ExecuteMsg::AddFeeder { feeder } => execute_add_feeder(deps, info, feeder),
+        ExecuteMsg::RemoveFeeder { feeder } => execute_remove_feeder(deps, info, feeder),
+        ExecuteMsg::UpdateFeedConfig {
+            quorum,
+            max_age,
+            reject_stale,
+        } => execute_update_feed_config(deps, info, quorum, max_age, reject_stale),
+        ExecuteMsg::FeedExchangeRate {
+            asset_info,
+            exchange_rate,
+        } => execute_feed_exchange_rate(deps, env, info, asset_info, exchange_rate),
+        ExecuteMsg::UpdateRateLimit {
+            asset_info,
+            rate_limit,
+        } => execute_update_rate_limit(deps, info, asset_info, rate_limit),
+        ExecuteMsg::AddConfirmer { confirmer } => execute_add_confirmer(deps, info, confirmer),
+        ExecuteMsg::RemoveConfirmer { confirmer } => {
+            execute_remove_confirmer(deps, info, confirmer)
+        }
+        ExecuteMsg::ConfirmExchangeRate { asset_info } => {
+            execute_confirm_exchange_rate(deps, env, info, asset_info)
+        }
+        ExecuteMsg::RejectExchangeRate { asset_info } => {
+            execute_reject_exchange_rate(deps, info, asset_info)
+        }
+        ExecuteMsg::UpdateFallbackPrice {
+            asset_info,
+            fallback,
+        } => execute_update_fallback_price(deps, info, asset_info, fallback),
+        ExecuteMsg::ObserveFallbackPrice { asset_info } => {
+            execute_observe_fallback_price(deps, env, info, asset_info)
+        }
     }
 }
 
 pub fn execute_update_tax_cap(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
-    denom: String,
+    asset_info: AssetInfo,
     cap: Uint128,
 ) -> Result<Response, ContractError> {
     let contract_info = CONTRACT_INFO.load(deps.storage)?;
@@ -90,7 +149,10 @@ pub fn execute_update_tax_cap(
     }
 
     // update tax cap
-    TAX_CAP.save(deps.storage, denom.as_bytes(), &cap)?;
+    let key = asset_info.to_vec(deps.api)?;
+    TAX_CAP.save(deps.storage, &key, &cap)?;
+    TAX_CAP_ASSET_INFOS.save(deps.storage, &key, &asset_info)?;
+    TAX_CAP_LAST_UPDATED.save(deps.storage, &key, &env.block.time.seconds())?;
 
     // return nothing new
     Ok(Response::default())
@@ -117,6 +179,78 @@ pub fn execute_update_tax_rate(
     Ok(Response::default())
 }
 
+pub fn execute_update_denom_tax_rate(
+    deps: DepsMut,
+    info: MessageInfo,
+    asset_info: AssetInfo,
+    rate: Option<Decimal>,
+) -> Result<Response, ContractError> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+
+    // check authorized
+    if contract_info.admin.ne(&sender_addr) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    // same constraint as the global tax rate
+    let key = asset_info.to_vec(deps.api)?;
+    match rate {
+        Some(rate) => {
+            let rate = rate.clamp(contract_info.min_rate, contract_info.max_rate);
+            TAX_RATES.save(deps.storage, &key, &rate)?;
+        }
+        None => TAX_RATES.remove(deps.storage, &key),
+    }
+
+    // return nothing new
+    Ok(Response::default())
+}
+
+pub fn execute_add_tax_exempt(
+    deps: DepsMut,
+    info: MessageInfo,
+    address: Addr,
+) -> Result<Response, ContractError> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+
+    // check authorized
+    if contract_info.admin.ne(&sender_addr) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let address_raw = deps.api.addr_canonicalize(address.as_str())?;
+    TAX_EXEMPT.save(deps.storage, address_raw.as_slice(), &true)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "add_tax_exempt"),
+        attr("address", address),
+    ]))
+}
+
+pub fn execute_remove_tax_exempt(
+    deps: DepsMut,
+    info: MessageInfo,
+    address: Addr,
+) -> Result<Response, ContractError> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+
+    // check authorized
+    if contract_info.admin.ne(&sender_addr) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let address_raw = deps.api.addr_canonicalize(address.as_str())?;
+    TAX_EXEMPT.remove(deps.storage, address_raw.as_slice());
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "remove_tax_exempt"),
+        attr("address", address),
+    ]))
+}
+
 pub fn execute_update_admin(
     deps: DepsMut,
     info: MessageInfo,
@@ -138,10 +272,12 @@ pub fn execute_update_admin(
     Ok(Response::default())
 }
 
+/// admin override, bypasses the feeder quorum
 pub fn execute_update_exchange_rate(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
-    denom: String,
+    asset_info: AssetInfo,
     exchange_rate: Decimal,
 ) -> Result<Response, ContractError> {
     let contract_info = CONTRACT_INFO.load(deps.storage)?;
@@ -152,15 +288,397 @@ pub fn execute_update_exchange_rate(
         return Err(ContractError::Unauthorized {});
     }
 
-    EXCHANGE_RATES.save(deps.storage, denom.as_bytes(), &exchange_rate)?;
+    let status = accept_exchange_rate(
+        deps.storage,
+        &asset_info,
+        &asset_info.to_vec(deps.api)?,
+        exchange_rate,
+        env.block.time.seconds(),
+        sender_addr,
+    )?;
 
-    Ok(Response::default())
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "update_exchange_rate"),
+        attr("denom", asset_info.to_string()),
+        attr("status", status),
+    ]))
+}
+
+pub fn execute_add_feeder(
+    deps: DepsMut,
+    info: MessageInfo,
+    feeder: Addr,
+) -> Result<Response, ContractError> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+
+    // check authorized
+    if contract_info.admin.ne(&sender_addr) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let feeder_raw = deps.api.addr_canonicalize(feeder.as_str())?;
+    FEEDERS.save(deps.storage, feeder_raw.as_slice(), &true)?;
+
+    Ok(Response::new().add_attributes(vec![attr("action", "add_feeder"), attr("feeder", feeder)]))
+}
+
+pub fn execute_remove_feeder(
+    deps: DepsMut,
+    info: MessageInfo,
+    feeder: Addr,
+) -> Result<Response, ContractError> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+
+    // check authorized
+    if contract_info.admin.ne(&sender_addr) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    // submissions of removed feeders are ignored when aggregating
+    let feeder_raw = deps.api.addr_canonicalize(feeder.as_str())?;
+    FEEDERS.remove(deps.storage, feeder_raw.as_slice());
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "remove_feeder"),
+        attr("feeder", feeder),
+    ]))
+}
+
+pub fn execute_update_feed_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    quorum: Option<u32>,
+    max_age: Option<u64>,
+    reject_stale: Option<bool>,
+) -> Result<Response, ContractError> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+
+    // check authorized
+    if contract_info.admin.ne(&sender_addr) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let mut feed_config = FEED_CONFIG.may_load(deps.storage)?.unwrap_or_default();
+    if let Some(quorum) = quorum {
+        if quorum == 0 {
+            return Err(ContractError::InvalidQuorum {});
+        }
+        feed_config.quorum = quorum;
+    }
+    if let Some(max_age) = max_age {
+        feed_config.max_age = if max_age == 0 { None } else { Some(max_age) };
+    }
+    if let Some(reject_stale) = reject_stale {
+        feed_config.reject_stale = reject_stale;
+    }
+    FEED_CONFIG.save(deps.storage, &feed_config)?;
+
+    Ok(Response::new().add_attribute("action", "update_feed_config"))
+}
+
+pub fn execute_feed_exchange_rate(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    asset_info: AssetInfo,
+    exchange_rate: Decimal,
+) -> Result<Response, ContractError> {
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+
+    // check authorized
+    if !FEEDERS.has(deps.storage, sender_addr.as_slice()) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let now = env.block.time.seconds();
+    let key = asset_info.to_vec(deps.api)?;
+    FEEDER_SUBMISSIONS.save(
+        deps.storage,
+        (key.as_slice(), sender_addr.as_slice()),
+        &FeederSubmission {
+            exchange_rate,
+            timestamp: now,
+        },
+    )?;
+
+    // collect fresh submissions from the current feeder set
+    let feed_config = FEED_CONFIG.may_load(deps.storage)?.unwrap_or_default();
+    let mut rates: Vec<Decimal> = vec![];
+    for item in FEEDER_SUBMISSIONS
+        .prefix(&key)
+        .range(deps.storage, None, None, Order::Ascending)
+    {
+        let (feeder, submission) = item?;
+        if !FEEDERS.has(deps.storage, &feeder) {
+            continue;
+        }
+        let max_age = feed_config.max_age.map_or(MAX_SUBMISSION_AGE, |max_age| {
+            max_age.min(MAX_SUBMISSION_AGE)
+        });
+        if submission.timestamp.saturating_add(max_age) < now {
+            continue;
+        }
+        rates.push(submission.exchange_rate);
+    }
+
+    let mut attributes = vec![
+        attr("action", "feed_exchange_rate"),
+        attr("denom", asset_info.to_string()),
+        attr("feeder", info.sender),
+        attr("submissions", rates.len().to_string()),
+    ];
+
+    // only update the rate once quorum is reached
+    if rates.len() >= feed_config.quorum as usize {
+        let median_rate = median(rates);
+        let status = match accept_exchange_rate(
+            deps.storage,
+            &asset_info,
+            &key,
+            median_rate,
+            now,
+            sender_addr,
+        ) {
+            Ok(status) => {
+                // the next rate needs a new round of submissions
+                remove_feeder_submissions(deps.storage, &key)?;
+                status
+            }
+            // keep the submission, the stored rate is left unchanged
+            Err(ContractError::RateChangeExceeded { .. }) => "rejected",
+            Err(err) => return Err(err),
+        };
+        attributes.push(attr("exchange_rate", median_rate.to_string()));
+        attributes.push(attr("status", status));
+    }
+
+    Ok(Response::new().add_attributes(attributes))
+}
+
+/// stores the rate if it stays within the rate limit of denom, otherwise
+/// queues it for confirmation or rejects it, depending on the rate limit
+fn accept_exchange_rate(
+    storage: &mut dyn Storage,
+    asset_info: &AssetInfo,
+    denom_key: &[u8],
+    exchange_rate: Decimal,
+    now: u64,
+    proposer: CanonicalAddr,
+) -> Result<&'static str, ContractError> {
+    if let Some(rate_limit) = RATE_LIMITS.may_load(storage, denom_key)? {
+        if exceeds_rate_limit(storage, denom_key, &rate_limit, exchange_rate, now)? {
+            if !rate_limit.require_confirmation {
+                return Err(ContractError::RateChangeExceeded {
+                    denom: asset_info.to_string(),
+                });
+            }
+
+            PENDING_EXCHANGE_RATES.save(
+                storage,
+                denom_key,
+                &PendingExchangeRate {
+                    exchange_rate,
+                    proposer,
+                    timestamp: now,
+                },
+            )?;
+            return Ok("pending");
+        }
+    }
+
+    store_exchange_rate(storage, asset_info, denom_key, &exchange_rate, now)?;
+    Ok("updated")
+}
+
+fn exceeds_rate_limit(
+    storage: &dyn Storage,
+    denom_key: &[u8],
+    rate_limit: &RateLimit,
+    exchange_rate: Decimal,
+    now: u64,
+) -> StdResult<bool> {
+    if let Some(max_change) = rate_limit.max_change {
+        if let Some(current_rate) = EXCHANGE_RATES.may_load(storage, denom_key)? {
+            if relative_change(current_rate, exchange_rate) > max_change {
+                return Ok(true);
+            }
+        }
+    }
+
+    if let Some(max_change_per_hour) = rate_limit.max_change_per_hour {
+        let hour_ago = now.saturating_sub(3600);
+        if let Some(reference_rate) = read_reference_exchange_rate(storage, denom_key, hour_ago)? {
+            if relative_change(reference_rate, exchange_rate) > max_change_per_hour {
+                return Ok(true);
+            }
+        }
+    }
+
+    Ok(false)
+}
+
+fn relative_change(from: Decimal, to: Decimal) -> Decimal {
+    let diff = if to > from { to - from } else { from - to };
+    diff.checked_div(from).unwrap_or(Decimal::MAX)
+}
+
+pub fn execute_update_rate_limit(
+    deps: DepsMut,
+    info: MessageInfo,
+    asset_info: AssetInfo,
+    rate_limit: Option<RateLimit>,
+) -> Result<Response, ContractError> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+
+    // check authorized
+    if contract_info.admin.ne(&sender_addr) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let key = asset_info.to_vec(deps.api)?;
+    match rate_limit {
+        Some(rate_limit) => RATE_LIMITS.save(deps.storage, &key, &rate_limit)?,
+        None => RATE_LIMITS.remove(deps.storage, &key),
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "update_rate_limit"),
+        attr("denom", asset_info.to_string()),
+    ]))
+}
+
+pub fn execute_add_confirmer(
+    deps: DepsMut,
+    info: MessageInfo,
+    confirmer: Addr,
+) -> Result<Response, ContractError> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+
+    // check authorized
+    if contract_info.admin.ne(&sender_addr) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let confirmer_raw = deps.api.addr_canonicalize(confirmer.as_str())?;
+    CONFIRMERS.save(deps.storage, confirmer_raw.as_slice(), &true)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "add_confirmer"),
+        attr("confirmer", confirmer),
+    ]))
+}
+
+pub fn execute_remove_confirmer(
+    deps: DepsMut,
+    info: MessageInfo,
+    confirmer: Addr,
+) -> Result<Response, ContractError> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+
+    // check authorized
+    if contract_info.admin.ne(&sender_addr) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let confirmer_raw = deps.api.addr_canonicalize(confirmer.as_str())?;
+    CONFIRMERS.remove(deps.storage, confirmer_raw.as_slice());
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "remove_confirmer"),
+        attr("confirmer", confirmer),
+    ]))
+}
+
+/// a pending rate must be confirmed by the admin or a confirmer other than the proposer
+pub fn execute_confirm_exchange_rate(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    asset_info: AssetInfo,
+) -> Result<Response, ContractError> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+
+    // check authorized
+    if contract_info.admin.ne(&sender_addr) && !CONFIRMERS.has(deps.storage, sender_addr.as_slice())
+    {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let key = asset_info.to_vec(deps.api)?;
+    let pending = PENDING_EXCHANGE_RATES
+        .may_load(deps.storage, &key)?
+        .ok_or_else(|| ContractError::NoPendingExchangeRate {
+            denom: asset_info.to_string(),
+        })?;
+
+    if pending.proposer.eq(&sender_addr) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    PENDING_EXCHANGE_RATES.remove(deps.storage, &key);
+    store_exchange_rate(
+        deps.storage,
+        &asset_info,
+        &key,
+        &pending.exchange_rate,
+        env.block.time.seconds(),
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "confirm_exchange_rate"),
+        attr("denom", asset_info.to_string()),
+        attr("exchange_rate", pending.exchange_rate.to_string()),
+    ]))
+}
+
+pub fn execute_reject_exchange_rate(
+    deps: DepsMut,
+    info: MessageInfo,
+    asset_info: AssetInfo,
+) -> Result<Response, ContractError> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+
+    // check authorized
+    if contract_info.admin.ne(&sender_addr) && !CONFIRMERS.has(deps.storage, sender_addr.as_slice())
+    {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let denom = asset_info.to_string();
+    let key = asset_info.to_vec(deps.api)?;
+    if !PENDING_EXCHANGE_RATES.has(deps.storage, &key) {
+        return Err(ContractError::NoPendingExchangeRate { denom });
+    }
+    PENDING_EXCHANGE_RATES.remove(deps.storage, &key);
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "reject_exchange_rate"),
+        attr("denom", denom),
+    ]))
+}
+
+fn median(mut rates: Vec<Decimal>) -> Decimal {
+    rates.sort();
+    let mid = rates.len() / 2;
+    if rates.len() % 2 == 0 {
+        (rates[mid - 1] + rates[mid]) * Decimal::percent(50)
+    } else {
+        rates[mid]
+    }
 }
 
 pub fn execute_delete_exchange_rate(
     deps: DepsMut,
     info: MessageInfo,
-    denom: String,
+    asset_info: AssetInfo,
 ) -> Result<Response, ContractError> {
     let contract_info = CONTRACT_INFO.load(deps.storage)?;
     let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
@@ -170,17 +688,111 @@ pub fn execute_delete_exchange_rate(
         return Err(ContractError::Unauthorized {});
     }
 
-    EXCHANGE_RATES.remove(deps.storage, denom.as_bytes());
+    remove_exchange_rate(deps.storage, &asset_info.to_vec(deps.api)?)?;
 
     Ok(Response::default())
 }
 
+pub fn execute_update_fallback_price(
+    deps: DepsMut,
+    info: MessageInfo,
+    asset_info: AssetInfo,
+    fallback: Option<FallbackPrice>,
+) -> Result<Response, ContractError> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+
+    // check authorized
+    if contract_info.admin.ne(&sender_addr) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let key = asset_info.to_vec(deps.api)?;
+    match fallback {
+        Some(fallback) => {
+            match &fallback.source {
+                PriceSource::Pair { pair_addr } => {
+                    deps.api.addr_validate(pair_addr.as_str())?;
+                }
+                PriceSource::Route {
+                    factory_addr,
+                    asset_infos,
+                } => {
+                    deps.api.addr_validate(factory_addr.as_str())?;
+                    // the route must go from the asset to orai
+                    if asset_infos.len() < 2
+                        || asset_infos[0].ne(&asset_info)
+                        || asset_infos[asset_infos.len() - 1].ne(&AssetInfo::NativeToken {
+                            denom: ORAI_DENOM.to_string(),
+                        })
+                    {
+                        return Err(ContractError::InvalidPriceSource {});
+                    }
+                }
+            }
+            FALLBACK_PRICES.save(deps.storage, &key, &fallback)?;
+        }
+        None => FALLBACK_PRICES.remove(deps.storage, &key),
+    }
+    remove_fallback_price_observations(deps.storage, &key)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "update_fallback_price"),
+        attr("denom", asset_info.to_string()),
+    ]))
+}
+
+pub fn execute_observe_fallback_price(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    asset_info: AssetInfo,
+) -> Result<Response, ContractError> {
+    let contract_info = CONTRACT_INFO.load(deps.storage)?;
+    let sender_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+
+    // check authorized, feeders keep the observations going
+    if contract_info.admin.ne(&sender_addr) && !FEEDERS.has(deps.storage, sender_addr.as_slice()) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let key = asset_info.to_vec(deps.api)?;
+    let fallback = FALLBACK_PRICES
+        .may_load(deps.storage, &key)?
+        .ok_or(ContractError::InvalidPriceSource {})?;
+
+    let now = env.block.time.seconds();
+    if matches!(
+        read_fallback_price_observation(deps.storage, &key, now)?,
+        Some((timestamp, _)) if timestamp == now
+    ) {
+        return Err(ContractError::FallbackPriceObserved {
+            denom: asset_info.to_string(),
+        });
+    }
+
+    let price_cumulatives =
+        query_fallback_cumulative_prices(deps.as_ref(), &asset_info, &fallback.source)?;
+    store_fallback_price_observation(deps.storage, &key, price_cumulatives, now)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "observe_fallback_price"),
+        attr("denom", asset_info.to_string()),
+    ]))
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Treasury(query_data) => match query_data {
-            OracleTreasuryQuery::TaxRate {} => to_json_binary(&query_tax_rate(deps)?),
+            OracleTreasuryQuery::TaxRate { denom } => to_json_binary(&query_tax_rate(deps, denom)?),
             OracleTreasuryQuery::TaxCap { denom } => to_json_binary(&query_tax_cap(deps, denom)?),
+            OracleTreasuryQuery::AllTaxCaps { start_after, limit } => {
+                to_json_binary(&query_all_tax_caps(deps, start_after, limit)?)
+            }
+            OracleTreasuryQuery::TaxExempt { address } => {
+                to_json_binary(&query_tax_exempt(deps, address)?)
+            }
         },
         QueryMsg::Exchange(query_data) => match query_data {
             OracleExchangeQuery::ExchangeRate {
@@ -188,28 +800,79 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
                 quote_denom,
             } => to_json_binary(&query_exchange_rate(
                 deps,
-                base_denom.unwrap_or(ORAI_DENOM.to_string()),
-                quote_denom,
+                env,
+                native_asset_info(base_denom.unwrap_or(ORAI_DENOM.to_string())),
+                native_asset_info(quote_denom),
             )?),
             OracleExchangeQuery::ExchangeRates {
                 base_denom,
                 quote_denoms,
             } => to_json_binary(&query_exchange_rates(
                 deps,
-                base_denom.unwrap_or(ORAI_DENOM.to_string()),
-                quote_denoms,
+                env,
+                native_asset_info(base_denom.unwrap_or(ORAI_DENOM.to_string())),
+                quote_denoms.into_iter().map(native_asset_info).collect(),
+            )?),
+            OracleExchangeQuery::AssetExchangeRate {
+                base_asset_info,
+                quote_asset_info,
+            } => to_json_binary(&query_asset_exchange_rate(
+                deps,
+                env,
+                base_asset_info.unwrap_or(native_asset_info(ORAI_DENOM.to_string())),
+                quote_asset_info,
+            )?),
+            OracleExchangeQuery::ExchangeRateHistory {
+                asset_info,
+                start_after,
+                limit,
+            } => to_json_binary(&query_exchange_rate_history(
+                deps,
+                asset_info,
+                start_after,
+                limit,
+            )?),
+            OracleExchangeQuery::ExchangeRateTwap {
+                base_asset_info,
+                quote_asset_info,
+                window_seconds,
+            } => to_json_binary(&query_exchange_rate_twap(
+                deps,
+                env,
+                base_asset_info.unwrap_or(native_asset_info(ORAI_DENOM.to_string())),
+                quote_asset_info,
+                window_seconds,
             )?),
+            OracleExchangeQuery::RateLimit { asset_info } => {
+                to_json_binary(&query_rate_limit(deps, asset_info)?)
+            }
+            OracleExchangeQuery::PendingExchangeRate { asset_info } => {
+                to_json_binary(&query_pending_exchange_rate(deps, asset_info)?)
+            }
+            OracleExchangeQuery::AllExchangeRates { start_after, limit } => {
+                to_json_binary(&query_all_exchange_rates(deps, env, start_after, limit)?)
+            }
+            OracleExchangeQuery::FallbackPrice { asset_info } => {
+                to_json_binary(&query_fallback_price(deps, asset_info)?)
+            }
         },
         QueryMsg::Contract(query_data) => match query_data {
             OracleContractQuery::ContractInfo {} => to_json_binary(&query_contract_info(deps)?),
             OracleContractQuery::RewardPool { denom } => {
                 to_json_binary(&query_contract_balance(deps, env, denom)?)
             }
+            OracleContractQuery::FeedConfig {} => to_json_binary(&query_feed_config(deps)?),
         },
     }
 }
 
-pub fn query_tax_rate(deps: Deps) -> StdResult<TaxRateResponse> {
+pub fn query_tax_rate(deps: Deps, denom: Option<String>) -> StdResult<TaxRateResponse> {
+    if let Some(denom) = denom {
+        if let Some(rate) = TAX_RATES.may_load(deps.storage, denom.as_bytes())? {
+            return Ok(TaxRateResponse { rate });
+        }
+    }
+
     if let Ok(Some(rate)) = TAX_RATE.may_load(deps.storage) {
         return Ok(TaxRateResponse { rate });
     }
@@ -229,50 +892,203 @@ pub fn query_tax_cap(deps: Deps, denom: String) -> StdResult<TaxCapResponse> {
     })
 }
 
+pub fn query_all_tax_caps(
+    deps: Deps,
+    start_after: Option<AssetInfo>,
+    limit: Option<u32>,
+) -> StdResult<AllTaxCapsResponse> {
+    let start_after = start_after
+        .map(|asset_info| asset_info.to_vec(deps.api))
+        .transpose()?;
+    let items = read_tax_caps(deps.storage, start_after.as_deref(), limit)?
+        .into_iter()
+        .map(|(key, cap)| {
+            Ok(TaxCapItem {
+                asset_info: read_tax_cap_asset_info(deps.storage, &key)?,
+                cap,
+                last_updated: TAX_CAP_LAST_UPDATED.may_load(deps.storage, &key)?,
+            })
+        })
+        .collect::<StdResult<Vec<TaxCapItem>>>()?;
+
+    Ok(AllTaxCapsResponse { items })
+}
+
+pub fn query_tax_exempt(deps: Deps, address: String) -> StdResult<TaxExemptResponse> {
+    let address_raw = deps.api.addr_canonicalize(&address)?;
+    let exempt = TAX_EXEMPT.has(deps.storage, address_raw.as_slice());
+
+    Ok(TaxExemptResponse { exempt })
+}
+
 pub fn query_exchange_rate(
     deps: Deps,
-    base_denom: String,
-    quote_denom: String,
+    env: Env,
+    base_asset_info: AssetInfo,
+    quote_asset_info: AssetInfo,
 ) -> StdResult<ExchangeRateResponse> {
-    // quote = ask, offer = base
-    let base_rate = get_orai_exchange_rate(deps, &base_denom)?;
-    let quote_rate = get_orai_exchange_rate(deps, &quote_denom)?;
-
-    let res = ExchangeRateResponse {
-        base_denom: base_denom.clone(),
-        item: ExchangeRateItem {
-            quote_denom,
-            exchange_rate: quote_rate / base_rate,
-        },
-    };
+    let res = query_asset_exchange_rate(deps, env, base_asset_info, quote_asset_info)?;
 
-    Ok(res)
+    Ok(ExchangeRateResponse {
+        base_denom: res.base_asset_info.to_string(),
+        item: to_exchange_rate_item(res.item),
+    })
 }
 
 pub fn query_exchange_rates(
     deps: Deps,
-    base_denom: String,
-    quote_denoms: Vec<String>,
+    env: Env,
+    base_asset_info: AssetInfo,
+    quote_asset_infos: Vec<AssetInfo>,
 ) -> StdResult<ExchangeRatesResponse> {
+    let feed_config = FEED_CONFIG.may_load(deps.storage)?.unwrap_or_default();
+
+    let (base_rate, base_last_updated, _) =
+        get_orai_exchange_rate(deps, &env, &feed_config, &base_asset_info, None)?;
+
     let mut res = ExchangeRatesResponse {
-        base_denom: base_denom.clone(),
+        base_denom: base_asset_info.to_string(),
         items: vec![],
     };
 
-    let base_rate = get_orai_exchange_rate(deps, &base_denom)?;
-
-    for quote_denom in quote_denoms {
-        let quote_rate = get_orai_exchange_rate(deps, &quote_denom)?;
-
-        res.items.push(ExchangeRateItem {
-            quote_denom,
-            exchange_rate: quote_rate / base_rate,
-        });
+    for quote_asset_info in quote_asset_infos {
+        res.items.push(to_exchange_rate_item(get_exchange_rate_item(
+            deps,
+            &env,
+            &feed_config,
+            (base_rate, base_last_updated),
+            quote_asset_info,
+            None,
+        )?));
     }
 
     Ok(res)
 }
 
+pub fn query_asset_exchange_rate(
+    deps: Deps,
+    env: Env,
+    base_asset_info: AssetInfo,
+    quote_asset_info: AssetInfo,
+) -> StdResult<AssetExchangeRateResponse> {
+    get_cross_exchange_rate(deps, &env, base_asset_info, quote_asset_info, None)
+}
+
+pub fn query_all_exchange_rates(
+    deps: Deps,
+    env: Env,
+    start_after: Option<AssetInfo>,
+    limit: Option<u32>,
+) -> StdResult<AllExchangeRatesResponse> {
+    let feed_config = FEED_CONFIG.may_load(deps.storage)?.unwrap_or_default();
+    let start_after = start_after
+        .map(|asset_info| asset_info.to_vec(deps.api))
+        .transpose()?;
+
+    // stale rates are listed with their flag even when queries reject them
+    let items = read_exchange_rates(deps.storage, start_after.as_deref(), limit)?
+        .into_iter()
+        .map(|(key, _)| {
+            build_exchange_rate_item(
+                deps,
+                &env,
+                &feed_config,
+                (Decimal::one(), None),
+                read_exchange_rate_asset_info(deps.storage, &key)?,
+                None,
+            )
+        })
+        .collect::<StdResult<Vec<AssetExchangeRateItem>>>()?;
+
+    Ok(AllExchangeRatesResponse { items })
+}
+
+pub fn query_exchange_rate_history(
+    deps: Deps,
+    asset_info: AssetInfo,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<ExchangeRateHistoryResponse> {
+    let key = asset_info.to_vec(deps.api)?;
+    let items = read_exchange_rate_history(deps.storage, &key, start_after, limit)?
+        .into_iter()
+        .map(|(timestamp, exchange_rate)| ExchangeRateHistoryItem {
+            timestamp,
+            exchange_rate,
+        })
+        .collect();
+
+    Ok(ExchangeRateHistoryResponse { asset_info, items })
+}
+
+pub fn query_exchange_rate_twap(
+    deps: Deps,
+    env: Env,
+    base_asset_info: AssetInfo,
+    quote_asset_info: AssetInfo,
+    window_seconds: u64,
+) -> StdResult<ExchangeRateTwapResponse> {
+    let res = get_cross_exchange_rate(
+        deps,
+        &env,
+        base_asset_info,
+        quote_asset_info,
+        Some(window_seconds),
+    )?;
+
+    Ok(ExchangeRateTwapResponse {
+        base_asset_info: res.base_asset_info,
+        quote_asset_info: res.item.asset_info,
+        window_seconds,
+        exchange_rate: res.item.exchange_rate,
+    })
+}
+
+pub fn query_rate_limit(deps: Deps, asset_info: AssetInfo) -> StdResult<RateLimit> {
+    let key = asset_info.to_vec(deps.api)?;
+    Ok(RATE_LIMITS
+        .may_load(deps.storage, &key)?
+        .unwrap_or_default())
+}
+
+pub fn query_pending_exchange_rate(
+    deps: Deps,
+    asset_info: AssetInfo,
+) -> StdResult<Option<PendingExchangeRateResponse>> {
+    let key = asset_info.to_vec(deps.api)?;
+    PENDING_EXCHANGE_RATES
+        .may_load(deps.storage, &key)?
+        .map(|pending| {
+            Ok(PendingExchangeRateResponse {
+                asset_info,
+                exchange_rate: pending.exchange_rate,
+                proposer: deps.api.addr_humanize(&pending.proposer)?,
+                timestamp: pending.timestamp,
+            })
+        })
+        .transpose()
+}
+
+pub fn query_fallback_price(deps: Deps, asset_info: AssetInfo) -> StdResult<Option<FallbackPrice>> {
+    let key = asset_info.to_vec(deps.api)?;
+    FALLBACK_PRICES.may_load(deps.storage, &key)
+}
+
+pub fn query_feed_config(deps: Deps) -> StdResult<FeedConfigResponse> {
+    let feed_config = FEED_CONFIG.may_load(deps.storage)?.unwrap_or_default();
+    let feeders = FEEDERS
+        .keys(deps.storage, None, None, Order::Ascending)
+        .map(|key| deps.api.addr_humanize(&CanonicalAddr::from(key?)))
+        .collect::<StdResult<Vec<Addr>>>()?;
+
+    Ok(FeedConfigResponse {
+        feeders,
+        quorum: feed_config.quorum,
+        max_age: feed_config.max_age,
+        reject_stale: feed_config.reject_stale,
+    })
+}
+
 pub fn query_contract_info(deps: Deps) -> StdResult<ContractInfoResponse> {
     let info = CONTRACT_INFO.load(deps.storage)?;
     Ok(ContractInfoResponse {
@@ -290,15 +1106,380 @@ pub fn query_contract_balance(deps: Deps, env: Env, denom: String) -> StdResult<
     deps.querier.query_balance(env.contract.address, denom)
 }
 
-fn get_orai_exchange_rate(deps: Deps, denom: &str) -> StdResult<Decimal> {
-    if denom == ORAI_DENOM {
-        return Ok(Decimal::one());
+/// denoms of the string queries are native denoms, cw20 tokens are queried by AssetInfo
+fn native_asset_info(denom: String) -> AssetInfo {
+    AssetInfo::NativeToken { denom }
+}
+
+fn is_orai(asset_info: &AssetInfo) -> bool {
+    matches!(asset_info, AssetInfo::NativeToken { denom } if denom == ORAI_DENOM)
+}
+
+/// asset and current key of a key stored before the assets were recorded,
+/// None when asset_infos already records it or it is not a denom
+fn legacy_asset_info(
+    deps: Deps,
+    key: &[u8],
+    asset_infos: &Map<&[u8], AssetInfo>,
+) -> StdResult<Option<(AssetInfo, Vec<u8>)>> {
+    if asset_infos.has(deps.storage, key) {
+        return Ok(None);
+    }
+    let Ok(denom) = String::from_utf8(key.to_vec()) else {
+        return Ok(None);
+    };
+    // legacy cw20 entries are keyed by the contract address
+    let asset_info = AssetInfo::from_denom(deps.api, &denom);
+    let new_key = asset_info.to_vec(deps.api)?;
+    Ok(Some((asset_info, new_key)))
+}
+
+/// return the exchange rate of asset_info to Orai with its last update time and source,
+/// averaged over window_seconds if any, the fallback price is used when there is no fresh
+/// pushed rate
+fn get_orai_exchange_rate(
+    deps: Deps,
+    env: &Env,
+    feed_config: &FeedConfig,
+    asset_info: &AssetInfo,
+    window_seconds: Option<u64>,
+) -> StdResult<(Decimal, Option<u64>, ExchangeRateSource)> {
+    if is_orai(asset_info) {
+        return Ok((Decimal::one(), None, ExchangeRateSource::Pushed));
+    }
+
+    let key = asset_info.to_vec(deps.api)?;
+    let exchange_rate = EXCHANGE_RATES.may_load(deps.storage, &key)?;
+    let last_updated = EXCHANGE_RATES_LAST_UPDATED.may_load(deps.storage, &key)?;
+
+    if exchange_rate.is_none() || is_stale(env, feed_config, last_updated) {
+        if let Some(fallback) = FALLBACK_PRICES.may_load(deps.storage, &key)? {
+            let twap_window = window_seconds.or(fallback.twap_window);
+            match get_fallback_exchange_rate(deps, env, asset_info, &key, &fallback, twap_window) {
+                Ok((rate, source)) => return Ok((rate, Some(env.block.time.seconds()), source)),
+                // keep the stale pushed rate if any
+                Err(err) if exchange_rate.is_none() => return Err(err),
+                Err(_) => {}
+            }
+        }
+    }
+
+    let exchange_rate = match (exchange_rate, window_seconds) {
+        (Some(exchange_rate), Some(window_seconds)) => {
+            let now = env.block.time.seconds();
+            read_exchange_rate_twap(deps.storage, &key, now.saturating_sub(window_seconds), now)?
+                .unwrap_or(exchange_rate)
+        }
+        (Some(exchange_rate), None) => exchange_rate,
+        (None, _) => EXCHANGE_RATES.load(deps.storage, &key)?,
+    };
+
+    Ok((exchange_rate, last_updated, ExchangeRateSource::Pushed))
+}
+
+fn get_fallback_exchange_rate(
+    deps: Deps,
+    env: &Env,
+    asset_info: &AssetInfo,
+    denom_key: &[u8],
+    fallback: &FallbackPrice,
+    twap_window: Option<u64>,
+) -> StdResult<(Decimal, ExchangeRateSource)> {
+    if let Some(twap_window) = twap_window {
+        let now = env.block.time.seconds();
+        // the window is shortened when the observations start inside it
+        if let Some((timestamp, start_cumulatives)) = read_fallback_price_observation(
+            deps.storage,
+            denom_key,
+            now.saturating_sub(twap_window),
+        )? {
+            if timestamp < now {
+                let end_cumulatives =
+                    query_fallback_cumulative_prices(deps, asset_info, &fallback.source)?;
+                let twap =
+                    fallback_price_twap(&start_cumulatives, &end_cumulatives, now - timestamp)?;
+                return Ok((twap, ExchangeRateSource::PairTwap));
+            }
+        }
+    }
+
+    Ok((
+        query_fallback_spot_price(deps, asset_info, &fallback.source)?,
+        ExchangeRateSource::PairReserves,
+    ))
+}
+
+/// pairs along the source with the assets they swap, from asset_info to Orai
+fn fallback_hops(
+    deps: Deps,
+    asset_info: &AssetInfo,
+    source: &PriceSource,
+) -> StdResult<Vec<(Addr, [AssetInfo; 2])>> {
+    let orai_info = AssetInfo::NativeToken {
+        denom: ORAI_DENOM.to_string(),
+    };
+    let (pair_addrs, asset_infos) = match source {
+        PriceSource::Pair { pair_addr } => {
+            (vec![pair_addr.clone()], vec![asset_info.clone(), orai_info])
+        }
+        PriceSource::Route {
+            factory_addr,
+            asset_infos,
+        } => (
+            asset_infos
+                .windows(2)
+                .map(|infos| {
+                    query_pair_info(
+                        &deps.querier,
+                        factory_addr.clone(),
+                        &[infos[0].clone(), infos[1].clone()],
+                    )
+                    .map(|pair_info| pair_info.contract_addr)
+                })
+                .collect::<StdResult<Vec<Addr>>>()?,
+            asset_infos.clone(),
+        ),
+    };
+
+    Ok(pair_addrs
+        .into_iter()
+        .zip(asset_infos.windows(2))
+        .map(|(pair_addr, infos)| (pair_addr, [infos[0].clone(), infos[1].clone()]))
+        .collect())
+}
+
+/// amount of asset_info per Orai from the reserves of the pairs along the source
+fn query_fallback_spot_price(
+    deps: Deps,
+    asset_info: &AssetInfo,
+    source: &PriceSource,
+) -> StdResult<Decimal> {
+    let mut price = Decimal::one();
+    for (pair_addr, infos) in fallback_hops(deps, asset_info, source)? {
+        let pool = query_pool(&deps.querier, pair_addr)?;
+        let offer_pool = pool.assets.iter().find(|asset| asset.info.eq(&infos[0]));
+        let ask_pool = pool.assets.iter().find(|asset| asset.info.eq(&infos[1]));
+        match (offer_pool, ask_pool) {
+            (Some(offer_pool), Some(ask_pool)) if !ask_pool.amount.is_zero() => {
+                price = price.checked_mul(
+                    Decimal::checked_from_ratio(offer_pool.amount, ask_pool.amount)
+                        .map_err(|err| StdError::generic_err(err.to_string()))?,
+                )?;
+            }
+            _ => {
+                return Err(StdError::generic_err(format!(
+                    "No fallback price for {}",
+                    asset_info
+                )))
+            }
+        }
+    }
+
+    Ok(price)
+}
+
+/// cumulative price of each pair along the source, in the offer asset per ask asset
+fn query_fallback_cumulative_prices(
+    deps: Deps,
+    asset_info: &AssetInfo,
+    source: &PriceSource,
+) -> StdResult<Vec<Decimal256>> {
+    fallback_hops(deps, asset_info, source)?
+        .into_iter()
+        .map(|(pair_addr, infos)| {
+            let res = query_cumulative_prices(&deps.querier, pair_addr)?;
+            // price1 is the first pair asset per the second one
+            if res.assets[0].info.eq(&infos[0]) && res.assets[1].info.eq(&infos[1]) {
+                Ok(res.price1_cumulative)
+            } else if res.assets[0].info.eq(&infos[1]) && res.assets[1].info.eq(&infos[0]) {
+                Ok(res.price0_cumulative)
+            } else {
+                Err(StdError::generic_err(format!(
+                    "No fallback price for {}",
+                    asset_info
+                )))
+            }
+        })
+        .collect()
+}
+
+/// product of the average price of each pair between two observations elapsed seconds apart
+fn fallback_price_twap(
+    start_cumulatives: &[Decimal256],
+    end_cumulatives: &[Decimal256],
+    elapsed: u64,
+) -> StdResult<Decimal> {
+    // the source changed since the observation
+    if start_cumulatives.len() != end_cumulatives.len() {
+        return Err(StdError::generic_err("Fallback source changed"));
+    }
+
+    let elapsed = Decimal256::from_ratio(elapsed, 1u64);
+    let mut price = Decimal256::one();
+    for (start, end) in start_cumulatives.iter().zip(end_cumulatives) {
+        let average = end
+            .checked_sub(*start)?
+            .checked_div(elapsed)
+            .map_err(|err| StdError::generic_err(err.to_string()))?;
+        price = price.checked_mul(average)?;
+    }
+
+    Decimal::try_from(price).map_err(|err| StdError::generic_err(err.to_string()))
+}
+
+fn is_stale(env: &Env, feed_config: &FeedConfig, last_updated: Option<u64>) -> bool {
+    match (last_updated, feed_config.max_age) {
+        (Some(last_updated), Some(max_age)) => {
+            last_updated.saturating_add(max_age) < env.block.time.seconds()
+        }
+        _ => false,
+    }
+}
+
+/// rate of quote_asset_info per base_asset_info, averaged over window_seconds if any,
+/// the spot and twap queries share it so both apply the fallback and staleness rules
+fn get_cross_exchange_rate(
+    deps: Deps,
+    env: &Env,
+    base_asset_info: AssetInfo,
+    quote_asset_info: AssetInfo,
+    window_seconds: Option<u64>,
+) -> StdResult<AssetExchangeRateResponse> {
+    let feed_config = FEED_CONFIG.may_load(deps.storage)?.unwrap_or_default();
+
+    // quote = ask, offer = base
+    let (base_rate, base_last_updated, base_source) =
+        get_orai_exchange_rate(deps, env, &feed_config, &base_asset_info, window_seconds)?;
+    let item = get_exchange_rate_item(
+        deps,
+        env,
+        &feed_config,
+        (base_rate, base_last_updated),
+        quote_asset_info,
+        window_seconds,
+    )?;
+
+    Ok(AssetExchangeRateResponse {
+        base_asset_info,
+        base_source,
+        item,
+    })
+}
+
+fn get_exchange_rate_item(
+    deps: Deps,
+    env: &Env,
+    feed_config: &FeedConfig,
+    (base_rate, base_last_updated): (Decimal, Option<u64>),
+    quote_asset_info: AssetInfo,
+    window_seconds: Option<u64>,
+) -> StdResult<AssetExchangeRateItem> {
+    let item = build_exchange_rate_item(
+        deps,
+        env,
+        feed_config,
+        (base_rate, base_last_updated),
+        quote_asset_info,
+        window_seconds,
+    )?;
+    if item.stale && feed_config.reject_stale {
+        return Err(StdError::generic_err(format!(
+            "Exchange rate of {} is stale",
+            item.asset_info
+        )));
     }
 
-    EXCHANGE_RATES.load(deps.storage, denom.as_bytes())
+    Ok(item)
+}
+
+fn build_exchange_rate_item(
+    deps: Deps,
+    env: &Env,
+    feed_config: &FeedConfig,
+    (base_rate, base_last_updated): (Decimal, Option<u64>),
+    quote_asset_info: AssetInfo,
+    window_seconds: Option<u64>,
+) -> StdResult<AssetExchangeRateItem> {
+    let (quote_rate, quote_last_updated, source) =
+        get_orai_exchange_rate(deps, env, feed_config, &quote_asset_info, window_seconds)?;
+
+    // the cross rate is as old as its oldest component
+    let last_updated = match (base_last_updated, quote_last_updated) {
+        (Some(base), Some(quote)) => Some(base.min(quote)),
+        (base, quote) => base.or(quote),
+    };
+    let stale = is_stale(env, feed_config, last_updated);
+
+    Ok(AssetExchangeRateItem {
+        asset_info: quote_asset_info,
+        exchange_rate: quote_rate / base_rate,
+        source,
+        last_updated,
+        stale,
+    })
+}
+
+fn to_exchange_rate_item(item: AssetExchangeRateItem) -> ExchangeRateItem {
+    ExchangeRateItem {
+        quote_denom: item.asset_info.to_string(),
+        exchange_rate: item.exchange_rate,
+    }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(_deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+pub fn migrate(deps: DepsMut, env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    if FEED_CONFIG.may_load(deps.storage)?.is_none() {
+        FEED_CONFIG.save(deps.storage, &FeedConfig::default())?;
+    }
+
+    let legacy_rates = EXCHANGE_RATES
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<(Vec<u8>, Decimal)>>>()?;
+    for (key, rate) in legacy_rates {
+        if key == ORAI_DENOM.as_bytes() {
+            continue;
+        }
+        // legacy cw20 rates are keyed by the contract address, move them to the canonical key
+        let key = match legacy_asset_info(deps.as_ref(), &key, &EXCHANGE_RATE_ASSET_INFOS)? {
+            Some((asset_info, new_key)) => {
+                if new_key != key {
+                    let last_updated = EXCHANGE_RATES_LAST_UPDATED.may_load(deps.storage, &key)?;
+                    remove_exchange_rate(deps.storage, &key)?;
+                    EXCHANGE_RATES.save(deps.storage, &new_key, &rate)?;
+                    if let Some(last_updated) = last_updated {
+                        EXCHANGE_RATES_LAST_UPDATED.save(deps.storage, &new_key, &last_updated)?;
+                    }
+                }
+                EXCHANGE_RATE_ASSET_INFOS.save(deps.storage, &new_key, &asset_info)?;
+                new_key
+            }
+            None => key,
+        };
+        // legacy rates have no update time, consider them fresh from now on
+        if !EXCHANGE_RATES_LAST_UPDATED.has(deps.storage, &key) {
+            EXCHANGE_RATES_LAST_UPDATED.save(deps.storage, &key, &env.block.time.seconds())?;
+        }
+    }
+
+    let legacy_caps = TAX_CAP
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<(Vec<u8>, Uint128)>>>()?;
+    for (key, cap) in legacy_caps {
+        if let Some((asset_info, new_key)) =
+            legacy_asset_info(deps.as_ref(), &key, &TAX_CAP_ASSET_INFOS)?
+        {
+            if new_key != key {
+                let last_updated = TAX_CAP_LAST_UPDATED.may_load(deps.storage, &key)?;
+                TAX_CAP.remove(deps.storage, &key);
+                TAX_CAP_LAST_UPDATED.remove(deps.storage, &key);
+                TAX_CAP.save(deps.storage, &new_key, &cap)?;
+                if let Some(last_updated) = last_updated {
+                    TAX_CAP_LAST_UPDATED.save(deps.storage, &new_key, &last_updated)?;
+                }
+            }
+            TAX_CAP_ASSET_INFOS.save(deps.storage, &new_key, &asset_info)?;
+        }
+    }
+
     Ok(Response::default())
 }
@@ -1,12 +1,372 @@
-use cosmwasm_std::{Decimal, Uint128};
-use cw_storage_plus::{Item, Map};
-use oraiswap::oracle::ContractInfo;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_schema::serde::{de::DeserializeOwned, Serialize};
+use cosmwasm_std::{CanonicalAddr, Decimal, Decimal256, Order, StdResult, Storage, Uint128};
+use cw_storage_plus::{Bound, Item, Map};
+use oraiswap::asset::AssetInfo;
+use oraiswap::oracle::{ContractInfo, FallbackPrice, FeedConfig, RateLimit};
 
 // put the length bytes at the first for compatibility with legacy singleton store
 pub const CONTRACT_INFO: Item<ContractInfo> = Item::new("\u{0}\u{13}contract_info");
 pub const TAX_RATE: Item<Decimal> = Item::new("\u{0}\u{8}tax_rate");
 
 pub const TAX_CAP: Map<&[u8], Uint128> = Map::new("tax_cap");
+/// Last update time in seconds of each tax cap
+pub const TAX_CAP_LAST_UPDATED: Map<&[u8], u64> = Map::new("tax_cap_last_updated");
+/// Asset of each tax cap key, cw20 keys are canonical addresses
+pub const TAX_CAP_ASSET_INFOS: Map<&[u8], AssetInfo> = Map::new("tax_cap_asset_infos");
+/// Tax rate of denom overriding TAX_RATE
+pub const TAX_RATES: Map<&[u8], Decimal> = Map::new("tax_rates");
+/// Tax exempt recipients, keyed by canonical address
+pub const TAX_EXEMPT: Map<&[u8], bool> = Map::new("tax_exempt");
 /// Exchange rate of denom to Orai
 /// (QUOTE_DENOM / ORAI)  / (BASE_DENOM / ORAI) = QUOTE_DENOM / BASE_DENOM
 pub const EXCHANGE_RATES: Map<&[u8], Decimal> = Map::new("exchange_rates");
+/// Last update time in seconds of each exchange rate
+pub const EXCHANGE_RATES_LAST_UPDATED: Map<&[u8], u64> = Map::new("exchange_rates_last_updated");
+/// Asset of each exchange rate key, cw20 keys are canonical addresses
+pub const EXCHANGE_RATE_ASSET_INFOS: Map<&[u8], AssetInfo> = Map::new("exchange_rate_asset_infos");
+/// Accepted exchange rates keyed by (denom, update time in seconds)
+pub const EXCHANGE_RATE_HISTORY: Map<(&[u8], u64), Decimal> = Map::new("exchange_rate_history");
+pub const EXCHANGE_RATE_HISTORY_LEN: Map<&[u8], u32> = Map::new("exchange_rate_history_len");
+// oldest entries are pruned once a denom has more than this
+pub const MAX_EXCHANGE_RATE_HISTORY: u32 = 500;
+
+pub const FEED_CONFIG: Item<FeedConfig> = Item::new("feed_config");
+/// Whitelisted feeders, keyed by canonical address
+pub const FEEDERS: Map<&[u8], bool> = Map::new("feeders");
+/// Latest submission of each feeder, keyed by (denom, canonical feeder address)
+pub const FEEDER_SUBMISSIONS: Map<(&[u8], &[u8]), FeederSubmission> =
+    Map::new("feeder_submissions");
+// submissions older than this are ignored, whatever the feed config max age
+pub const MAX_SUBMISSION_AGE: u64 = 3600;
+
+/// Circuit breaker of each denom
+pub const RATE_LIMITS: Map<&[u8], RateLimit> = Map::new("rate_limits");
+/// Exchange rates beyond the limits waiting for confirmation
+pub const PENDING_EXCHANGE_RATES: Map<&[u8], PendingExchangeRate> =
+    Map::new("pending_exchange_rates");
+/// Addresses allowed to confirm pending exchange rates, keyed by canonical address
+pub const CONFIRMERS: Map<&[u8], bool> = Map::new("confirmers");
+
+/// On-chain price source of denom, used without a fresh pushed rate
+pub const FALLBACK_PRICES: Map<&[u8], FallbackPrice> = Map::new("fallback_prices");
+/// Cumulative prices of each hop of the fallback source keyed by (denom, observation time in seconds)
+pub const FALLBACK_PRICE_HISTORY: Map<(&[u8], u64), Vec<Decimal256>> =
+    Map::new("fallback_price_history");
+pub const FALLBACK_PRICE_HISTORY_LEN: Map<&[u8], u32> = Map::new("fallback_price_history_len");
+
+#[cw_serde]
+pub struct PendingExchangeRate {
+    pub exchange_rate: Decimal,
+    pub proposer: CanonicalAddr,
+    pub timestamp: u64,
+}
+
+#[cw_serde]
+pub struct FeederSubmission {
+    pub exchange_rate: Decimal,
+    pub timestamp: u64,
+}
+
+pub fn store_exchange_rate(
+    storage: &mut dyn Storage,
+    asset_info: &AssetInfo,
+    denom_key: &[u8],
+    exchange_rate: &Decimal,
+    timestamp: u64,
+) -> StdResult<()> {
+    EXCHANGE_RATES.save(storage, denom_key, exchange_rate)?;
+    EXCHANGE_RATE_ASSET_INFOS.save(storage, denom_key, asset_info)?;
+    EXCHANGE_RATES_LAST_UPDATED.save(storage, denom_key, &timestamp)?;
+    append_history(
+        storage,
+        &EXCHANGE_RATE_HISTORY,
+        &EXCHANGE_RATE_HISTORY_LEN,
+        denom_key,
+        exchange_rate,
+        timestamp,
+    )
+}
+
+pub fn store_fallback_price_observation(
+    storage: &mut dyn Storage,
+    denom_key: &[u8],
+    price_cumulatives: Vec<Decimal256>,
+    timestamp: u64,
+) -> StdResult<()> {
+    append_history(
+        storage,
+        &FALLBACK_PRICE_HISTORY,
+        &FALLBACK_PRICE_HISTORY_LEN,
+        denom_key,
+        &price_cumulatives,
+        timestamp,
+    )
+}
+
+fn append_history<'a, T: Serialize + DeserializeOwned>(
+    storage: &mut dyn Storage,
+    history: &Map<(&'a [u8], u64), T>,
+    history_len: &Map<&'a [u8], u32>,
+    denom_key: &'a [u8],
+    value: &T,
+    timestamp: u64,
+) -> StdResult<()> {
+    // updates within the same second overwrite each other
+    if !history.has(storage, (denom_key, timestamp)) {
+        let mut len = history_len
+            .may_load(storage, denom_key)?
+            .unwrap_or_default()
+            + 1;
+        if len > MAX_EXCHANGE_RATE_HISTORY {
+            let oldest = history
+                .prefix(denom_key)
+                .keys(storage, None, None, Order::Ascending)
+                .next()
+                .transpose()?;
+            if let Some(oldest) = oldest {
+                history.remove(storage, (denom_key, oldest));
+                len -= 1;
+            }
+        }
+        history_len.save(storage, denom_key, &len)?;
+    }
+
+    history.save(storage, (denom_key, timestamp), value)
+}
+
+/// the rate in effect at timestamp, or the oldest recorded rate after it
+pub fn read_reference_exchange_rate(
+    storage: &dyn Storage,
+    denom_key: &[u8],
+    timestamp: u64,
+) -> StdResult<Option<Decimal>> {
+    let rate = EXCHANGE_RATE_HISTORY
+        .prefix(denom_key)
+        .range(
+            storage,
+            None,
+            Some(Bound::inclusive(timestamp)),
+            Order::Descending,
+        )
+        .next()
+        .transpose()?;
+    if let Some((_, rate)) = rate {
+        return Ok(Some(rate));
+    }
+
+    Ok(EXCHANGE_RATE_HISTORY
+        .prefix(denom_key)
+        .range(storage, None, None, Order::Ascending)
+        .next()
+        .transpose()?
+        .map(|(_, rate)| rate))
+}
+
+// settings for pagination
+const MAX_LIMIT: u32 = 30;
+const DEFAULT_LIMIT: u32 = 10;
+pub fn read_exchange_rate_history(
+    storage: &dyn Storage,
+    denom_key: &[u8],
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<(u64, Decimal)>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    EXCHANGE_RATE_HISTORY
+        .prefix(denom_key)
+        .range(storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect()
+}
+
+/// time-weighted average of the recorded rates over [start, end], None without any record
+pub fn read_exchange_rate_twap(
+    storage: &dyn Storage,
+    denom_key: &[u8],
+    start: u64,
+    end: u64,
+) -> StdResult<Option<Decimal>> {
+    read_twap(storage, &EXCHANGE_RATE_HISTORY, denom_key, start, end)
+}
+
+/// the observation in effect at timestamp, or the oldest one after it
+pub fn read_fallback_price_observation(
+    storage: &dyn Storage,
+    denom_key: &[u8],
+    timestamp: u64,
+) -> StdResult<Option<(u64, Vec<Decimal256>)>> {
+    let observation = FALLBACK_PRICE_HISTORY
+        .prefix(denom_key)
+        .range(
+            storage,
+            None,
+            Some(Bound::inclusive(timestamp)),
+            Order::Descending,
+        )
+        .next()
+        .transpose()?;
+    if observation.is_some() {
+        return Ok(observation);
+    }
+
+    FALLBACK_PRICE_HISTORY
+        .prefix(denom_key)
+        .range(storage, None, None, Order::Ascending)
+        .next()
+        .transpose()
+}
+
+fn read_twap<'a>(
+    storage: &dyn Storage,
+    history: &Map<(&'a [u8], u64), Decimal>,
+    denom_key: &'a [u8],
+    start: u64,
+    end: u64,
+) -> StdResult<Option<Decimal>> {
+    // the rate in effect at the start of the window, if recorded
+    let mut last = history
+        .prefix(denom_key)
+        .range(
+            storage,
+            None,
+            Some(Bound::inclusive(start)),
+            Order::Descending,
+        )
+        .next()
+        .transpose()?
+        .map(|(_, rate)| (start, rate));
+
+    let mut weighted: Vec<(u64, Decimal)> = vec![];
+    for item in history.prefix(denom_key).range(
+        storage,
+        Some(Bound::exclusive(start)),
+        Some(Bound::inclusive(end)),
+        Order::Ascending,
+    ) {
+        let (timestamp, rate) = item?;
+        if let Some((last_timestamp, last_rate)) = last {
+            weighted.push((timestamp - last_timestamp, last_rate));
+        }
+        last = Some((timestamp, rate));
+    }
+
+    let (last_timestamp, last_rate) = match last {
+        Some(last) => last,
+        None => return Ok(None),
+    };
+    weighted.push((end - last_timestamp, last_rate));
+
+    // the window is shortened when the history starts inside it
+    let total: u64 = weighted.iter().map(|(duration, _)| duration).sum();
+    if total == 0 {
+        return Ok(Some(last_rate));
+    }
+
+    let mut twap = Decimal::zero();
+    for (duration, rate) in weighted {
+        twap += rate * Decimal::from_ratio(duration, total);
+    }
+
+    Ok(Some(twap))
+}
+
+pub fn remove_exchange_rate(storage: &mut dyn Storage, denom_key: &[u8]) -> StdResult<()> {
+    EXCHANGE_RATES.remove(storage, denom_key);
+    EXCHANGE_RATES_LAST_UPDATED.remove(storage, denom_key);
+    EXCHANGE_RATE_ASSET_INFOS.remove(storage, denom_key);
+
+    // a new rate starts a new history
+    let timestamps = EXCHANGE_RATE_HISTORY
+        .prefix(denom_key)
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<u64>>>()?;
+    for timestamp in timestamps {
+        EXCHANGE_RATE_HISTORY.remove(storage, (denom_key, timestamp));
+    }
+    EXCHANGE_RATE_HISTORY_LEN.remove(storage, denom_key);
+    Ok(())
+}
+
+pub fn remove_fallback_price_observations(
+    storage: &mut dyn Storage,
+    denom_key: &[u8],
+) -> StdResult<()> {
+    // observations of another source can not be compared with the new one
+    let timestamps = FALLBACK_PRICE_HISTORY
+        .prefix(denom_key)
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<u64>>>()?;
+    for timestamp in timestamps {
+        FALLBACK_PRICE_HISTORY.remove(storage, (denom_key, timestamp));
+    }
+    FALLBACK_PRICE_HISTORY_LEN.remove(storage, denom_key);
+    Ok(())
+}
+
+pub fn remove_feeder_submissions(storage: &mut dyn Storage, denom_key: &[u8]) -> StdResult<()> {
+    let feeders = FEEDER_SUBMISSIONS
+        .prefix(denom_key)
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for feeder in feeders {
+        FEEDER_SUBMISSIONS.remove(storage, (denom_key, &feeder));
+    }
+    Ok(())
+}
+
+/// asset of an exchange rate key, legacy keys are native denoms
+pub fn read_exchange_rate_asset_info(
+    storage: &dyn Storage,
+    denom_key: &[u8],
+) -> StdResult<AssetInfo> {
+    read_asset_info(storage, &EXCHANGE_RATE_ASSET_INFOS, denom_key)
+}
+
+/// asset of a tax cap key, legacy keys are native denoms
+pub fn read_tax_cap_asset_info(storage: &dyn Storage, denom_key: &[u8]) -> StdResult<AssetInfo> {
+    read_asset_info(storage, &TAX_CAP_ASSET_INFOS, denom_key)
+}
+
+fn read_asset_info(
+    storage: &dyn Storage,
+    asset_infos: &Map<&[u8], AssetInfo>,
+    denom_key: &[u8],
+) -> StdResult<AssetInfo> {
+    match asset_infos.may_load(storage, denom_key)? {
+        Some(asset_info) => Ok(asset_info),
+        None => Ok(AssetInfo::NativeToken {
+            denom: String::from_utf8_lossy(denom_key).to_string(),
+        }),
+    }
+}
+
+pub fn read_exchange_rates(
+    storage: &dyn Storage,
+    start_after: Option<&[u8]>,
+    limit: Option<u32>,
+) -> StdResult<Vec<(Vec<u8>, Decimal)>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    EXCHANGE_RATES
+        .range(storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect()
+}
+
+pub fn read_tax_caps(
+    storage: &dyn Storage,
+    start_after: Option<&[u8]>,
+    limit: Option<u32>,
+) -> StdResult<Vec<(Vec<u8>, Uint128)>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    TAX_CAP
+        .range(storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect()
+}
@@ -1,11 +1,28 @@
-use cosmwasm_std::testing::MOCK_CONTRACT_ADDR;
-use cosmwasm_std::{to_json_binary, Addr, BankMsg, Coin, CosmosMsg, Decimal, Uint128, WasmMsg};
+use cosmwasm_std::testing::{
+    mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR,
+};
+use cosmwasm_std::{
+    from_json, to_json_binary, Addr, Api, BankMsg, Coin, ContractResult, CosmosMsg, Decimal,
+    Decimal256, Deps, Env, OwnedDeps, SystemResult, Uint128, WasmMsg, WasmQuery,
+};
+use std::cell::Cell;
+use std::rc::Rc;
 
 use oraiswap::asset::{Asset, AssetInfo, ORAI_DENOM};
 use oraiswap::create_entry_points_testing;
-use oraiswap::oracle::{ExecuteMsg, OracleContract};
+use oraiswap::error::ContractError;
+use oraiswap::oracle::{
+    AllExchangeRatesResponse, AllTaxCapsResponse, AssetExchangeRateResponse,
+    ExchangeRateHistoryResponse, ExchangeRateSource, ExchangeRateTwapResponse, ExecuteMsg,
+    FallbackPrice, InstantiateMsg, MigrateMsg, OracleContract, OracleExchangeQuery,
+    OracleTreasuryQuery, PendingExchangeRateResponse, PriceSource, QueryMsg, RateLimit, TaxCapItem,
+};
+use oraiswap::pair::{CumulativePricesResponse, PoolResponse, QueryMsg as PairQueryMsg};
 use oraiswap::testing::{MockApp, APP_OWNER};
 
+use crate::contract::{execute, instantiate, migrate, query};
+use crate::state::{EXCHANGE_RATES, EXCHANGE_RATE_HISTORY_LEN, TAX_CAP};
+
 fn setup_contract() -> MockApp {
     let mut app = MockApp::new(&[(
         &APP_OWNER.to_string(),
@@ -20,12 +37,33 @@ fn setup_contract() -> MockApp {
     app
 }
 
+// oracle instantiated by admin with the default rates
+fn setup_deps() -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+    let mut deps = mock_dependencies();
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("admin", &[]),
+        InstantiateMsg {
+            name: None,
+            version: None,
+            admin: None,
+            min_rate: None,
+            max_rate: None,
+        },
+    )
+    .unwrap();
+    deps
+}
+
 #[test]
 fn proper_initialization() {
     let mut app = setup_contract();
 
     let msg = ExecuteMsg::UpdateExchangeRate {
-        denom: "usdt".to_string(),
+        asset_info: AssetInfo::NativeToken {
+            denom: "usdt".to_string(),
+        },
         exchange_rate: Decimal::percent(10), // 1 orai = 10 usdt
     };
 
@@ -46,7 +84,9 @@ fn proper_initialization() {
     assert_eq!("10", exchange_rate_res.item.exchange_rate.to_string());
 
     let msg = ExecuteMsg::UpdateExchangeRate {
-        denom: "airi".to_string(),
+        asset_info: AssetInfo::NativeToken {
+            denom: "airi".to_string(),
+        },
         exchange_rate: Decimal::percent(1), // 1 orai = 100 airi
     };
 
@@ -186,3 +226,967 @@ fn test_asset() {
         })
     );
 }
+
+#[test]
+fn feeders_median_and_staleness() {
+    let mut deps = setup_deps();
+    let mut env = mock_env();
+
+    for feeder in ["feeder1", "feeder2", "feeder3"] {
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("admin", &[]),
+            ExecuteMsg::AddFeeder {
+                feeder: Addr::unchecked(feeder),
+            },
+        )
+        .unwrap();
+    }
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("admin", &[]),
+        ExecuteMsg::UpdateFeedConfig {
+            quorum: Some(3),
+            max_age: Some(60),
+            reject_stale: Some(false),
+        },
+    )
+    .unwrap();
+
+    // only whitelisted feeders can submit
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("admin", &[]),
+        ExecuteMsg::FeedExchangeRate {
+            asset_info: AssetInfo::NativeToken {
+                denom: "usdt".to_string(),
+            },
+            exchange_rate: Decimal::percent(10),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    // rate is not stored until quorum is reached
+    for (feeder, rate) in [("feeder1", 10u64), ("feeder2", 30u64)] {
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(feeder, &[]),
+            ExecuteMsg::FeedExchangeRate {
+                asset_info: AssetInfo::NativeToken {
+                    denom: "usdt".to_string(),
+                },
+                exchange_rate: Decimal::percent(rate),
+            },
+        )
+        .unwrap();
+    }
+    let query_msg = QueryMsg::Exchange(OracleExchangeQuery::AssetExchangeRate {
+        base_asset_info: None,
+        quote_asset_info: AssetInfo::NativeToken {
+            denom: "usdt".to_string(),
+        },
+    });
+    query(deps.as_ref(), env.clone(), query_msg.clone()).unwrap_err();
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("feeder3", &[]),
+        ExecuteMsg::FeedExchangeRate {
+            asset_info: AssetInfo::NativeToken {
+                denom: "usdt".to_string(),
+            },
+            exchange_rate: Decimal::percent(12),
+        },
+    )
+    .unwrap();
+
+    let res: AssetExchangeRateResponse =
+        from_json(query(deps.as_ref(), env.clone(), query_msg.clone()).unwrap()).unwrap();
+    assert_eq!(res.item.exchange_rate, Decimal::percent(12));
+    assert_eq!(res.item.last_updated, Some(env.block.time.seconds()));
+    assert!(!res.item.stale);
+
+    // accepted submissions are cleared, the next rate needs a new quorum
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("feeder1", &[]),
+        ExecuteMsg::FeedExchangeRate {
+            asset_info: AssetInfo::NativeToken {
+                denom: "usdt".to_string(),
+            },
+            exchange_rate: Decimal::percent(20),
+        },
+    )
+    .unwrap();
+    let res: AssetExchangeRateResponse =
+        from_json(query(deps.as_ref(), env.clone(), query_msg.clone()).unwrap()).unwrap();
+    assert_eq!(res.item.exchange_rate, Decimal::percent(12));
+
+    // rate is flagged after max_age, then rejected once configured
+    env.block.time = env.block.time.plus_seconds(61);
+    let res: AssetExchangeRateResponse =
+        from_json(query(deps.as_ref(), env.clone(), query_msg.clone()).unwrap()).unwrap();
+    assert!(res.item.stale);
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("admin", &[]),
+        ExecuteMsg::UpdateFeedConfig {
+            quorum: None,
+            max_age: None,
+            reject_stale: Some(true),
+        },
+    )
+    .unwrap();
+    query(deps.as_ref(), env.clone(), query_msg).unwrap_err();
+}
+
+#[test]
+fn exchange_rate_history_and_twap() {
+    let mut deps = setup_deps();
+    let mut env = mock_env();
+    let start = env.block.time.seconds();
+
+    for rate in [10u64, 30u64] {
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("admin", &[]),
+            ExecuteMsg::UpdateExchangeRate {
+                asset_info: AssetInfo::NativeToken {
+                    denom: "usdt".to_string(),
+                },
+                exchange_rate: Decimal::percent(rate),
+            },
+        )
+        .unwrap();
+        env.block.time = env.block.time.plus_seconds(100);
+    }
+
+    let res: ExchangeRateHistoryResponse = from_json(
+        query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Exchange(OracleExchangeQuery::ExchangeRateHistory {
+                asset_info: AssetInfo::NativeToken {
+                    denom: "usdt".to_string(),
+                },
+                start_after: None,
+                limit: None,
+            }),
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.items
+            .iter()
+            .map(|item| (item.timestamp, item.exchange_rate))
+            .collect::<Vec<_>>(),
+        vec![
+            (start, Decimal::percent(10)),
+            (start + 100, Decimal::percent(30))
+        ]
+    );
+
+    // 100s at 0.1 and 100s at 0.3
+    let res: ExchangeRateTwapResponse = from_json(
+        query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Exchange(OracleExchangeQuery::ExchangeRateTwap {
+                base_asset_info: None,
+                quote_asset_info: AssetInfo::NativeToken {
+                    denom: "usdt".to_string(),
+                },
+                window_seconds: 200,
+            }),
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.exchange_rate, Decimal::percent(20));
+
+    // only the latest rate is in effect during the last 50s
+    let res: ExchangeRateTwapResponse = from_json(
+        query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Exchange(OracleExchangeQuery::ExchangeRateTwap {
+                base_asset_info: None,
+                quote_asset_info: AssetInfo::NativeToken {
+                    denom: "usdt".to_string(),
+                },
+                window_seconds: 50,
+            }),
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.exchange_rate, Decimal::percent(30));
+
+    // deleting the rate drops its history
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("admin", &[]),
+        ExecuteMsg::DeleteExchangeRate {
+            asset_info: AssetInfo::NativeToken {
+                denom: "usdt".to_string(),
+            },
+        },
+    )
+    .unwrap();
+    let res: ExchangeRateHistoryResponse = from_json(
+        query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Exchange(OracleExchangeQuery::ExchangeRateHistory {
+                asset_info: AssetInfo::NativeToken {
+                    denom: "usdt".to_string(),
+                },
+                start_after: None,
+                limit: None,
+            }),
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert!(res.items.is_empty());
+    assert!(!EXCHANGE_RATE_HISTORY_LEN.has(&deps.storage, b"usdt"));
+}
+
+#[test]
+fn denom_tax_rate_and_tax_exempt() {
+    let mut app = setup_contract();
+
+    app.set_tax(Decimal::percent(1), &[("uusd", 1000000u128)]);
+
+    let oracle_addr = app.oracle_addr.clone();
+    app.execute(
+        Addr::unchecked(APP_OWNER),
+        oracle_addr.clone(),
+        &ExecuteMsg::UpdateDenomTaxRate {
+            asset_info: AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+            rate: Some(Decimal::permille(5)),
+        },
+        &[],
+    )
+    .unwrap();
+
+    let native_token_asset = Asset {
+        amount: Uint128::from(123123u128),
+        info: AssetInfo::NativeToken {
+            denom: "uusd".to_string(),
+        },
+    };
+    let orai_oracle = OracleContract(app.oracle_addr.clone());
+
+    // denom rate overrides the global rate
+    assert_eq!(
+        native_token_asset
+            .compute_tax(&orai_oracle, &app.as_querier().into_empty())
+            .unwrap(),
+        Uint128::from(613u128)
+    );
+
+    app.execute(
+        Addr::unchecked(APP_OWNER),
+        oracle_addr,
+        &ExecuteMsg::AddTaxExempt {
+            address: Addr::unchecked("router0000"),
+        },
+        &[],
+    )
+    .unwrap();
+
+    assert_eq!(
+        native_token_asset
+            .compute_tax_to(
+                &orai_oracle,
+                &app.as_querier().into_empty(),
+                &Addr::unchecked("router0000")
+            )
+            .unwrap(),
+        Uint128::zero()
+    );
+    assert_eq!(
+        native_token_asset
+            .into_msg(
+                Some(&orai_oracle),
+                &app.as_querier().into_empty(),
+                Addr::unchecked("router0000")
+            )
+            .unwrap(),
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "router0000".into(),
+            amount: vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::from(123123u128),
+            }]
+        })
+    );
+    assert_eq!(
+        native_token_asset
+            .compute_tax_to(
+                &orai_oracle,
+                &app.as_querier().into_empty(),
+                &Addr::unchecked("addr0000")
+            )
+            .unwrap(),
+        Uint128::from(613u128)
+    );
+}
+
+#[test]
+fn cw20_exchange_rate() {
+    let mut deps = setup_deps();
+
+    let token_info = AssetInfo::Token {
+        contract_addr: Addr::unchecked("asset0000"),
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("admin", &[]),
+        ExecuteMsg::UpdateExchangeRate {
+            asset_info: token_info.clone(),
+            exchange_rate: Decimal::percent(10),
+        },
+    )
+    .unwrap();
+
+    // cw20 rates are keyed like the factory does
+    let key = token_info.to_vec(deps.as_ref().api).unwrap();
+    assert_eq!(
+        key,
+        deps.api
+            .addr_canonicalize("asset0000")
+            .unwrap()
+            .as_slice()
+            .to_vec()
+    );
+    assert_eq!(
+        EXCHANGE_RATES.load(&deps.storage, &key).unwrap(),
+        Decimal::percent(10)
+    );
+
+    let res: AssetExchangeRateResponse = from_json(
+        query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Exchange(OracleExchangeQuery::AssetExchangeRate {
+                base_asset_info: Some(token_info.clone()),
+                quote_asset_info: AssetInfo::NativeToken {
+                    denom: ORAI_DENOM.to_string(),
+                },
+            }),
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.item.exchange_rate, Decimal::from_ratio(10u128, 1u128));
+}
+
+#[test]
+fn migrate_rekeys_legacy_cw20_entries() {
+    let mut deps = setup_deps();
+    let env = mock_env();
+
+    // legacy entries are keyed by the denom bytes, even for cw20 addresses
+    EXCHANGE_RATES
+        .save(&mut deps.storage, b"asset0000", &Decimal::percent(10))
+        .unwrap();
+    TAX_CAP
+        .save(&mut deps.storage, b"asset0000", &Uint128::from(100u128))
+        .unwrap();
+
+    migrate(deps.as_mut(), env.clone(), MigrateMsg {}).unwrap();
+
+    let key = AssetInfo::from_denom(deps.as_ref().api, "asset0000")
+        .to_vec(deps.as_ref().api)
+        .unwrap();
+    assert!(!EXCHANGE_RATES.has(&deps.storage, b"asset0000"));
+    assert!(!TAX_CAP.has(&deps.storage, b"asset0000"));
+
+    let res: AllExchangeRatesResponse = from_json(
+        query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Exchange(OracleExchangeQuery::AllExchangeRates {
+                start_after: None,
+                limit: None,
+            }),
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    let item = res
+        .items
+        .iter()
+        .find(|item| {
+            item.asset_info
+                == AssetInfo::Token {
+                    contract_addr: Addr::unchecked("asset0000"),
+                }
+        })
+        .unwrap();
+    assert_eq!(item.exchange_rate, Decimal::percent(10));
+    assert_eq!(item.last_updated, Some(env.block.time.seconds()));
+    assert_eq!(
+        EXCHANGE_RATES.load(&deps.storage, &key).unwrap(),
+        Decimal::percent(10)
+    );
+
+    assert_eq!(
+        TAX_CAP.load(&deps.storage, &key).unwrap(),
+        Uint128::from(100u128)
+    );
+}
+
+#[test]
+fn exchange_rate_circuit_breaker() {
+    let mut deps = setup_deps();
+    let mut env = mock_env();
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("admin", &[]),
+        ExecuteMsg::UpdateExchangeRate {
+            asset_info: AssetInfo::NativeToken {
+                denom: "usdt".to_string(),
+            },
+            exchange_rate: Decimal::percent(100),
+        },
+    )
+    .unwrap();
+
+    let usdt_key = b"usdt".to_vec();
+
+    // at most 10% per update
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("admin", &[]),
+        ExecuteMsg::UpdateRateLimit {
+            asset_info: AssetInfo::NativeToken {
+                denom: "usdt".to_string(),
+            },
+            rate_limit: Some(RateLimit {
+                max_change: Some(Decimal::percent(10)),
+                max_change_per_hour: None,
+                require_confirmation: false,
+            }),
+        },
+    )
+    .unwrap();
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("admin", &[]),
+        ExecuteMsg::UpdateExchangeRate {
+            asset_info: AssetInfo::NativeToken {
+                denom: "usdt".to_string(),
+            },
+            exchange_rate: Decimal::percent(150),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::RateChangeExceeded {
+            denom: "usdt".to_string()
+        }
+    );
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("admin", &[]),
+        ExecuteMsg::UpdateExchangeRate {
+            asset_info: AssetInfo::NativeToken {
+                denom: "usdt".to_string(),
+            },
+            exchange_rate: Decimal::percent(105),
+        },
+    )
+    .unwrap();
+
+    // switch to manual confirmation, at most 10% per hour
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("admin", &[]),
+        ExecuteMsg::UpdateRateLimit {
+            asset_info: AssetInfo::NativeToken {
+                denom: "usdt".to_string(),
+            },
+            rate_limit: Some(RateLimit {
+                max_change: None,
+                max_change_per_hour: Some(Decimal::percent(10)),
+                require_confirmation: true,
+            }),
+        },
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("admin", &[]),
+        ExecuteMsg::AddConfirmer {
+            confirmer: Addr::unchecked("confirmer"),
+        },
+    )
+    .unwrap();
+
+    // 100 -> 120 within the same hour is queued
+    env.block.time = env.block.time.plus_seconds(60);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("admin", &[]),
+        ExecuteMsg::UpdateExchangeRate {
+            asset_info: AssetInfo::NativeToken {
+                denom: "usdt".to_string(),
+            },
+            exchange_rate: Decimal::percent(120),
+        },
+    )
+    .unwrap();
+    let rate: Decimal = EXCHANGE_RATES
+        .load(deps.as_ref().storage, &usdt_key)
+        .unwrap();
+    assert_eq!(rate, Decimal::percent(105));
+
+    let pending: Option<PendingExchangeRateResponse> = from_json(
+        query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Exchange(OracleExchangeQuery::PendingExchangeRate {
+                asset_info: AssetInfo::NativeToken {
+                    denom: "usdt".to_string(),
+                },
+            }),
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    let pending = pending.unwrap();
+    assert_eq!(pending.exchange_rate, Decimal::percent(120));
+    assert_eq!(pending.proposer, Addr::unchecked("admin"));
+
+    // the proposer cannot confirm its own update
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("admin", &[]),
+        ExecuteMsg::ConfirmExchangeRate {
+            asset_info: AssetInfo::NativeToken {
+                denom: "usdt".to_string(),
+            },
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("confirmer", &[]),
+        ExecuteMsg::ConfirmExchangeRate {
+            asset_info: AssetInfo::NativeToken {
+                denom: "usdt".to_string(),
+            },
+        },
+    )
+    .unwrap();
+    let rate: Decimal = EXCHANGE_RATES
+        .load(deps.as_ref().storage, &usdt_key)
+        .unwrap();
+    assert_eq!(rate, Decimal::percent(120));
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("confirmer", &[]),
+        ExecuteMsg::RejectExchangeRate {
+            asset_info: AssetInfo::NativeToken {
+                denom: "usdt".to_string(),
+            },
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::NoPendingExchangeRate {
+            denom: "usdt".to_string()
+        }
+    );
+}
+
+#[test]
+fn all_exchange_rates_and_tax_caps() {
+    let mut deps = setup_deps();
+    let env = mock_env();
+
+    let usdt_info = AssetInfo::NativeToken {
+        denom: "usdt".to_string(),
+    };
+    let atom_info = AssetInfo::NativeToken {
+        denom: "atom".to_string(),
+    };
+    let token_info = AssetInfo::Token {
+        contract_addr: Addr::unchecked("asset0000"),
+    };
+    for (asset_info, rate) in [
+        (usdt_info.clone(), 10u64),
+        (atom_info.clone(), 20u64),
+        (token_info.clone(), 30u64),
+    ] {
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("admin", &[]),
+            ExecuteMsg::UpdateExchangeRate {
+                asset_info: asset_info.clone(),
+                exchange_rate: Decimal::percent(rate),
+            },
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("admin", &[]),
+            ExecuteMsg::UpdateTaxCap {
+                asset_info,
+                cap: Uint128::from(rate),
+            },
+        )
+        .unwrap();
+    }
+
+    // walk the rates two at a time
+    let mut items = vec![];
+    let mut start_after = None;
+    loop {
+        let res: AllExchangeRatesResponse = from_json(
+            query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::Exchange(OracleExchangeQuery::AllExchangeRates {
+                    start_after: start_after.clone(),
+                    limit: Some(2),
+                }),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        if res.items.is_empty() {
+            break;
+        }
+        start_after = res.items.last().map(|item| item.asset_info.clone());
+        items.extend(res.items);
+    }
+    items.sort_by_key(|item| item.asset_info.to_string());
+    assert_eq!(
+        items
+            .iter()
+            .map(|item| (
+                item.asset_info.to_string(),
+                item.exchange_rate,
+                item.last_updated
+            ))
+            .collect::<Vec<_>>(),
+        vec![
+            (
+                "asset0000".to_string(),
+                Decimal::percent(30),
+                Some(env.block.time.seconds())
+            ),
+            (
+                "atom".to_string(),
+                Decimal::percent(20),
+                Some(env.block.time.seconds())
+            ),
+            (ORAI_DENOM.to_string(), Decimal::one(), None),
+            (
+                "usdt".to_string(),
+                Decimal::percent(10),
+                Some(env.block.time.seconds())
+            ),
+        ]
+    );
+
+    // caps are keyed like the rates, walk them the same way
+    let mut caps = vec![];
+    let mut start_after = None;
+    loop {
+        let res: AllTaxCapsResponse = from_json(
+            query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::Treasury(OracleTreasuryQuery::AllTaxCaps {
+                    start_after: start_after.clone(),
+                    limit: Some(2),
+                }),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        if res.items.is_empty() {
+            break;
+        }
+        start_after = res.items.last().map(|item| item.asset_info.clone());
+        caps.extend(res.items);
+    }
+    caps.sort_by_key(|item| item.asset_info.to_string());
+    assert_eq!(
+        caps,
+        vec![
+            TaxCapItem {
+                asset_info: token_info,
+                cap: Uint128::from(30u128),
+                last_updated: Some(env.block.time.seconds()),
+            },
+            TaxCapItem {
+                asset_info: atom_info,
+                cap: Uint128::from(20u128),
+                last_updated: Some(env.block.time.seconds()),
+            },
+            TaxCapItem {
+                asset_info: usdt_info,
+                cap: Uint128::from(10u128),
+                last_updated: Some(env.block.time.seconds()),
+            },
+        ]
+    );
+}
+
+#[test]
+fn fallback_price_from_pair() {
+    let mut deps = setup_deps();
+    let mut env = mock_env();
+
+    // usdt/orai pair holding 2000 usdt and 1000 orai, with its usdt per orai cumulative price
+    let usdt_info = AssetInfo::NativeToken {
+        denom: "usdt".to_string(),
+    };
+    let price1_cumulative = Rc::new(Cell::new(Decimal256::percent(10000)));
+    let pool_assets = [
+        Asset {
+            info: usdt_info.clone(),
+            amount: Uint128::from(2000u128),
+        },
+        Asset {
+            info: AssetInfo::NativeToken {
+                denom: ORAI_DENOM.to_string(),
+            },
+            amount: Uint128::from(1000u128),
+        },
+    ];
+    let pair_price1_cumulative = price1_cumulative.clone();
+    deps.querier.update_wasm(move |query| {
+        let res = match query {
+            WasmQuery::Smart { msg, .. } => match from_json(msg).unwrap() {
+                PairQueryMsg::Pool {} => to_json_binary(&PoolResponse {
+                    assets: pool_assets.clone(),
+                    total_share: Uint128::from(1000u128),
+                }),
+                PairQueryMsg::CumulativePrices {} => to_json_binary(&CumulativePricesResponse {
+                    assets: pool_assets.clone(),
+                    price0_cumulative: Decimal256::zero(),
+                    price1_cumulative: pair_price1_cumulative.get(),
+                    timestamp: 0,
+                }),
+                _ => panic!("unexpected pair query"),
+            },
+            _ => panic!("unexpected wasm query"),
+        };
+        SystemResult::Ok(ContractResult::Ok(res.unwrap()))
+    });
+
+    // the route must end with orai
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("admin", &[]),
+        ExecuteMsg::UpdateFallbackPrice {
+            asset_info: usdt_info.clone(),
+            fallback: Some(FallbackPrice {
+                source: PriceSource::Route {
+                    factory_addr: Addr::unchecked("factory"),
+                    asset_infos: vec![usdt_info.clone()],
+                },
+                twap_window: None,
+            }),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::InvalidPriceSource {});
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("admin", &[]),
+        ExecuteMsg::UpdateFallbackPrice {
+            asset_info: usdt_info.clone(),
+            fallback: Some(FallbackPrice {
+                source: PriceSource::Pair {
+                    pair_addr: Addr::unchecked("pair"),
+                },
+                twap_window: Some(100),
+            }),
+        },
+    )
+    .unwrap();
+
+    let query_usdt = |deps: Deps, env: &Env| {
+        let res: AssetExchangeRateResponse = from_json(
+            query(
+                deps,
+                env.clone(),
+                QueryMsg::Exchange(OracleExchangeQuery::AssetExchangeRate {
+                    base_asset_info: None,
+                    quote_asset_info: AssetInfo::NativeToken {
+                        denom: "usdt".to_string(),
+                    },
+                }),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        (res.item.exchange_rate, res.item.source)
+    };
+
+    // no observation yet, the current reserves are used
+    assert_eq!(
+        query_usdt(deps.as_ref(), &env),
+        (Decimal::percent(200), ExchangeRateSource::PairReserves)
+    );
+
+    // only the admin or a feeder can observe
+    let observe = ExecuteMsg::ObserveFallbackPrice {
+        asset_info: usdt_info.clone(),
+    };
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("anyone", &[]),
+        observe.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("admin", &[]),
+        observe.clone(),
+    )
+    .unwrap();
+
+    // once per block
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("admin", &[]),
+        observe.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::FallbackPriceObserved {
+            denom: "usdt".to_string()
+        }
+    );
+
+    // the twap comes from the cumulative price, averaging 300% since the observation
+    // whatever the current reserves
+    env.block.time = env.block.time.plus_seconds(50);
+    price1_cumulative.set(Decimal256::percent(10000 + 300 * 50));
+    assert_eq!(
+        query_usdt(deps.as_ref(), &env),
+        (Decimal::percent(300), ExchangeRateSource::PairTwap)
+    );
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("admin", &[]),
+        ExecuteMsg::AddFeeder {
+            feeder: Addr::unchecked("feeder1"),
+        },
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("feeder1", &[]),
+        observe,
+    )
+    .unwrap();
+
+    // a pushed rate takes precedence
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("admin", &[]),
+        ExecuteMsg::UpdateExchangeRate {
+            asset_info: usdt_info.clone(),
+            exchange_rate: Decimal::percent(150),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        query_usdt(deps.as_ref(), &env),
+        (Decimal::percent(150), ExchangeRateSource::Pushed)
+    );
+
+    // the listing falls back like the single rate queries once the pushed rate is stale
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("admin", &[]),
+        ExecuteMsg::UpdateFeedConfig {
+            quorum: None,
+            max_age: Some(10),
+            reject_stale: None,
+        },
+    )
+    .unwrap();
+    env.block.time = env.block.time.plus_seconds(11);
+    price1_cumulative.set(Decimal256::percent(10000 + 300 * 61));
+    let res: AllExchangeRatesResponse = from_json(
+        query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Exchange(OracleExchangeQuery::AllExchangeRates {
+                start_after: None,
+                limit: None,
+            }),
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    let item = res
+        .items
+        .into_iter()
+        .find(|item| item.asset_info == usdt_info)
+        .unwrap();
+    assert_eq!(
+        (item.exchange_rate, item.source, item.stale),
+        (Decimal::percent(300), ExchangeRateSource::PairTwap, false)
+    );
+}
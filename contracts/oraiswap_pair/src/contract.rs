@@ -1,11 +1,13 @@
-use crate::state::{ADMIN, PAIR_INFO, WHITELISTED, WHITELISTED_TRADERS};
+use crate::state::{
+    PriceCumulative, ADMIN, PAIR_INFO, PRICE_CUMULATIVE, WHITELISTED, WHITELISTED_TRADERS,
+};
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 
 use cosmwasm_std::{
     from_json, to_json_binary, Addr, Binary, CanonicalAddr, Coin, CosmosMsg, Decimal, Decimal256,
-    Deps, DepsMut, Env, MessageInfo, Reply, Response, StdError, StdResult, SubMsg, Uint128,
-    Uint256, WasmMsg,
+    Deps, DepsMut, Env, MessageInfo, Reply, Response, StdError, StdResult, Storage, SubMsg,
+    Uint128, Uint256, WasmMsg,
 };
 
 use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg, MinterResponse};
@@ -15,9 +17,9 @@ use oraiswap::asset::{Asset, AssetInfo, PairInfoRaw};
 use oraiswap::error::ContractError;
 use oraiswap::oracle::OracleContract;
 use oraiswap::pair::{
-    compute_offer_amount, compute_swap, Cw20HookMsg, ExecuteMsg, InstantiateMsg, MigrateMsg,
-    PairResponse, PoolResponse, QueryMsg, ReverseSimulationResponse, SimulationResponse,
-    DEFAULT_COMMISSION_RATE,
+    compute_offer_amount, compute_swap, CumulativePricesResponse, Cw20HookMsg, ExecuteMsg,
+    InstantiateMsg, MigrateMsg, PairResponse, PoolResponse, QueryMsg, ReverseSimulationResponse,
+    SimulationResponse, DEFAULT_COMMISSION_RATE,
 };
 use oraiswap::querier::query_supply;
 use oraiswap::response::MsgInstantiateContractResponse;
@@ -294,6 +296,15 @@ pub fn provide_liquidity(
         return Err(ContractError::InvalidZeroAmount {});
     }
 
+    update_price_cumulative(
+        deps.storage,
+        &env,
+        [
+            pools[0].amount.checked_add(deposits[0])?,
+            pools[1].amount.checked_add(deposits[1])?,
+        ],
+    )?;
+
     // mint LP token to sender
     let receiver = receiver.unwrap_or(info.sender.clone());
     messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
@@ -347,6 +358,15 @@ pub fn withdraw_liquidity(
         })
         .collect();
 
+    update_price_cumulative(
+        deps.storage,
+        &env,
+        [
+            pools[0].amount.checked_sub(refund_assets[0].amount)?,
+            pools[1].amount.checked_sub(refund_assets[1].amount)?,
+        ],
+    )?;
+
     let oracle_contract = OracleContract(deps.api.addr_humanize(&pair_info.oracle_addr)?);
 
     let messages = vec![
@@ -442,6 +462,15 @@ pub fn swap(
         spread_amount,
     )?;
 
+    let offer_reserve = offer_pool.amount.checked_add(offer_amount)?;
+    let ask_reserve = ask_pool.amount.checked_sub(return_amount)?;
+    let reserves = if offer_pool.info.eq(&pools[0].info) {
+        [offer_reserve, ask_reserve]
+    } else {
+        [ask_reserve, offer_reserve]
+    };
+    update_price_cumulative(deps.storage, &env, reserves)?;
+
     // compute tax
     let return_asset = Asset {
         info: ask_pool.info.clone(),
@@ -450,8 +479,8 @@ pub fn swap(
 
     let oracle_contract = OracleContract(deps.api.addr_humanize(&pair_info.oracle_addr)?);
 
-    let tax_amount = return_asset.compute_tax(&oracle_contract, &deps.querier)?;
     let receiver = to.unwrap_or_else(|| sender.clone());
+    let tax_amount = return_asset.compute_tax_to(&oracle_contract, &deps.querier, &receiver)?;
 
     // update oracle_contract
     let mut messages: Vec<CosmosMsg> = vec![];
@@ -479,6 +508,44 @@ pub fn swap(
     ]))
 }
 
+/// accumulates the price of the reserves left by the previous trades over the time elapsed since then,
+/// then records the reserves left by this one, so a price moved within a block is never accumulated
+pub fn update_price_cumulative(
+    storage: &mut dyn Storage,
+    env: &Env,
+    reserves: [Uint128; 2],
+) -> StdResult<()> {
+    let now = env.block.time.seconds();
+    let mut cumulative = match PRICE_CUMULATIVE.may_load(storage)? {
+        Some(cumulative) => cumulative,
+        None => PriceCumulative {
+            reserves,
+            price0_cumulative: Decimal256::zero(),
+            price1_cumulative: Decimal256::zero(),
+            block_time_last: now,
+        },
+    };
+    accumulate_price(&mut cumulative, now)?;
+    cumulative.reserves = reserves;
+    PRICE_CUMULATIVE.save(storage, &cumulative)
+}
+
+fn accumulate_price(cumulative: &mut PriceCumulative, now: u64) -> StdResult<()> {
+    let elapsed = now.saturating_sub(cumulative.block_time_last);
+    let [reserve0, reserve1] = cumulative.reserves;
+    if elapsed > 0 && !reserve0.is_zero() && !reserve1.is_zero() {
+        let elapsed = Decimal256::from_ratio(elapsed, 1u64);
+        cumulative.price0_cumulative = cumulative
+            .price0_cumulative
+            .checked_add(Decimal256::from_ratio(reserve1, reserve0).checked_mul(elapsed)?)?;
+        cumulative.price1_cumulative = cumulative
+            .price1_cumulative
+            .checked_add(Decimal256::from_ratio(reserve0, reserve1).checked_mul(elapsed)?)?;
+    }
+    cumulative.block_time_last = cumulative.block_time_last.max(now);
+    Ok(())
+}
+
 fn execute_register_traders(
     deps: DepsMut,
     info: MessageInfo,
@@ -545,7 +612,7 @@ fn assert_is_open_for_whitelisted_trader(deps: Deps, trader: Addr) -> Result<(),
     Ok(())
 }
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> Result<Binary, ContractError> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> Result<Binary, ContractError> {
     match msg {
         QueryMsg::Pair {} => Ok(to_json_binary(&query_pair_info(deps)?)?),
         QueryMsg::Pool {} => Ok(to_json_binary(&query_pool(deps)?)?),
@@ -559,6 +626,7 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> Result<Binary, ContractErr
             Ok(to_json_binary(&query_trader_is_whitelisted(deps, trader)?)?)
         }
         QueryMsg::Admin {} => Ok(to_json_binary(&query_admin(deps)?)?),
+        QueryMsg::CumulativePrices {} => Ok(to_json_binary(&query_cumulative_prices(deps, env)?)?),
     }
 }
 
@@ -598,6 +666,31 @@ pub fn query_pool(deps: Deps) -> Result<PoolResponse, ContractError> {
     Ok(resp)
 }
 
+/// the accumulators are carried forward to the current block with the recorded reserves,
+/// which only change once the pair itself is traded
+pub fn query_cumulative_prices(deps: Deps, env: Env) -> StdResult<CumulativePricesResponse> {
+    let pair_info: PairInfoRaw = PAIR_INFO.load(deps.storage)?;
+    let mut cumulative = PRICE_CUMULATIVE.load(deps.storage)?;
+    let now = env.block.time.seconds();
+    accumulate_price(&mut cumulative, now)?;
+
+    Ok(CumulativePricesResponse {
+        assets: [
+            Asset {
+                info: pair_info.asset_infos[0].to_normal(deps.api)?,
+                amount: cumulative.reserves[0],
+            },
+            Asset {
+                info: pair_info.asset_infos[1].to_normal(deps.api)?,
+                amount: cumulative.reserves[1],
+            },
+        ],
+        price0_cumulative: cumulative.price0_cumulative,
+        price1_cumulative: cumulative.price1_cumulative,
+        timestamp: now,
+    })
+}
+
 pub fn query_simulation(
     deps: Deps,
     offer_asset: Asset,
@@ -1,4 +1,5 @@
-use cosmwasm_std::{Addr, CanonicalAddr};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, CanonicalAddr, Decimal256, Uint128};
 use cw_storage_plus::{Item, Map};
 use oraiswap::asset::PairInfoRaw;
 
@@ -9,6 +10,17 @@ pub const ADMIN: Item<CanonicalAddr> = Item::new("\u{0}\u{5}admin");
 pub const WHITELISTED: Item<bool> = Item::new("\u{0}\u{11}whitelisted");
 pub const WHITELISTED_TRADERS: Map<&Addr, bool> = Map::new("\u{0}\u{19}whitelisted_traders");
 
+/// Time-weighted price accumulators, advanced with the reserves recorded by the previous trade
+#[cw_serde]
+pub struct PriceCumulative {
+    pub reserves: [Uint128; 2],
+    pub price0_cumulative: Decimal256,
+    pub price1_cumulative: Decimal256,
+    pub block_time_last: u64,
+}
+
+pub const PRICE_CUMULATIVE: Item<PriceCumulative> = Item::new("price_cumulative");
+
 #[cfg(test)]
 mod test {
 
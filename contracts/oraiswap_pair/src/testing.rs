@@ -1,9 +1,13 @@
-use cosmwasm_std::testing::MOCK_CONTRACT_ADDR;
-use cosmwasm_std::{attr, to_json_binary, Addr, Coin, Decimal, Uint128};
+use crate::contract::{query_cumulative_prices, update_price_cumulative};
+use crate::state::PAIR_INFO;
+use cosmwasm_std::testing::{mock_dependencies, mock_env, MOCK_CONTRACT_ADDR};
+use cosmwasm_std::{attr, to_json_binary, Addr, Api, Coin, Decimal, Decimal256, Uint128};
 use cw20::Cw20ReceiveMsg;
-use oraiswap::asset::{Asset, AssetInfo, ORAI_DENOM};
+use oraiswap::asset::{Asset, AssetInfo, PairInfoRaw, ORAI_DENOM};
 use oraiswap::create_entry_points_testing;
-use oraiswap::pair::{Cw20HookMsg, ExecuteMsg, InstantiateMsg, PairResponse};
+use oraiswap::pair::{
+    Cw20HookMsg, ExecuteMsg, InstantiateMsg, PairResponse, DEFAULT_COMMISSION_RATE,
+};
 use oraiswap::testing::{MockApp, ATOM_DENOM};
 
 #[test]
@@ -607,3 +611,48 @@ fn test_pool_whitelist_for_trader() {
     )
     .unwrap();
 }
+
+#[test]
+fn cumulative_prices() {
+    let mut deps = mock_dependencies();
+    PAIR_INFO
+        .save(
+            &mut deps.storage,
+            &PairInfoRaw {
+                oracle_addr: deps.api.addr_canonicalize("oracle0000").unwrap(),
+                asset_infos: [
+                    AssetInfo::NativeToken {
+                        denom: ORAI_DENOM.to_string(),
+                    }
+                    .to_raw(&deps.api)
+                    .unwrap(),
+                    AssetInfo::NativeToken {
+                        denom: ATOM_DENOM.to_string(),
+                    }
+                    .to_raw(&deps.api)
+                    .unwrap(),
+                ],
+                contract_addr: deps.api.addr_canonicalize(MOCK_CONTRACT_ADDR).unwrap(),
+                liquidity_token: deps.api.addr_canonicalize("liquidity0000").unwrap(),
+                commission_rate: DEFAULT_COMMISSION_RATE.to_string(),
+            },
+        )
+        .unwrap();
+
+    let mut env = mock_env();
+    update_price_cumulative(&mut deps.storage, &env, [100u128.into(), 200u128.into()]).unwrap();
+
+    // the first trade of a later block accumulates the reserves left by the previous one,
+    // a price moved and restored within that block is never accumulated
+    env.block.time = env.block.time.plus_seconds(10);
+    update_price_cumulative(&mut deps.storage, &env, [400u128.into(), 50u128.into()]).unwrap();
+    update_price_cumulative(&mut deps.storage, &env, [100u128.into(), 100u128.into()]).unwrap();
+
+    env.block.time = env.block.time.plus_seconds(5);
+    let res = query_cumulative_prices(deps.as_ref(), env.clone()).unwrap();
+    assert_eq!(res.price0_cumulative, Decimal256::from_ratio(25u128, 1u128));
+    assert_eq!(res.price1_cumulative, Decimal256::from_ratio(10u128, 1u128));
+    assert_eq!(res.timestamp, env.block.time.seconds());
+    assert_eq!(res.assets[0].amount, Uint128::from(100u128));
+    assert_eq!(res.assets[1].amount, Uint128::from(100u128));
+}
@@ -2,22 +2,30 @@
 use cosmwasm_std::entry_point;
 
 use cosmwasm_std::{
-    from_json, to_json_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdError,
-    StdResult, Uint128,
+    from_json, to_json_binary, Addr, Binary, Decimal, Decimal256, Deps, DepsMut, Env, MessageInfo,
+    QuerierWrapper, Response, StdError, StdResult, Uint128,
 };
 use oraiswap::error::ContractError;
 
-use crate::operations::{execute_swap_operation, execute_swap_operations};
+use crate::operations::{
+    execute_split_swap, execute_swap_operation, execute_swap_operations,
+    execute_swap_operations_exact_out, execute_transfer_excess, load_converter,
+    query_orderbook_direction,
+};
 use crate::state::{Config, CONFIG};
 
 use cw20::Cw20ReceiveMsg;
 use oraiswap::asset::{Asset, AssetInfo, PairInfo};
+use oraiswap::math::compute_price_impact;
 use oraiswap::oracle::OracleContract;
+use oraiswap::orderbook::{
+    OrderDirection, QueryMsg as OrderBookQueryMsg, SimulateMarketOrderResponse,
+};
 use oraiswap::pair::{QueryMsg as PairQueryMsg, SimulationResponse};
-use oraiswap::querier::{query_pair_config, query_pair_info};
+use oraiswap::querier::{query_pair_config, query_pair_info, query_pool, reverse_simulate};
 use oraiswap::router::{
-    ConfigResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg,
-    SimulateSwapOperationsResponse, SwapOperation,
+    ConfigResponse, Cw20HookMsg, ExecuteMsg, HopSimulation, InstantiateMsg, MigrateMsg, QueryMsg,
+    SimulateSwapOperationsDetailedResponse, SimulateSwapOperationsResponse, SwapOperation,
 };
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -32,6 +40,10 @@ pub fn instantiate(
         &Config {
             factory_addr: deps.api.addr_canonicalize(msg.factory_addr.as_str())?,
             factory_addr_v2: deps.api.addr_canonicalize(msg.factory_addr_v2.as_str())?,
+            converter: msg
+                .converter
+                .map(|converter| deps.api.addr_canonicalize(converter.as_str()))
+                .transpose()?,
         },
     )?;
 
@@ -39,7 +51,14 @@ pub fn instantiate(
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(_deps: DepsMut, _env: Env, _msg: MigrateMsg) -> StdResult<Response> {
+pub fn migrate(deps: DepsMut, _env: Env, msg: MigrateMsg) -> StdResult<Response> {
+    if let Some(converter) = msg.converter {
+        CONFIG.update(deps.storage, |mut config| -> StdResult<_> {
+            config.converter = Some(deps.api.addr_canonicalize(converter.as_str())?);
+            Ok(config)
+        })?;
+    }
+
     Ok(Response::default())
 }
 
@@ -57,12 +76,79 @@ pub fn execute(
             minimum_receive,
             to,
         } => execute_swap_operations(deps, env, info.sender, operations, minimum_receive, to),
+        ExecuteMsg::ExecuteSwapOperationsExactOut {
+            operations,
+            ask_amount,
+            max_offer,
+        } => {
+            // native offer comes with exactly the offer coin, token offer is pulled from the allowance
+            let offer_amount = match operations.first().map(|op| op.get_offer_asset_info()) {
+                Some(AssetInfo::NativeToken { denom }) => match info.funds.as_slice() {
+                    [fund] if fund.denom.eq(&denom) => Some(fund.amount),
+                    _ => return Err(ContractError::InvalidFunds {}),
+                },
+                _ if !info.funds.is_empty() => return Err(ContractError::InvalidFunds {}),
+                _ => None,
+            };
+            execute_swap_operations_exact_out(
+                deps,
+                env,
+                info.sender,
+                operations,
+                ask_amount,
+                max_offer,
+                offer_amount,
+            )
+        }
+        ExecuteMsg::ExecuteSplitSwap {
+            routes,
+            minimum_receive,
+            to,
+        } => {
+            // only native offers come with funds, token offers use the cw20 hook
+            let offer_asset_info = routes
+                .first()
+                .and_then(|route| route.operations.first())
+                .map(|op| op.get_offer_asset_info())
+                .ok_or(ContractError::NoSwapOperation {})?;
+            let AssetInfo::NativeToken { denom } = &offer_asset_info else {
+                return Err(ContractError::InvalidFunds {});
+            };
+            // exactly the offer coin, anything else would be stuck in the router
+            let offer_amount = match info.funds.as_slice() {
+                [fund] if fund.denom.eq(denom) => fund.amount,
+                _ => return Err(ContractError::InvalidFunds {}),
+            };
+            execute_split_swap(
+                deps,
+                env,
+                info.sender,
+                offer_asset_info,
+                offer_amount,
+                routes,
+                minimum_receive,
+                to,
+            )
+        }
         ExecuteMsg::ExecuteSwapOperation {
             operation,
             to,
             sender,
-        } => execute_swap_operation(deps, env, info, operation, to, sender),
+            amount,
+        } => execute_swap_operation(deps, env, info, operation, to, sender, amount),
 
+        ExecuteMsg::TransferExcess {
+            asset_info,
+            retained_amount,
+            receiver,
+        } => execute_transfer_excess(
+            deps.as_ref(),
+            env,
+            info,
+            asset_info,
+            retained_amount,
+            receiver,
+        ),
         ExecuteMsg::AssertMinimumReceive {
             asset_info,
             prev_balance,
@@ -81,7 +167,7 @@ pub fn execute(
 pub fn receive_cw20(
     deps: DepsMut,
     env: Env,
-    _info: MessageInfo,
+    info: MessageInfo,
     cw20_msg: Cw20ReceiveMsg,
 ) -> Result<Response, ContractError> {
     let sender = deps.api.addr_validate(&cw20_msg.sender)?;
@@ -96,6 +182,47 @@ pub fn receive_cw20(
             let receiver = to.and_then(|addr| deps.api.addr_validate(addr.as_str()).ok());
             execute_swap_operations(deps, env, sender, operations, minimum_receive, receiver)
         }
+        Cw20HookMsg::ExecuteSwapOperationsExactOut {
+            operations,
+            ask_amount,
+            max_offer,
+        } => {
+            // the sent token must be the offer asset, so the excess can be refunded
+            let sent_asset_info = AssetInfo::Token {
+                contract_addr: info.sender,
+            };
+            if operations.first().map(|op| op.get_offer_asset_info()) != Some(sent_asset_info) {
+                return Err(ContractError::AssetMismatch {});
+            }
+            execute_swap_operations_exact_out(
+                deps,
+                env,
+                sender,
+                operations,
+                ask_amount,
+                max_offer,
+                Some(cw20_msg.amount),
+            )
+        }
+        Cw20HookMsg::ExecuteSplitSwap {
+            routes,
+            minimum_receive,
+            to,
+        } => {
+            let receiver = to.and_then(|addr| deps.api.addr_validate(addr.as_str()).ok());
+            execute_split_swap(
+                deps,
+                env,
+                sender,
+                AssetInfo::Token {
+                    contract_addr: info.sender,
+                },
+                cw20_msg.amount,
+                routes,
+                minimum_receive,
+                receiver,
+            )
+        }
     }
 }
 
@@ -120,13 +247,33 @@ fn assert_minium_receive(
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Config {} => to_json_binary(&query_config(deps)?),
         QueryMsg::SimulateSwapOperations {
             offer_amount,
             operations,
-        } => to_json_binary(&simulate_swap_operations(deps, offer_amount, operations)?),
+        } => to_json_binary(&simulate_swap_operations(
+            deps,
+            env,
+            offer_amount,
+            operations,
+        )?),
+        QueryMsg::SimulateSwapOperationsDetailed {
+            offer_amount,
+            operations,
+        } => to_json_binary(&simulate_swap_operations_detailed(
+            deps,
+            env,
+            offer_amount,
+            operations,
+        )?),
+        QueryMsg::SimulateReverseSwapOperations {
+            ask_amount,
+            operations,
+        } => to_json_binary(&simulate_reverse_swap_operations(
+            deps, env, ask_amount, operations,
+        )?),
     }
 }
 
@@ -135,6 +282,10 @@ pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
     let resp = ConfigResponse {
         factory_addr: deps.api.addr_humanize(&state.factory_addr)?,
         factory_addr_v2: deps.api.addr_humanize(&state.factory_addr_v2)?,
+        converter: state
+            .converter
+            .map(|converter| deps.api.addr_humanize(&converter))
+            .transpose()?,
     };
 
     Ok(resp)
@@ -142,9 +293,88 @@ pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
 
 fn simulate_swap_operations(
     deps: Deps,
+    env: Env,
     offer_amount: Uint128,
     operations: Vec<SwapOperation>,
 ) -> StdResult<SimulateSwapOperationsResponse> {
+    let hops = simulate_hops(deps, &env, offer_amount, operations)?;
+
+    Ok(SimulateSwapOperationsResponse {
+        amount: hops.last().map(|hop| hop.return_amount).unwrap_or_default(),
+    })
+}
+
+fn simulate_swap_operations_detailed(
+    deps: Deps,
+    env: Env,
+    offer_amount: Uint128,
+    operations: Vec<SwapOperation>,
+) -> StdResult<SimulateSwapOperationsDetailedResponse> {
+    let hops = simulate_hops(deps, &env, offer_amount, operations.clone())?;
+    let amount = hops.last().map(|hop| hop.return_amount).unwrap_or_default();
+
+    // spot price of the route is the product of the spot prices of every hop
+    let config: Config = CONFIG.load(deps.storage)?;
+    let mut spot_price = Decimal256::one();
+    for (hop, operation) in hops.iter().zip(operations) {
+        let hop_spot_price = match operation {
+            SwapOperation::OraiSwap { .. } => {
+                let pools = query_pool(&deps.querier, hop.pair_addr.clone())?.assets;
+                let (offer_pool, ask_pool) = if pools[0].info.eq(&hop.offer_asset_info) {
+                    (pools[0].amount, pools[1].amount)
+                } else {
+                    (pools[1].amount, pools[0].amount)
+                };
+                if offer_pool.is_zero() {
+                    Decimal256::zero()
+                } else {
+                    Decimal256::from_ratio(ask_pool, offer_pool)
+                }
+            }
+            SwapOperation::OrderbookMarket {
+                orderbook_addr,
+                asset_infos,
+                ..
+            } => {
+                // mid price is in quote per base
+                let direction =
+                    query_orderbook_direction(&deps.querier, orderbook_addr.clone(), &asset_infos)?;
+                let mid_price: Decimal = deps.querier.query_wasm_smart(
+                    orderbook_addr,
+                    &OrderBookQueryMsg::MidPrice { asset_infos },
+                )?;
+                let mid_price = Decimal256::from(mid_price);
+                match direction {
+                    OrderDirection::Sell => mid_price,
+                    OrderDirection::Buy if mid_price.is_zero() => mid_price,
+                    OrderDirection::Buy => Decimal256::one()
+                        .checked_div(mid_price)
+                        .map_err(|err| StdError::generic_err(err.to_string()))?,
+                }
+            }
+            SwapOperation::Convert { from, to } => {
+                let conversion =
+                    load_converter(deps, &config)?.query_conversion(&deps.querier, &from, &to)?;
+                Decimal256::from(conversion.price()?)
+            }
+        };
+        spot_price = spot_price.checked_mul(hop_spot_price)?;
+    }
+
+    Ok(SimulateSwapOperationsDetailedResponse {
+        amount,
+        price_impact: compute_price_impact(offer_amount, amount, spot_price)?,
+        hops,
+    })
+}
+
+/// simulate the operations one by one, the return amount of a hop is the offer amount of the next
+fn simulate_hops(
+    deps: Deps,
+    env: &Env,
+    offer_amount: Uint128,
+    operations: Vec<SwapOperation>,
+) -> StdResult<Vec<HopSimulation>> {
     let config: Config = CONFIG.load(deps.storage)?;
     let factory_addr = deps.api.addr_humanize(&config.factory_addr)?;
     let factory_addr_v2 = deps.api.addr_humanize(&config.factory_addr_v2)?;
@@ -155,16 +385,170 @@ fn simulate_swap_operations(
         ));
     }
 
+    let mut hops = Vec::with_capacity(operations_len);
     let mut offer_amount = offer_amount;
-    for operation in operations.into_iter() {
-        let pair_config = query_pair_config(&deps.querier, factory_addr.clone())
-            .or_else(|_| query_pair_config(&deps.querier, factory_addr_v2.clone()))?;
-        let oracle_contract = OracleContract(pair_config.oracle_addr);
+    for (operation_index, operation) in operations.into_iter().enumerate() {
         match operation {
             SwapOperation::OraiSwap {
                 offer_asset_info,
                 ask_asset_info,
             } => {
+                let pair_config = query_pair_config(&deps.querier, factory_addr.clone())
+                    .or_else(|_| query_pair_config(&deps.querier, factory_addr_v2.clone()))?;
+                let oracle_contract = OracleContract(pair_config.oracle_addr);
+                let asset_infos = [offer_asset_info.clone(), ask_asset_info.clone()];
+                let (pair_info, from_factory_v2) =
+                    match query_pair_info(&deps.querier, factory_addr.clone(), &asset_infos) {
+                        Ok(pair_info) => (pair_info, false),
+                        Err(_) => (
+                            query_pair_info(&deps.querier, factory_addr_v2.clone(), &asset_infos)?,
+                            true,
+                        ),
+                    };
+
+                let return_asset = Asset {
+                    info: offer_asset_info.clone(),
+                    amount: offer_amount,
+                };
+
+                // Deduct tax before querying simulation, with native token only
+                let offer_tax = return_asset.compute_tax_to(
+                    &oracle_contract,
+                    &deps.querier,
+                    &pair_info.contract_addr,
+                )?;
+
+                let res: SimulationResponse = deps.querier.query_wasm_smart(
+                    pair_info.contract_addr.clone(),
+                    &PairQueryMsg::Simulation {
+                        offer_asset: Asset {
+                            info: offer_asset_info.clone(),
+                            amount: offer_amount.checked_sub(offer_tax)?,
+                        },
+                    },
+                )?;
+
+                let return_asset = Asset {
+                    info: ask_asset_info.clone(),
+                    amount: res.return_amount,
+                };
+
+                // Deduct tax after querying simulation, with native token only
+                // intermediate hops are returned to the router
+                let return_tax = if operation_index + 1 < operations_len {
+                    return_asset.compute_tax_to(
+                        &oracle_contract,
+                        &deps.querier,
+                        &env.contract.address,
+                    )?
+                } else {
+                    return_asset.compute_tax(&oracle_contract, &deps.querier)?
+                };
+                let return_amount = res.return_amount.checked_sub(return_tax)?;
+
+                hops.push(HopSimulation {
+                    offer_asset_info,
+                    ask_asset_info,
+                    offer_amount,
+                    return_amount,
+                    spread_amount: res.spread_amount,
+                    commission_amount: res.commission_amount,
+                    tax_amount: offer_tax.checked_add(return_tax)?,
+                    refund_amount: Uint128::zero(),
+                    pair_addr: pair_info.contract_addr,
+                    from_factory_v2,
+                });
+
+                offer_amount = return_amount;
+            }
+            SwapOperation::OrderbookMarket {
+                orderbook_addr,
+                asset_infos,
+                slippage,
+            } => {
+                let direction =
+                    query_orderbook_direction(&deps.querier, orderbook_addr.clone(), &asset_infos)?;
+                let res: SimulateMarketOrderResponse = deps.querier.query_wasm_smart(
+                    orderbook_addr.clone(),
+                    &OrderBookQueryMsg::SimulateMarketOrder {
+                        direction,
+                        asset_infos: asset_infos.clone(),
+                        slippage,
+                        offer_amount,
+                    },
+                )?;
+
+                // the unfilled offer is returned to the sender, only the fill goes on
+                let [offer_asset_info, ask_asset_info] = asset_infos;
+                hops.push(HopSimulation {
+                    offer_asset_info,
+                    ask_asset_info,
+                    offer_amount,
+                    return_amount: res.receive,
+                    spread_amount: Uint128::zero(),
+                    commission_amount: Uint128::zero(),
+                    tax_amount: Uint128::zero(),
+                    refund_amount: res.refunds,
+                    pair_addr: orderbook_addr,
+                    from_factory_v2: false,
+                });
+
+                offer_amount = res.receive;
+            }
+            SwapOperation::Convert { from, to } => {
+                let converter = load_converter(deps, &config)?;
+                let return_amount = converter
+                    .query_conversion(&deps.querier, &from, &to)?
+                    .convert(offer_amount)?;
+
+                hops.push(HopSimulation {
+                    offer_asset_info: from,
+                    ask_asset_info: to,
+                    offer_amount,
+                    return_amount,
+                    spread_amount: Uint128::zero(),
+                    commission_amount: Uint128::zero(),
+                    tax_amount: Uint128::zero(),
+                    refund_amount: Uint128::zero(),
+                    pair_addr: converter.addr(),
+                    from_factory_v2: false,
+                });
+
+                offer_amount = return_amount;
+            }
+        }
+    }
+
+    Ok(hops)
+}
+
+pub fn simulate_reverse_swap_operations(
+    deps: Deps,
+    env: Env,
+    ask_amount: Uint128,
+    operations: Vec<SwapOperation>,
+) -> StdResult<SimulateSwapOperationsResponse> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    let factory_addr = deps.api.addr_humanize(&config.factory_addr)?;
+    let factory_addr_v2 = deps.api.addr_humanize(&config.factory_addr_v2)?;
+    let operations_len = operations.len();
+    if operations_len == 0 {
+        return Err(StdError::generic_err(
+            ContractError::NoSwapOperation {}.to_string(),
+        ));
+    }
+
+    // walk the operations backwards, from the ask amount to the offer amount
+    let mut ask_amount = ask_amount;
+    for (operation_index, operation) in operations.into_iter().enumerate().rev() {
+        match operation {
+            SwapOperation::OraiSwap {
+                offer_asset_info,
+                ask_asset_info,
+            } => {
+                let pair_config = query_pair_config(&deps.querier, factory_addr.clone())
+                    .or_else(|_| query_pair_config(&deps.querier, factory_addr_v2.clone()))?;
+                let oracle_contract = OracleContract(pair_config.oracle_addr);
                 let pair_info = query_pair_info(
                     &deps.querier,
                     factory_addr.clone(),
@@ -178,41 +562,90 @@ fn simulate_swap_operations(
                     )
                 })?;
 
+                // Add the tax deducted from the return, with native token only
+                // intermediate hops are returned to the router
                 let return_asset = Asset {
-                    info: offer_asset_info.clone(),
-                    amount: offer_amount,
+                    info: ask_asset_info,
+                    amount: ask_amount,
                 };
+                let return_recipient = if operation_index + 1 < operations_len {
+                    Some(&env.contract.address)
+                } else {
+                    None
+                };
+                let return_amount = add_tax(
+                    &oracle_contract,
+                    &deps.querier,
+                    &return_asset,
+                    return_recipient,
+                )?;
 
-                // Deduct tax before querying simulation, with native token only
-                offer_amount = offer_amount
-                    .checked_sub(return_asset.compute_tax(&oracle_contract, &deps.querier)?)?;
-
-                let mut res: SimulationResponse = deps.querier.query_wasm_smart(
-                    pair_info.contract_addr,
-                    &PairQueryMsg::Simulation {
-                        offer_asset: Asset {
-                            info: offer_asset_info,
-                            amount: offer_amount,
-                        },
+                let res = reverse_simulate(
+                    &deps.querier,
+                    pair_info.contract_addr.clone(),
+                    &Asset {
+                        info: return_asset.info,
+                        amount: return_amount,
                     },
                 )?;
 
-                let return_asset = Asset {
-                    info: ask_asset_info,
-                    amount: res.return_amount,
+                // the pair rounds the offer amount down, so round it up
+                let offer_asset = Asset {
+                    info: offer_asset_info,
+                    amount: res.offer_amount.checked_add(Uint128::one())?,
                 };
 
-                // Deduct tax after querying simulation, with native token only
-                res.return_amount = res
-                    .return_amount
-                    .checked_sub(return_asset.compute_tax(&oracle_contract, &deps.querier)?)?;
-
-                offer_amount = res.return_amount;
+                // Add the tax deducted before the swap, with native token only
+                ask_amount = add_tax(
+                    &oracle_contract,
+                    &deps.querier,
+                    &offer_asset,
+                    Some(&pair_info.contract_addr),
+                )?;
+            }
+            SwapOperation::OrderbookMarket { .. } => {
+                return Err(StdError::generic_err(
+                    "reverse simulation is not supported for orderbook operations",
+                ));
+            }
+            SwapOperation::Convert { from, to } => {
+                ask_amount = load_converter(deps, &config)?
+                    .query_conversion(&deps.querier, &from, &to)?
+                    .convert_reverse(ask_amount)?;
             }
         }
     }
 
-    Ok(SimulateSwapOperationsResponse {
-        amount: offer_amount,
-    })
+    Ok(SimulateSwapOperationsResponse { amount: ask_amount })
+}
+
+/// smallest amount that still leaves asset.amount after the tax on its transfer to recipient
+fn add_tax(
+    oracle_contract: &OracleContract,
+    querier: &QuerierWrapper,
+    asset: &Asset,
+    recipient: Option<&Addr>,
+) -> StdResult<Uint128> {
+    let tax_amount = match recipient {
+        Some(recipient) => asset.compute_tax_to(oracle_contract, querier, recipient)?,
+        None => asset.compute_tax(oracle_contract, querier)?,
+    };
+    if tax_amount.is_zero() {
+        return Ok(asset.amount);
+    }
+
+    let AssetInfo::NativeToken { denom } = &asset.info else {
+        return Ok(asset.amount);
+    };
+    // the tax of amount * (1 + rate) is amount * rate, up to the cap
+    let tax_rate = oracle_contract
+        .query_denom_tax_rate(querier, denom.to_string())?
+        .rate;
+    let tax_cap = oracle_contract
+        .query_tax_cap(querier, denom.to_string())?
+        .cap;
+
+    Ok(asset
+        .amount
+        .checked_add(std::cmp::min(asset.amount.mul_ceil(tax_rate), tax_cap))?)
 }
@@ -1,19 +1,25 @@
 use std::collections::HashMap;
 
 use cosmwasm_std::{
-    to_json_binary, Addr, Coin, CosmosMsg, Decimal, Deps, DepsMut, Env, MessageInfo, Response, StdError,
-    StdResult, Uint128, WasmMsg,
+    attr, to_json_binary, Addr, Coin, CosmosMsg, Decimal, Deps, DepsMut, Env, MessageInfo,
+    QuerierWrapper, Response, StdError, StdResult, Uint128, WasmMsg,
 };
 use oraiswap::error::ContractError;
 
+use crate::contract::simulate_reverse_swap_operations;
 use crate::state::{Config, CONFIG};
 
 use cw20::Cw20ExecuteMsg;
 use oraiswap::asset::{Asset, AssetInfo, PairInfo};
+use oraiswap::converter::ConverterController;
 use oraiswap::oracle::OracleContract;
+use oraiswap::orderbook::{
+    Cw20HookMsg as OrderBookCw20HookMsg, ExecuteMsg as OrderBookExecuteMsg, OrderBookResponse,
+    OrderDirection, QueryMsg as OrderBookQueryMsg,
+};
 use oraiswap::pair::{ExecuteMsg as PairExecuteMsg, PairExecuteMsgCw20, QueryMsg as PairQueryMsg};
 use oraiswap::querier::{query_pair_config, query_pair_info, query_token_balance};
-use oraiswap::router::{ExecuteMsg, SwapOperation};
+use oraiswap::router::{ExecuteMsg, SplitOffer, SplitRoute, SwapOperation};
 
 /// Execute swap operation
 /// swap all offer asset to ask asset
@@ -24,6 +30,7 @@ pub fn execute_swap_operation(
     operation: SwapOperation,
     to: Option<Addr>,
     sender: Addr,
+    amount: Option<Uint128>,
 ) -> Result<Response, ContractError> {
     if env.contract.address != info.sender {
         return Err(ContractError::Unauthorized {});
@@ -67,13 +74,14 @@ pub fn execute_swap_operation(
                 return Err(ContractError::PoolWhitelisted {});
             }
 
-            let amount = match offer_asset_info.clone() {
-                AssetInfo::NativeToken { denom } => {
+            let amount = match (amount, offer_asset_info.clone()) {
+                (Some(amount), _) => amount,
+                (None, AssetInfo::NativeToken { denom }) => {
                     deps.querier
                         .query_balance(env.contract.address, denom)?
                         .amount
                 }
-                AssetInfo::Token { contract_addr } => {
+                (None, AssetInfo::Token { contract_addr }) => {
                     query_token_balance(&deps.querier, contract_addr, env.contract.address)?
                 }
             };
@@ -92,11 +100,162 @@ pub fn execute_swap_operation(
                 to,
             )?]
         }
+        SwapOperation::OrderbookMarket {
+            orderbook_addr,
+            asset_infos,
+            slippage,
+        } => {
+            let direction =
+                query_orderbook_direction(&deps.querier, orderbook_addr.clone(), &asset_infos)?;
+            let [offer_asset_info, ask_asset_info] = asset_infos.clone();
+            let offer_balance =
+                offer_asset_info.query_pool(&deps.querier, env.contract.address.clone())?;
+            let ask_balance =
+                ask_asset_info.query_pool(&deps.querier, env.contract.address.clone())?;
+            let amount = amount.unwrap_or(offer_balance);
+
+            let mut msgs: Vec<CosmosMsg> = vec![match offer_asset_info.clone() {
+                AssetInfo::NativeToken { denom } => CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: orderbook_addr.to_string(),
+                    msg: to_json_binary(&OrderBookExecuteMsg::SubmitMarketOrder {
+                        direction,
+                        asset_infos,
+                        slippage,
+                    })?,
+                    funds: vec![Coin { denom, amount }],
+                }),
+                AssetInfo::Token { contract_addr } => CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: contract_addr.to_string(),
+                    msg: to_json_binary(&Cw20ExecuteMsg::Send {
+                        contract: orderbook_addr.to_string(),
+                        amount,
+                        msg: to_json_binary(&OrderBookCw20HookMsg::SubmitMarketOrder {
+                            direction,
+                            asset_infos,
+                            slippage,
+                        })?,
+                    })?,
+                    funds: vec![],
+                }),
+            }];
+
+            // the unfilled offer is refunded to the router, return it to the sender
+            msgs.push(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: env.contract.address.to_string(),
+                funds: vec![],
+                msg: to_json_binary(&ExecuteMsg::TransferExcess {
+                    asset_info: offer_asset_info,
+                    retained_amount: offer_balance.checked_sub(amount)?,
+                    receiver: sender,
+                })?,
+            }));
+
+            // the orderbook pays the router, forward the last hop to the receiver
+            if let Some(to) = to {
+                msgs.push(CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: env.contract.address.to_string(),
+                    funds: vec![],
+                    msg: to_json_binary(&ExecuteMsg::TransferExcess {
+                        asset_info: ask_asset_info,
+                        retained_amount: ask_balance,
+                        receiver: to,
+                    })?,
+                }));
+            }
+
+            msgs
+        }
+        SwapOperation::Convert {
+            from,
+            to: ask_asset_info,
+        } => {
+            let converter = load_converter(deps.as_ref(), &config)?;
+            let conversion = converter.query_conversion(&deps.querier, &from, &ask_asset_info)?;
+            let amount = match amount {
+                Some(amount) => amount,
+                None => from.query_pool(&deps.querier, env.contract.address.clone())?,
+            };
+            let ask_balance =
+                ask_asset_info.query_pool(&deps.querier, env.contract.address.clone())?;
+
+            // the converter pays the router, forward the last hop to the receiver
+            let mut msgs =
+                vec![converter.convert_msg(&conversion, from, ask_asset_info.clone(), amount)?];
+            if let Some(to) = to {
+                msgs.push(CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: env.contract.address.to_string(),
+                    funds: vec![],
+                    msg: to_json_binary(&ExecuteMsg::TransferExcess {
+                        asset_info: ask_asset_info,
+                        retained_amount: ask_balance,
+                        receiver: to,
+                    })?,
+                }));
+            }
+
+            msgs
+        }
     };
 
     Ok(Response::new().add_messages(messages))
 }
 
+/// Send the balance of asset_info above retained_amount to receiver
+pub fn execute_transfer_excess(
+    deps: Deps,
+    env: Env,
+    info: MessageInfo,
+    asset_info: AssetInfo,
+    retained_amount: Uint128,
+    receiver: Addr,
+) -> Result<Response, ContractError> {
+    if env.contract.address != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let balance = asset_info.query_pool(&deps.querier, env.contract.address)?;
+    let amount = balance.saturating_sub(retained_amount);
+    if amount.is_zero() {
+        return Ok(Response::default());
+    }
+
+    Ok(Response::new().add_message(
+        Asset {
+            info: asset_info,
+            amount,
+        }
+        .into_msg(None, &deps.querier, receiver)?,
+    ))
+}
+
+/// the converter used by convert operations
+pub fn load_converter(deps: Deps, config: &Config) -> StdResult<ConverterController> {
+    match &config.converter {
+        Some(converter) => Ok(ConverterController(deps.api.addr_humanize(converter)?)),
+        None => Err(StdError::generic_err("converter is not set")),
+    }
+}
+
+/// buy when offering the quote asset of the orderbook pair, sell otherwise
+pub fn query_orderbook_direction(
+    querier: &QuerierWrapper,
+    orderbook_addr: Addr,
+    asset_infos: &[AssetInfo; 2],
+) -> StdResult<OrderDirection> {
+    let orderbook: OrderBookResponse = querier.query_wasm_smart(
+        orderbook_addr,
+        &OrderBookQueryMsg::OrderBook {
+            asset_infos: asset_infos.clone(),
+        },
+    )?;
+
+    Ok(if asset_infos[0] == orderbook.quote_coin_info {
+        OrderDirection::Buy
+    } else {
+        OrderDirection::Sell
+    })
+}
+
 pub fn execute_swap_operations(
     deps: DepsMut,
     env: Env,
@@ -116,26 +275,7 @@ pub fn execute_swap_operations(
     let to = to.unwrap_or(sender.clone());
     let target_asset_info = operations.last().unwrap().get_target_asset_info();
 
-    let mut operation_index = 0;
-    let mut messages: Vec<CosmosMsg> = operations
-        .into_iter()
-        .map(|op| {
-            operation_index += 1;
-            Ok(CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: env.contract.address.to_string(),
-                funds: vec![],
-                msg: to_json_binary(&ExecuteMsg::ExecuteSwapOperation {
-                    operation: op,
-                    to: if operation_index == operations_len {
-                        Some(to.clone())
-                    } else {
-                        None
-                    },
-                    sender: sender.clone(),
-                })?,
-            }))
-        })
-        .collect::<StdResult<Vec<CosmosMsg>>>()?;
+    let mut messages = swap_operation_msgs(&env, &sender, operations, &to, None)?;
 
     // Execute minimum amount assertion
     if let Some(minimum_receive) = minimum_receive {
@@ -156,6 +296,234 @@ pub fn execute_swap_operations(
     Ok(Response::new().add_messages(messages))
 }
 
+/// Execute several routes of swap operations in one go
+/// offer_amount of offer_asset_info is split between the routes
+#[allow(clippy::too_many_arguments)]
+pub fn execute_split_swap(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    offer_asset_info: AssetInfo,
+    offer_amount: Uint128,
+    routes: Vec<SplitRoute>,
+    minimum_receive: Option<Uint128>,
+    to: Option<Addr>,
+) -> Result<Response, ContractError> {
+    if routes.is_empty() {
+        return Err(ContractError::NoSwapOperation {});
+    }
+
+    let to = to.unwrap_or(sender.clone());
+    let amounts = split_offer_amount(offer_amount, &routes)?;
+
+    let mut target_asset_info: Option<AssetInfo> = None;
+    let mut messages: Vec<CosmosMsg> = vec![];
+    for (route, amount) in routes.into_iter().zip(amounts) {
+        if route.operations.is_empty() {
+            return Err(ContractError::NoSwapOperation {});
+        }
+
+        // Assert the operations are properly set, and every route joins the same assets
+        assert_operations(&route.operations)?;
+        let route_target_asset_info = route.operations.last().unwrap().get_target_asset_info();
+        if route.operations[0].get_offer_asset_info() != offer_asset_info
+            || *target_asset_info.get_or_insert(route_target_asset_info.clone())
+                != route_target_asset_info
+        {
+            return Err(ContractError::AssetMismatch {});
+        }
+
+        if amount.is_zero() {
+            continue;
+        }
+        messages.extend(swap_operation_msgs(
+            &env,
+            &sender,
+            route.operations,
+            &to,
+            Some(amount),
+        )?);
+    }
+
+    // Execute minimum amount assertion over every route
+    if let (Some(minimum_receive), Some(target_asset_info)) = (minimum_receive, target_asset_info) {
+        let receiver_balance = target_asset_info.query_pool(&deps.querier, to.clone())?;
+
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: env.contract.address.to_string(),
+            funds: vec![],
+            msg: to_json_binary(&ExecuteMsg::AssertMinimumReceive {
+                asset_info: target_asset_info,
+                prev_balance: receiver_balance,
+                minimum_receive,
+                receiver: to,
+            })?,
+        }))
+    }
+
+    Ok(Response::new().add_messages(messages))
+}
+
+/// offer amount of every route, the rounding dust of the percent shares goes to the last of them
+fn split_offer_amount(
+    offer_amount: Uint128,
+    routes: &[SplitRoute],
+) -> Result<Vec<Uint128>, ContractError> {
+    let mut amounts: Vec<Uint128> = routes
+        .iter()
+        .map(|route| match route.offer {
+            SplitOffer::Amount(amount) => amount,
+            SplitOffer::Percent(percent) => offer_amount * percent,
+        })
+        .collect();
+
+    let total = amounts
+        .iter()
+        .try_fold(Uint128::zero(), |total, amount| total.checked_add(*amount))?;
+    let percent_routes = routes
+        .iter()
+        .filter(|route| matches!(route.offer, SplitOffer::Percent(_)))
+        .count();
+    if let Some(last_percent_index) = routes
+        .iter()
+        .rposition(|route| matches!(route.offer, SplitOffer::Percent(_)))
+    {
+        // every percent share loses at most one unit when rounded down
+        let dust = offer_amount.saturating_sub(total);
+        if dust <= Uint128::from(percent_routes as u128) {
+            amounts[last_percent_index] += dust;
+        }
+    }
+
+    let total = amounts
+        .iter()
+        .try_fold(Uint128::zero(), |total, amount| total.checked_add(*amount))?;
+    if total != offer_amount {
+        return Err(ContractError::InvalidSplitRoutes {});
+    }
+
+    Ok(amounts)
+}
+
+/// messages executing the operations one by one, the first one swaps amount if given
+fn swap_operation_msgs(
+    env: &Env,
+    sender: &Addr,
+    operations: Vec<SwapOperation>,
+    to: &Addr,
+    amount: Option<Uint128>,
+) -> StdResult<Vec<CosmosMsg>> {
+    let operations_len = operations.len();
+    operations
+        .into_iter()
+        .enumerate()
+        .map(|(operation_index, op)| {
+            Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: env.contract.address.to_string(),
+                funds: vec![],
+                msg: to_json_binary(&ExecuteMsg::ExecuteSwapOperation {
+                    operation: op,
+                    to: if operation_index + 1 == operations_len {
+                        Some(to.clone())
+                    } else {
+                        None
+                    },
+                    sender: sender.clone(),
+                    amount: if operation_index == 0 { amount } else { None },
+                })?,
+            }))
+        })
+        .collect()
+}
+
+/// Execute multiple swap operations receiving exactly ask_amount
+/// offer_amount is the amount sent along, None pulls the required amount from the allowance
+pub fn execute_swap_operations_exact_out(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    operations: Vec<SwapOperation>,
+    ask_amount: Uint128,
+    max_offer: Uint128,
+    offer_amount: Option<Uint128>,
+) -> Result<Response, ContractError> {
+    if operations.is_empty() {
+        return Err(ContractError::NoSwapOperation {});
+    }
+
+    // Assert the operations are properly set
+    assert_operations(&operations)?;
+
+    let required = simulate_reverse_swap_operations(
+        deps.as_ref(),
+        env.clone(),
+        ask_amount,
+        operations.clone(),
+    )?
+    .amount;
+    let max_offer = offer_amount.map_or(max_offer, |amount| std::cmp::min(amount, max_offer));
+    if required > max_offer {
+        return Err(ContractError::MaxOfferExceeded {
+            max_offer,
+            offer_amount: required,
+        });
+    }
+
+    // take the required amount, the first operation swaps only that much of the router balance
+    let offer_asset_info = operations[0].get_offer_asset_info();
+    let message = match offer_amount {
+        Some(offer_amount) => {
+            let refund_amount = offer_amount.checked_sub(required)?;
+            if refund_amount.is_zero() {
+                None
+            } else {
+                Some(
+                    Asset {
+                        info: offer_asset_info,
+                        amount: refund_amount,
+                    }
+                    .into_msg(None, &deps.querier, sender.clone())?,
+                )
+            }
+        }
+        None => match offer_asset_info {
+            AssetInfo::Token { contract_addr } => Some(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: contract_addr.to_string(),
+                msg: to_json_binary(&Cw20ExecuteMsg::TransferFrom {
+                    owner: sender.to_string(),
+                    recipient: env.contract.address.to_string(),
+                    amount: required,
+                })?,
+                funds: vec![],
+            })),
+            AssetInfo::NativeToken { .. } => return Err(ContractError::InvalidFunds {}),
+        },
+    };
+
+    let target_asset_info = operations.last().unwrap().get_target_asset_info();
+    let receiver_balance = target_asset_info.query_pool(&deps.querier, sender.clone())?;
+    let mut messages = swap_operation_msgs(&env, &sender, operations, &sender, Some(required))?;
+    messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: env.contract.address.to_string(),
+        funds: vec![],
+        msg: to_json_binary(&ExecuteMsg::AssertMinimumReceive {
+            asset_info: target_asset_info,
+            prev_balance: receiver_balance,
+            minimum_receive: ask_amount,
+            receiver: sender,
+        })?,
+    }));
+
+    Ok(Response::new()
+        .add_messages(message)
+        .add_messages(messages)
+        .add_attributes(vec![
+            attr("action", "execute_swap_operations_exact_out"),
+            attr("offer_amount", required),
+            attr("ask_amount", ask_amount),
+        ]))
+}
+
 fn asset_into_swap_msg(
     deps: Deps,
     oracle_contract: &OracleContract,
@@ -174,9 +542,11 @@ fn asset_into_swap_msg(
             };
 
             // deduct tax first
-            let amount = offer_asset
-                .amount
-                .checked_sub(return_asset.compute_tax(oracle_contract, &deps.querier)?)?;
+            let amount = offer_asset.amount.checked_sub(return_asset.compute_tax_to(
+                oracle_contract,
+                &deps.querier,
+                &pair_contract,
+            )?)?;
 
             Ok(CosmosMsg::Wasm(WasmMsg::Execute {
                 contract_addr: pair_contract.to_string(),
@@ -211,12 +581,10 @@ fn asset_into_swap_msg(
 pub fn assert_operations(operations: &[SwapOperation]) -> StdResult<()> {
     let mut ask_asset_map: HashMap<String, bool> = HashMap::new();
     for operation in operations.iter() {
-        let (offer_asset, ask_asset) = match operation {
-            SwapOperation::OraiSwap {
-                offer_asset_info,
-                ask_asset_info,
-            } => (offer_asset_info.clone(), ask_asset_info.clone()),
-        };
+        let (offer_asset, ask_asset) = (
+            operation.get_offer_asset_info(),
+            operation.get_target_asset_info(),
+        );
 
         ask_asset_map.remove(&offer_asset.to_string());
         ask_asset_map.insert(ask_asset.to_string(), true);
@@ -7,6 +7,7 @@ use cw_storage_plus::Item;
 pub struct Config {
     pub factory_addr: CanonicalAddr,
     pub factory_addr_v2: CanonicalAddr,
+    pub converter: Option<CanonicalAddr>,
 }
 
 // put the length bytes at the first for compatibility with legacy singleton store
@@ -36,6 +37,7 @@ mod test {
             &Config {
                 factory_addr: deps.api.addr_canonicalize("addr0000").unwrap(),
                 factory_addr_v2: deps.api.addr_canonicalize("addr0000_v2").unwrap(),
+                converter: None,
             },
         )
         .unwrap();
@@ -1,8 +1,9 @@
-use cosmwasm_std::{Addr, Coin, Decimal, Uint128};
+use cosmwasm_std::{to_json_binary, Addr, Coin, Decimal, Uint128};
 use oraiswap::asset::{Asset, AssetInfo, ORAI_DENOM};
 use oraiswap::create_entry_points_testing;
 use oraiswap::router::{
-    ExecuteMsg, InstantiateMsg, QueryMsg, SimulateSwapOperationsResponse, SwapOperation,
+    Cw20HookMsg, ExecuteMsg, InstantiateMsg, QueryMsg, SimulateSwapOperationsDetailedResponse,
+    SimulateSwapOperationsResponse, SplitOffer, SplitRoute, SwapOperation,
 };
 
 use oraiswap::testing::{MockApp, ATOM_DENOM};
@@ -101,6 +102,7 @@ fn simulate_swap_operations_test() {
     let msg = InstantiateMsg {
         factory_addr: app.factory_addr.clone(),
         factory_addr_v2: Addr::unchecked("addr0000_v2"),
+        converter: None,
     };
 
     let code_id = app.upload(Box::new(create_entry_points_testing!(crate)));
@@ -122,8 +124,34 @@ fn simulate_swap_operations_test() {
         }],
     };
 
-    let res: SimulateSwapOperationsResponse = app.query(router_addr, &msg).unwrap();
+    let res: SimulateSwapOperationsResponse = app.query(router_addr.clone(), &msg).unwrap();
     println!("{:?}", res);
+
+    // the detailed simulation breaks the same amount down by hop
+    let QueryMsg::SimulateSwapOperations {
+        offer_amount,
+        operations,
+    } = msg
+    else {
+        unreachable!()
+    };
+    let detailed: SimulateSwapOperationsDetailedResponse = app
+        .query(
+            router_addr,
+            &QueryMsg::SimulateSwapOperationsDetailed {
+                offer_amount,
+                operations,
+            },
+        )
+        .unwrap();
+
+    assert_eq!(detailed.amount, res.amount);
+    assert_eq!(detailed.hops.len(), 1);
+    assert_eq!(detailed.hops[0].pair_addr, pair_addr);
+    assert!(!detailed.hops[0].from_factory_v2);
+    assert_eq!(detailed.hops[0].offer_amount, offer_amount);
+    assert_eq!(detailed.hops[0].return_amount, res.amount);
+    assert!(!detailed.price_impact.is_zero());
 }
 
 #[test]
@@ -284,6 +312,7 @@ fn execute_swap_operations() {
     let msg = InstantiateMsg {
         factory_addr: app.factory_addr.clone(),
         factory_addr_v2: Addr::unchecked("addr0000_v2"),
+        converter: None,
     };
 
     let code_id = app.upload(Box::new(create_entry_points_testing!(crate)));
@@ -453,3 +482,858 @@ fn execute_swap_operations() {
     )
     .unwrap();
 }
+
+#[test]
+fn execute_swap_operations_exact_out() {
+    let mut app = MockApp::new(&[(
+        "addr0000",
+        &[
+            Coin {
+                denom: ORAI_DENOM.to_string(),
+                amount: Uint128::from(10000000u128),
+            },
+            Coin {
+                denom: ATOM_DENOM.to_string(),
+                amount: Uint128::from(10000000u128),
+            },
+        ],
+    )]);
+
+    app.set_oracle_contract(Box::new(create_entry_points_testing!(oraiswap_oracle)));
+
+    app.set_token_contract(Box::new(create_entry_points_testing!(oraiswap_token)));
+
+    app.set_factory_and_pair_contract(
+        Box::new(
+            create_entry_points_testing!(oraiswap_factory)
+                .with_reply_empty(oraiswap_factory::contract::reply),
+        ),
+        Box::new(
+            create_entry_points_testing!(oraiswap_pair)
+                .with_reply_empty(oraiswap_pair::contract::reply),
+        ),
+    );
+
+    // set tax rate as 0.3%
+    app.set_tax(
+        Decimal::permille(3),
+        &[
+            (&ORAI_DENOM.to_string(), 10000000u128),
+            (&ATOM_DENOM.to_string(), 10000000u128),
+        ],
+    );
+
+    let asset_infos = [
+        AssetInfo::NativeToken {
+            denom: ORAI_DENOM.to_string(),
+        },
+        AssetInfo::NativeToken {
+            denom: ATOM_DENOM.to_string(),
+        },
+    ];
+
+    // create pair
+    let pair_addr = app.create_pair(asset_infos.clone()).unwrap();
+
+    // provide liquidity
+    let msg = oraiswap::pair::ExecuteMsg::ProvideLiquidity {
+        assets: [
+            Asset {
+                info: AssetInfo::NativeToken {
+                    denom: ATOM_DENOM.to_string(),
+                },
+                amount: Uint128::from(1000000u128),
+            },
+            Asset {
+                info: AssetInfo::NativeToken {
+                    denom: ORAI_DENOM.to_string(),
+                },
+                amount: Uint128::from(1000000u128),
+            },
+        ],
+        slippage_tolerance: None,
+        receiver: None,
+    };
+
+    let _res = app
+        .execute(
+            Addr::unchecked("addr0000"),
+            pair_addr.clone(),
+            &msg,
+            &[
+                Coin {
+                    denom: ORAI_DENOM.to_string(),
+                    amount: Uint128::from(1000000u128),
+                },
+                Coin {
+                    denom: ATOM_DENOM.to_string(),
+                    amount: Uint128::from(1000000u128),
+                },
+            ],
+        )
+        .unwrap();
+
+    let msg = InstantiateMsg {
+        factory_addr: app.factory_addr.clone(),
+        factory_addr_v2: Addr::unchecked("addr0000_v2"),
+        converter: None,
+    };
+
+    let code_id = app.upload(Box::new(create_entry_points_testing!(crate)));
+
+    let router_addr = app
+        .instantiate(code_id, Addr::unchecked("addr0000"), &msg, &[], "router")
+        .unwrap();
+
+    let operations = vec![SwapOperation::OraiSwap {
+        offer_asset_info: AssetInfo::NativeToken {
+            denom: ORAI_DENOM.to_string(),
+        },
+        ask_asset_info: AssetInfo::NativeToken {
+            denom: ATOM_DENOM.to_string(),
+        },
+    }];
+
+    let ask_amount = Uint128::from(10000u128);
+    let res: SimulateSwapOperationsResponse = app
+        .query(
+            router_addr.clone(),
+            &QueryMsg::SimulateReverseSwapOperations {
+                ask_amount,
+                operations: operations.clone(),
+            },
+        )
+        .unwrap();
+    let offer_amount = res.amount;
+
+    // the required offer amount returns at least the ask amount
+    let res: SimulateSwapOperationsResponse = app
+        .query(
+            router_addr.clone(),
+            &QueryMsg::SimulateSwapOperations {
+                offer_amount,
+                operations: operations.clone(),
+            },
+        )
+        .unwrap();
+    assert!(res.amount >= ask_amount);
+
+    // the funds must be exactly the offer coin
+    let error = app
+        .execute(
+            Addr::unchecked("addr0000"),
+            router_addr.clone(),
+            &ExecuteMsg::ExecuteSwapOperationsExactOut {
+                operations: operations.clone(),
+                ask_amount,
+                max_offer: offer_amount,
+            },
+            &[
+                Coin {
+                    denom: ORAI_DENOM.to_string(),
+                    amount: offer_amount,
+                },
+                Coin {
+                    denom: ATOM_DENOM.to_string(),
+                    amount: Uint128::one(),
+                },
+            ],
+        )
+        .unwrap_err();
+    assert!(error.root_cause().to_string().contains("Invalid funds"));
+
+    // token offers are pulled from the allowance, without any funds
+    let error = app
+        .execute(
+            Addr::unchecked("addr0000"),
+            router_addr.clone(),
+            &ExecuteMsg::ExecuteSwapOperationsExactOut {
+                operations: vec![SwapOperation::OraiSwap {
+                    offer_asset_info: AssetInfo::Token {
+                        contract_addr: Addr::unchecked("asset0000"),
+                    },
+                    ask_asset_info: AssetInfo::NativeToken {
+                        denom: ORAI_DENOM.to_string(),
+                    },
+                }],
+                ask_amount,
+                max_offer: offer_amount,
+            },
+            &[Coin {
+                denom: ORAI_DENOM.to_string(),
+                amount: offer_amount,
+            }],
+        )
+        .unwrap_err();
+    assert!(error.root_cause().to_string().contains("Invalid funds"));
+
+    // max offer lower than required
+    let error = app
+        .execute(
+            Addr::unchecked("addr0000"),
+            router_addr.clone(),
+            &ExecuteMsg::ExecuteSwapOperationsExactOut {
+                operations: operations.clone(),
+                ask_amount,
+                max_offer: offer_amount - Uint128::one(),
+            },
+            &[Coin {
+                denom: ORAI_DENOM.to_string(),
+                amount: offer_amount * Uint128::from(2u128),
+            }],
+        )
+        .unwrap_err();
+    assert!(error.root_cause().to_string().contains("exceeds max offer"));
+
+    let balance_of = |app: &MockApp, denom: &str| -> Uint128 {
+        app.query_all_balances(Addr::unchecked("addr0000"))
+            .unwrap()
+            .into_iter()
+            .find(|coin| coin.denom == denom)
+            .map(|coin| coin.amount)
+            .unwrap_or_default()
+    };
+    let orai_before = balance_of(&app, ORAI_DENOM);
+    let atom_before = balance_of(&app, ATOM_DENOM);
+
+    // orai already held by the router is not swapped along
+    app.set_balances(&[(ORAI_DENOM, &[(router_addr.as_str(), 500u128)])]);
+
+    // the excess offer is refunded
+    app.execute(
+        Addr::unchecked("addr0000"),
+        router_addr.clone(),
+        &ExecuteMsg::ExecuteSwapOperationsExactOut {
+            operations,
+            ask_amount,
+            max_offer: offer_amount * Uint128::from(2u128),
+        },
+        &[Coin {
+            denom: ORAI_DENOM.to_string(),
+            amount: offer_amount * Uint128::from(2u128),
+        }],
+    )
+    .unwrap();
+
+    assert_eq!(orai_before - balance_of(&app, ORAI_DENOM), offer_amount);
+    assert!(balance_of(&app, ATOM_DENOM) - atom_before >= ask_amount);
+    assert_eq!(
+        app.query_balance(router_addr, ORAI_DENOM.to_string())
+            .unwrap(),
+        Uint128::from(500u128)
+    );
+}
+
+#[test]
+fn execute_split_swap() {
+    let mut app = MockApp::new(&[(
+        "addr0000",
+        &[
+            Coin {
+                denom: ORAI_DENOM.to_string(),
+                amount: Uint128::from(10000000u128),
+            },
+            Coin {
+                denom: ATOM_DENOM.to_string(),
+                amount: Uint128::from(10000000u128),
+            },
+        ],
+    )]);
+
+    app.set_oracle_contract(Box::new(create_entry_points_testing!(oraiswap_oracle)));
+
+    app.set_token_contract(Box::new(create_entry_points_testing!(oraiswap_token)));
+
+    app.set_factory_and_pair_contract(
+        Box::new(
+            create_entry_points_testing!(oraiswap_factory)
+                .with_reply_empty(oraiswap_factory::contract::reply),
+        ),
+        Box::new(
+            create_entry_points_testing!(oraiswap_pair)
+                .with_reply_empty(oraiswap_pair::contract::reply),
+        ),
+    );
+
+    // set tax rate as 0.3%
+    app.set_tax(
+        Decimal::permille(3),
+        &[
+            (&ORAI_DENOM.to_string(), 10000000u128),
+            (&ATOM_DENOM.to_string(), 10000000u128),
+        ],
+    );
+
+    let asset_addr = app.create_token("asset");
+
+    app.set_token_balances(&[("asset", &[("addr0000", 10000000u128)])])
+        .unwrap();
+
+    let orai = AssetInfo::NativeToken {
+        denom: ORAI_DENOM.to_string(),
+    };
+    let atom = AssetInfo::NativeToken {
+        denom: ATOM_DENOM.to_string(),
+    };
+    let asset = AssetInfo::Token {
+        contract_addr: asset_addr.clone(),
+    };
+
+    // create pairs and provide liquidity, orai => asset directly or through atom
+    for asset_infos in [
+        [orai.clone(), asset.clone()],
+        [orai.clone(), atom.clone()],
+        [atom.clone(), asset.clone()],
+    ] {
+        let pair_addr = app.create_pair(asset_infos.clone()).unwrap();
+
+        let mut funds = vec![];
+        for info in asset_infos.iter() {
+            match info {
+                AssetInfo::NativeToken { denom } => funds.push(Coin {
+                    denom: denom.clone(),
+                    amount: Uint128::from(1000000u128),
+                }),
+                AssetInfo::Token { contract_addr } => {
+                    app.execute(
+                        Addr::unchecked("addr0000"),
+                        contract_addr.clone(),
+                        &cw20::Cw20ExecuteMsg::IncreaseAllowance {
+                            spender: pair_addr.to_string(),
+                            amount: Uint128::from(1000000u128),
+                            expires: None,
+                        },
+                        &[],
+                    )
+                    .unwrap();
+                }
+            }
+        }
+        funds.sort_by(|a, b| a.denom.cmp(&b.denom));
+
+        app.execute(
+            Addr::unchecked("addr0000"),
+            pair_addr,
+            &oraiswap::pair::ExecuteMsg::ProvideLiquidity {
+                assets: asset_infos.map(|info| Asset {
+                    info,
+                    amount: Uint128::from(1000000u128),
+                }),
+                slippage_tolerance: None,
+                receiver: None,
+            },
+            &funds,
+        )
+        .unwrap();
+    }
+
+    let msg = InstantiateMsg {
+        factory_addr: app.factory_addr.clone(),
+        factory_addr_v2: Addr::unchecked("addr0000_v2"),
+        converter: None,
+    };
+
+    let code_id = app.upload(Box::new(create_entry_points_testing!(crate)));
+
+    let router_addr = app
+        .instantiate(code_id, Addr::unchecked("addr0000"), &msg, &[], "router")
+        .unwrap();
+
+    let direct = vec![SwapOperation::OraiSwap {
+        offer_asset_info: orai.clone(),
+        ask_asset_info: asset.clone(),
+    }];
+    let through_atom = vec![
+        SwapOperation::OraiSwap {
+            offer_asset_info: orai.clone(),
+            ask_asset_info: atom.clone(),
+        },
+        SwapOperation::OraiSwap {
+            offer_asset_info: atom.clone(),
+            ask_asset_info: asset.clone(),
+        },
+    ];
+
+    let simulate = |app: &MockApp, offer_amount: u128, operations: Vec<SwapOperation>| {
+        let res: SimulateSwapOperationsResponse = app
+            .query(
+                router_addr.clone(),
+                &QueryMsg::SimulateSwapOperations {
+                    offer_amount: Uint128::from(offer_amount),
+                    operations,
+                },
+            )
+            .unwrap();
+        res.amount
+    };
+    let expected =
+        simulate(&app, 60001, direct.clone()) + simulate(&app, 40000, through_atom.clone());
+
+    let routes = vec![
+        SplitRoute {
+            offer: SplitOffer::Amount(Uint128::from(40000u128)),
+            operations: through_atom.clone(),
+        },
+        SplitRoute {
+            offer: SplitOffer::Percent(Decimal::percent(60)),
+            operations: direct.clone(),
+        },
+    ];
+    let funds = [Coin {
+        denom: ORAI_DENOM.to_string(),
+        amount: Uint128::from(100001u128),
+    }];
+
+    // the routes must spend the whole offer
+    let error = app
+        .execute(
+            Addr::unchecked("addr0000"),
+            router_addr.clone(),
+            &ExecuteMsg::ExecuteSplitSwap {
+                routes: vec![SplitRoute {
+                    offer: SplitOffer::Percent(Decimal::percent(50)),
+                    operations: direct.clone(),
+                }],
+                minimum_receive: None,
+                to: None,
+            },
+            &funds,
+        )
+        .unwrap_err();
+    assert!(error
+        .root_cause()
+        .to_string()
+        .contains("Split routes must spend exactly the offer amount"));
+
+    // extra coins are rejected instead of being left in the router
+    let error = app
+        .execute(
+            Addr::unchecked("addr0000"),
+            router_addr.clone(),
+            &ExecuteMsg::ExecuteSplitSwap {
+                routes: routes.clone(),
+                minimum_receive: None,
+                to: None,
+            },
+            &[
+                funds[0].clone(),
+                Coin {
+                    denom: ATOM_DENOM.to_string(),
+                    amount: Uint128::one(),
+                },
+            ],
+        )
+        .unwrap_err();
+    assert!(error.root_cause().to_string().contains("Invalid funds"));
+
+    // minimum receive applies to the sum of the routes
+    let error = app
+        .execute(
+            Addr::unchecked("addr0000"),
+            router_addr.clone(),
+            &ExecuteMsg::ExecuteSplitSwap {
+                routes: routes.clone(),
+                minimum_receive: Some(expected + Uint128::one()),
+                to: None,
+            },
+            &funds,
+        )
+        .unwrap_err();
+    assert!(error.root_cause().to_string().contains("Assertion failed"));
+
+    let balance_before: cw20::BalanceResponse = app
+        .query(
+            asset_addr.clone(),
+            &cw20::Cw20QueryMsg::Balance {
+                address: "addr0000".to_string(),
+            },
+        )
+        .unwrap();
+
+    // the percent route gets the rounding dust, 60001 orai
+    app.execute(
+        Addr::unchecked("addr0000"),
+        router_addr.clone(),
+        &ExecuteMsg::ExecuteSplitSwap {
+            routes,
+            minimum_receive: Some(expected),
+            to: None,
+        },
+        &funds,
+    )
+    .unwrap();
+
+    let balance_after: cw20::BalanceResponse = app
+        .query(
+            asset_addr,
+            &cw20::Cw20QueryMsg::Balance {
+                address: "addr0000".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(balance_after.balance - balance_before.balance, expected);
+}
+
+#[test]
+fn execute_orderbook_market_operation() {
+    let mut app = MockApp::new(&[
+        (
+            "addr0000",
+            &[Coin {
+                denom: ATOM_DENOM.to_string(),
+                amount: Uint128::from(1000000u128),
+            }],
+        ),
+        (
+            "addr0001",
+            &[Coin {
+                denom: ORAI_DENOM.to_string(),
+                amount: Uint128::from(1000000u128),
+            }],
+        ),
+    ]);
+
+    app.set_oracle_contract(Box::new(create_entry_points_testing!(oraiswap_oracle)));
+
+    app.set_token_contract(Box::new(create_entry_points_testing!(oraiswap_token)));
+
+    app.set_factory_and_pair_contract(
+        Box::new(
+            create_entry_points_testing!(oraiswap_factory)
+                .with_reply_empty(oraiswap_factory::contract::reply),
+        ),
+        Box::new(
+            create_entry_points_testing!(oraiswap_pair)
+                .with_reply_empty(oraiswap_pair::contract::reply),
+        ),
+    );
+
+    let orai = AssetInfo::NativeToken {
+        denom: ORAI_DENOM.to_string(),
+    };
+    let atom = AssetInfo::NativeToken {
+        denom: ATOM_DENOM.to_string(),
+    };
+
+    let orderbook_code_id = app.upload(Box::new(create_entry_points_testing!(oraiswap_orderbook)));
+    let orderbook_addr = app
+        .instantiate(
+            orderbook_code_id,
+            Addr::unchecked("addr0000"),
+            &oraiswap::orderbook::InstantiateMsg {
+                name: None,
+                version: None,
+                admin: None,
+                commission_rate: None,
+                operator: None,
+                reward_address: "reward_addr".to_string(),
+            },
+            &[],
+            "orderbook",
+        )
+        .unwrap();
+
+    // orai is the base coin, atom is the quote coin
+    app.execute(
+        Addr::unchecked("addr0000"),
+        orderbook_addr.clone(),
+        &oraiswap::orderbook::ExecuteMsg::CreateOrderBookPair {
+            base_coin_info: orai.clone(),
+            quote_coin_info: atom.clone(),
+            spread: None,
+            min_quote_coin_amount: Uint128::from(10u128),
+            refund_threshold: None,
+            min_offer_to_fulfilled: None,
+            min_ask_to_fulfilled: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    // sell 100000 orai at price 1
+    app.execute(
+        Addr::unchecked("addr0001"),
+        orderbook_addr.clone(),
+        &oraiswap::orderbook::ExecuteMsg::SubmitOrder {
+            direction: oraiswap::orderbook::OrderDirection::Sell,
+            assets: [
+                Asset {
+                    info: orai.clone(),
+                    amount: Uint128::from(100000u128),
+                },
+                Asset {
+                    info: atom.clone(),
+                    amount: Uint128::from(100000u128),
+                },
+            ],
+        },
+        &[Coin {
+            denom: ORAI_DENOM.to_string(),
+            amount: Uint128::from(100000u128),
+        }],
+    )
+    .unwrap();
+
+    let msg = InstantiateMsg {
+        factory_addr: app.factory_addr.clone(),
+        factory_addr_v2: Addr::unchecked("addr0000_v2"),
+        converter: None,
+    };
+
+    let code_id = app.upload(Box::new(create_entry_points_testing!(crate)));
+
+    let router_addr = app
+        .instantiate(code_id, Addr::unchecked("addr0000"), &msg, &[], "router")
+        .unwrap();
+
+    // buy orai with atom, more than the book can fill
+    let operations = vec![SwapOperation::OrderbookMarket {
+        orderbook_addr: orderbook_addr.clone(),
+        asset_infos: [atom.clone(), orai.clone()],
+        slippage: None,
+    }];
+    let offer_amount = Uint128::from(150000u128);
+
+    let detailed: SimulateSwapOperationsDetailedResponse = app
+        .query(
+            router_addr.clone(),
+            &QueryMsg::SimulateSwapOperationsDetailed {
+                offer_amount,
+                operations: operations.clone(),
+            },
+        )
+        .unwrap();
+    assert_eq!(detailed.hops.len(), 1);
+    assert_eq!(detailed.hops[0].pair_addr, orderbook_addr);
+    assert_eq!(detailed.hops[0].return_amount, detailed.amount);
+    assert!(!detailed.amount.is_zero());
+    assert!(!detailed.hops[0].refund_amount.is_zero());
+
+    // orderbook operations can not be simulated in reverse
+    let res: Result<SimulateSwapOperationsResponse, _> = app.query(
+        router_addr.clone(),
+        &QueryMsg::SimulateReverseSwapOperations {
+            ask_amount: Uint128::from(1000u128),
+            operations: operations.clone(),
+        },
+    );
+    let error = res.unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("reverse simulation is not supported for orderbook operations"));
+
+    app.execute(
+        Addr::unchecked("addr0000"),
+        router_addr.clone(),
+        &ExecuteMsg::ExecuteSwapOperations {
+            operations,
+            minimum_receive: Some(detailed.amount),
+            to: None,
+        },
+        &[Coin {
+            denom: ATOM_DENOM.to_string(),
+            amount: offer_amount,
+        }],
+    )
+    .unwrap();
+
+    // the fill goes to the sender and the unfilled atom is refunded
+    let balances = app.query_all_balances(Addr::unchecked("addr0000")).unwrap();
+    let balance_of = |denom: &str| {
+        balances
+            .iter()
+            .find(|coin| coin.denom == denom)
+            .map(|coin| coin.amount)
+            .unwrap_or_default()
+    };
+    assert_eq!(balance_of(ORAI_DENOM), detailed.amount);
+    assert_eq!(
+        balance_of(ATOM_DENOM),
+        Uint128::from(1000000u128) - offer_amount + detailed.hops[0].refund_amount
+    );
+    assert!(app.query_all_balances(router_addr).unwrap().is_empty());
+}
+
+#[test]
+fn execute_convert_operation() {
+    let mut app = MockApp::new(&[(
+        "addr0000",
+        &[Coin {
+            denom: ORAI_DENOM.to_string(),
+            amount: Uint128::from(1000000u128),
+        }],
+    )]);
+
+    app.set_oracle_contract(Box::new(create_entry_points_testing!(oraiswap_oracle)));
+
+    app.set_token_contract(Box::new(create_entry_points_testing!(oraiswap_token)));
+
+    app.set_factory_and_pair_contract(
+        Box::new(
+            create_entry_points_testing!(oraiswap_factory)
+                .with_reply_empty(oraiswap_factory::contract::reply),
+        ),
+        Box::new(
+            create_entry_points_testing!(oraiswap_pair)
+                .with_reply_empty(oraiswap_pair::contract::reply),
+        ),
+    );
+
+    let asset_addr = app.create_token("asset");
+
+    let orai = AssetInfo::NativeToken {
+        denom: ORAI_DENOM.to_string(),
+    };
+    let asset = AssetInfo::Token {
+        contract_addr: asset_addr.clone(),
+    };
+
+    let converter_code_id = app.upload(Box::new(create_entry_points_testing!(oraiswap_converter)));
+    let converter_addr = app
+        .instantiate(
+            converter_code_id,
+            Addr::unchecked("addr0000"),
+            &oraiswap::converter::InstantiateMsg {},
+            &[],
+            "converter",
+        )
+        .unwrap();
+
+    // 6 decimals orai is bridged as 18 decimals asset
+    app.execute(
+        Addr::unchecked("addr0000"),
+        converter_addr.clone(),
+        &oraiswap::converter::ExecuteMsg::UpdatePair {
+            from: oraiswap::converter::TokenInfo {
+                info: orai.clone(),
+                decimals: 6,
+            },
+            to: oraiswap::converter::TokenInfo {
+                info: asset.clone(),
+                decimals: 18,
+            },
+            is_mint_burn: false,
+        },
+        &[],
+    )
+    .unwrap();
+
+    app.set_token_balances(&[("asset", &[(converter_addr.as_str(), 10u128.pow(30))])])
+        .unwrap();
+
+    let msg = InstantiateMsg {
+        factory_addr: app.factory_addr.clone(),
+        factory_addr_v2: Addr::unchecked("addr0000_v2"),
+        converter: Some(converter_addr.clone()),
+    };
+
+    let code_id = app.upload(Box::new(create_entry_points_testing!(crate)));
+
+    let router_addr = app
+        .instantiate(code_id, Addr::unchecked("addr0000"), &msg, &[], "router")
+        .unwrap();
+
+    let convert = vec![SwapOperation::Convert {
+        from: orai.clone(),
+        to: asset.clone(),
+    }];
+    let convert_reverse = vec![SwapOperation::Convert {
+        from: asset.clone(),
+        to: orai.clone(),
+    }];
+
+    // simulation uses the stored ratio in both directions
+    let res: SimulateSwapOperationsResponse = app
+        .query(
+            router_addr.clone(),
+            &QueryMsg::SimulateSwapOperations {
+                offer_amount: Uint128::from(1000u128),
+                operations: convert.clone(),
+            },
+        )
+        .unwrap();
+    assert_eq!(res.amount, Uint128::from(1000u128 * 10u128.pow(12)));
+
+    let res: SimulateSwapOperationsResponse = app
+        .query(
+            router_addr.clone(),
+            &QueryMsg::SimulateSwapOperations {
+                offer_amount: Uint128::from(400u128 * 10u128.pow(12)),
+                operations: convert_reverse.clone(),
+            },
+        )
+        .unwrap();
+    assert_eq!(res.amount, Uint128::from(400u128));
+
+    let res: SimulateSwapOperationsResponse = app
+        .query(
+            router_addr.clone(),
+            &QueryMsg::SimulateReverseSwapOperations {
+                ask_amount: Uint128::from(1000u128 * 10u128.pow(12)),
+                operations: convert.clone(),
+            },
+        )
+        .unwrap();
+    assert_eq!(res.amount, Uint128::from(1000u128));
+
+    let detailed: SimulateSwapOperationsDetailedResponse = app
+        .query(
+            router_addr.clone(),
+            &QueryMsg::SimulateSwapOperationsDetailed {
+                offer_amount: Uint128::from(1000u128),
+                operations: convert.clone(),
+            },
+        )
+        .unwrap();
+    assert_eq!(detailed.hops[0].pair_addr, converter_addr);
+    assert_eq!(detailed.price_impact, Decimal::zero());
+
+    // orai => asset with Convert {}
+    app.execute(
+        Addr::unchecked("addr0000"),
+        router_addr.clone(),
+        &ExecuteMsg::ExecuteSwapOperations {
+            operations: convert,
+            minimum_receive: Some(Uint128::from(1000u128 * 10u128.pow(12))),
+            to: None,
+        },
+        &[Coin {
+            denom: ORAI_DENOM.to_string(),
+            amount: Uint128::from(1000u128),
+        }],
+    )
+    .unwrap();
+
+    let balance: cw20::BalanceResponse = app
+        .query(
+            asset_addr.clone(),
+            &cw20::Cw20QueryMsg::Balance {
+                address: "addr0000".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(balance.balance, Uint128::from(1000u128 * 10u128.pow(12)));
+
+    // asset => orai with the ConvertReverse hook
+    app.execute(
+        Addr::unchecked("addr0000"),
+        asset_addr,
+        &cw20::Cw20ExecuteMsg::Send {
+            contract: router_addr.to_string(),
+            amount: Uint128::from(400u128 * 10u128.pow(12)),
+            msg: to_json_binary(&Cw20HookMsg::ExecuteSwapOperations {
+                operations: convert_reverse,
+                minimum_receive: Some(Uint128::from(400u128)),
+                to: None,
+            })
+            .unwrap(),
+        },
+        &[],
+    )
+    .unwrap();
+
+    let balance = app
+        .query_balance(Addr::unchecked("addr0000"), ORAI_DENOM.to_string())
+        .unwrap();
+    assert_eq!(balance, Uint128::from(1000000u128 - 1000 + 400));
+}
@@ -1,12 +1,15 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
-use cosmwasm_std::{to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult};
+use cosmwasm_std::{to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response};
 use cw2::set_contract_version;
 use oraiswap::router::RouterController;
 
 use crate::error::ContractError;
-use crate::execute::{delete_route, set_route, update_config};
-use crate::query::{query_config, query_route, query_routes, query_smart_route};
+use crate::execute::{delete_route, refresh_routes, set_route, swap, update_config};
+use crate::query::{
+    query_config, query_discovered_routes, query_discovered_v3_routes, query_find_routes,
+    query_route, query_routes, query_smart_route, query_smart_split_route,
+};
 use crate::state::{Config, CONFIG};
 use oraiswap::smartrouter::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
 
@@ -30,6 +33,8 @@ pub fn instantiate(
     let state = Config {
         owner,
         router_contract,
+        keeper: None,
+        mixed_router: None,
     };
     CONFIG.save(deps.storage, &state)?;
 
@@ -70,44 +75,102 @@ pub fn execute(
         ExecuteMsg::UpdateConfig {
             new_owner,
             new_router,
-        } => update_config(deps, info, new_owner, new_router),
+            new_keeper,
+            new_mixed_router,
+        } => update_config(
+            deps,
+            info,
+            new_owner,
+            new_router,
+            new_keeper,
+            new_mixed_router,
+        ),
+        ExecuteMsg::RefreshRoutes {
+            input_info,
+            output_info,
+            max_hops,
+        } => refresh_routes(deps, info, input_info, output_info, max_hops),
+        ExecuteMsg::Swap {
+            input_coin,
+            output_denom,
+            slippage,
+            route,
+        } => swap(deps, info, input_coin, output_denom, slippage, route),
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> Result<Binary, ContractError> {
     match msg {
-        QueryMsg::Config {} => to_json_binary(&query_config(deps)?),
+        QueryMsg::Config {} => Ok(to_json_binary(&query_config(deps)?)?),
         QueryMsg::GetRoute {
             input_info,
             output_info,
             route_index,
-        } => to_json_binary(&query_route(
+        } => Ok(to_json_binary(&query_route(
             deps,
             &input_info.to_string(),
             &output_info.to_string(),
             route_index,
-        )?),
+        )?)?),
         QueryMsg::GetRoutes {
             input_info,
             output_info,
-        } => to_json_binary(&query_routes(
+        } => Ok(to_json_binary(&query_routes(
             deps,
             &input_info.to_string(),
             &output_info.to_string(),
-        )?),
+        )?)?),
         QueryMsg::GetSmartRoute {
             input_info,
             output_info,
             offer_amount,
             route_mode,
-        } => to_json_binary(&query_smart_route(
+        } => Ok(to_json_binary(&query_smart_route(
             deps,
             input_info,
             output_info,
             offer_amount,
             route_mode,
-        )?),
+        )?)?),
+        QueryMsg::GetSmartSplitRoute {
+            input_info,
+            output_info,
+            offer_amount,
+            max_splits,
+        } => Ok(to_json_binary(&query_smart_split_route(
+            deps,
+            input_info,
+            output_info,
+            offer_amount,
+            max_splits,
+        )?)?),
+        QueryMsg::FindRoutes {
+            input_info,
+            output_info,
+            max_hops,
+        } => Ok(to_json_binary(&query_find_routes(
+            deps,
+            input_info,
+            output_info,
+            max_hops,
+        )?)?),
+        QueryMsg::GetDiscoveredRoutes {
+            input_info,
+            output_info,
+        } => Ok(to_json_binary(&query_discovered_routes(
+            deps,
+            &input_info.to_string(),
+            &output_info.to_string(),
+        )?)?),
+        QueryMsg::GetDiscoveredV3Routes {
+            input_info,
+            output_info,
+        } => Ok(to_json_binary(&query_discovered_v3_routes(
+            deps,
+            &input_info.to_string(),
+            &output_info.to_string(),
+        )?)?),
     }
 }
 
@@ -1,4 +1,5 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{Decimal, StdError};
+use oraiswap::smartrouter::SmartRouteError;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -36,6 +37,31 @@ pub enum ContractError {
     #[error("InvalidTwap: Invalid value for twap price: {operation}.")]
     InvalidTwapOperation { operation: String },
 
+    #[error("Invalid slippage percentage: {slippage}, must not exceed 1")]
+    InvalidSlippage { slippage: Decimal },
+
+    #[error(
+        "No oracle rate for {denom}, the twap slippage needs both assets priced by the oracle"
+    )]
+    OracleRateNotFound { denom: String },
+
+    #[error("Oracle twap from {input_denom} to {output_denom} not found: {reason}")]
+    OracleTwapNotFound {
+        input_denom: String,
+        output_denom: String,
+        reason: String,
+    },
+
+    #[error(
+        "Minimum receive of simulate smart route is 0. Err: {}",
+        .errors
+            .iter()
+            .map(|error| format!("route {}: {}", error.route_index, error.error))
+            .collect::<Vec<_>>()
+            .join("; ")
+    )]
+    NoSmartRoute { errors: Vec<SmartRouteError> },
+
     #[error("Custom Error: {val:?}")]
     CustomError { val: String },
     // Add any other custom errors you like here.
@@ -1,9 +1,16 @@
-use cosmwasm_std::{DepsMut, MessageInfo, Response};
+use cosmwasm_std::{Coin, DepsMut, MessageInfo, Response};
+use oraiswap::asset::AssetInfo;
+use oraiswap::mixed_router::{MixedRouterController, SwapOperation as MixedSwapOperation};
 use oraiswap::router::{RouterController, SwapOperation};
+use oraiswap::smartrouter::Slippage;
 
 use crate::error::ContractError;
-use crate::helpers::{check_is_contract_owner, validate_pool_route};
-use crate::state::{store_route, CONFIG, ROUTING_TABLE};
+use crate::helpers::{
+    check_is_contract_owner, check_is_owner_or_keeper, check_route_assets, find_routes,
+    query_twap_minimum_receive, validate_pool_route,
+};
+use crate::query::query_smart_route;
+use crate::state::{store_route, CONFIG, DISCOVERED_ROUTES, DISCOVERED_V3_ROUTES, ROUTING_TABLE};
 
 pub fn set_route(
     deps: DepsMut,
@@ -21,11 +28,7 @@ pub fn set_route(
         output_denom.clone(),
         pool_route.clone(),
     )?;
-    store_route(
-        deps.storage,
-        (&input_denom, &output_denom),
-        &pool_route,
-    )?;
+    store_route(deps.storage, (&input_denom, &output_denom), &pool_route)?;
 
     Ok(Response::new().add_attribute("action", "set_route"))
 }
@@ -52,6 +55,8 @@ pub fn update_config(
     info: MessageInfo,
     new_owner: Option<String>,
     new_router: Option<String>,
+    new_keeper: Option<String>,
+    new_mixed_router: Option<String>,
 ) -> Result<Response, ContractError> {
     // only owner can update config
     check_is_contract_owner(deps.as_ref(), info.sender)?;
@@ -64,6 +69,115 @@ pub fn update_config(
     if let Some(new_router) = new_router {
         state.router_contract = RouterController(new_router);
     }
+    if let Some(new_keeper) = new_keeper {
+        state.keeper = Some(deps.api.addr_validate(&new_keeper)?);
+    }
+    if let Some(new_mixed_router) = new_mixed_router {
+        state.mixed_router = Some(MixedRouterController(new_mixed_router));
+    }
     CONFIG.save(deps.storage, &state)?;
     Ok(Response::new().add_attribute("action", "update_config"))
 }
+
+pub fn refresh_routes(
+    deps: DepsMut,
+    info: MessageInfo,
+    input_info: AssetInfo,
+    output_info: AssetInfo,
+    max_hops: u32,
+) -> Result<Response, ContractError> {
+    check_is_owner_or_keeper(deps.as_ref(), info.sender)?;
+
+    let config = CONFIG.load(deps.storage)?;
+    let candidates = find_routes(deps.as_ref(), &config, &input_info, &output_info, max_hops)?;
+    let key = (input_info.to_string(), output_info.to_string());
+    let routes: Vec<Vec<SwapOperation>> = candidates
+        .routes
+        .into_iter()
+        .map(|candidate| candidate.operations)
+        .collect();
+    DISCOVERED_ROUTES.save(deps.storage, (&key.0, &key.1), &routes)?;
+    let v3_routes: Vec<Vec<MixedSwapOperation>> = candidates
+        .v3_routes
+        .into_iter()
+        .map(|candidate| candidate.operations)
+        .collect();
+    DISCOVERED_V3_ROUTES.save(deps.storage, (&key.0, &key.1), &v3_routes)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "refresh_routes")
+        .add_attribute("input_info", key.0)
+        .add_attribute("output_info", key.1)
+        .add_attribute("routes", routes.len().to_string())
+        .add_attribute("v3_routes", v3_routes.len().to_string()))
+}
+
+pub fn swap(
+    deps: DepsMut,
+    info: MessageInfo,
+    input_coin: Coin,
+    output_denom: String,
+    slippage: Slippage,
+    route: Option<Vec<SwapOperation>>,
+) -> Result<Response, ContractError> {
+    // any other funds would be left in the router
+    if input_coin.amount.is_zero() || info.funds != vec![input_coin.clone()] {
+        return Err(ContractError::InsufficientFunds {});
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    let router = config.router_contract;
+    let input_info = AssetInfo::NativeToken {
+        denom: input_coin.denom.clone(),
+    };
+    let output_info = AssetInfo::from_denom(deps.api, &output_denom);
+
+    let operations = match route {
+        Some(route) => {
+            check_route_assets(&route, &input_info, &output_info)?;
+            route
+        }
+        None => {
+            query_smart_route(
+                deps.as_ref(),
+                input_info.clone(),
+                output_info.clone(),
+                input_coin.amount,
+                None,
+            )?
+            .swap_ops
+        }
+    };
+
+    let minimum_receive = match slippage {
+        Slippage::MinOutputAmount(minimum_receive) => minimum_receive,
+        Slippage::Twap {
+            window_seconds,
+            slippage_percentage,
+        } => query_twap_minimum_receive(
+            &deps.querier,
+            &router,
+            &input_info,
+            &output_info,
+            input_coin.amount,
+            window_seconds,
+            slippage_percentage,
+        )?,
+    };
+
+    let swap_msg = router.execute_operations(
+        input_info,
+        input_coin.amount,
+        operations,
+        Some(minimum_receive),
+        Some(info.sender.clone()),
+    )?;
+
+    Ok(Response::new()
+        .add_message(swap_msg)
+        .add_attribute("action", "swap")
+        .add_attribute("sender", info.sender)
+        .add_attribute("input_coin", input_coin.to_string())
+        .add_attribute("output_denom", output_denom)
+        .add_attribute("minimum_receive", minimum_receive))
+}
@@ -1,7 +1,35 @@
-use cosmwasm_std::{Addr, Deps};
-use oraiswap::router::SwapOperation;
+use std::collections::BTreeMap;
 
-use crate::{state::CONFIG, ContractError};
+use cosmwasm_std::{Addr, Decimal, Deps, QuerierWrapper, StdError, StdResult, Storage, Uint128};
+use oraiswap::asset::{AssetInfo, PairInfo};
+use oraiswap::factory::{
+    ConfigResponse as FactoryConfigResponse, PairsResponse, QueryMsg as FactoryQueryMsg,
+};
+use oraiswap::mixed_router::{
+    ConfigResponse as MixedRouterConfigResponse, QueryMsg as MixedRouterQueryMsg,
+    SwapOperation as MixedSwapOperation,
+};
+use oraiswap::oracle::OracleContract;
+use oraiswap::router::{ConfigResponse as RouterConfigResponse, QueryMsg as RouterQueryMsg};
+use oraiswap::router::{RouterController, SwapOperation};
+use oraiswap::smartrouter::{
+    FindRoutesResponse, RouteCandidate, SmartSwapOperation, V3RouteCandidate,
+};
+use oraiswap_v3::interface::PoolWithPoolKey;
+use oraiswap_v3::msg::QueryMsg as SwapV3QueryMsg;
+use oraiswap_v3::PoolKey;
+
+use crate::state::{Config, CONFIG, DISCOVERED_ROUTES, ROUTING_TABLE};
+use crate::ContractError;
+
+// the factory caps pairs queries at 30, the v3 pools are paged the same
+const PAIRS_PAGE_LIMIT: u32 = 30;
+pub const MAX_HOPS: u32 = 3;
+// paths simulated per search, the shorter ones are found first
+pub const MAX_ROUTE_CANDIDATES: usize = 20;
+// offer amount used to rank the candidate routes, one unit of a 6 decimals asset
+pub const PROBE_OFFER_AMOUNT: Uint128 = Uint128::new(1_000_000);
+pub const DEFAULT_TWAP_WINDOW_SECONDS: u64 = 3600;
 
 pub fn check_is_contract_owner(deps: Deps, sender: Addr) -> Result<(), ContractError> {
     let config = CONFIG.load(deps.storage)?;
@@ -12,6 +40,15 @@ pub fn check_is_contract_owner(deps: Deps, sender: Addr) -> Result<(), ContractE
     }
 }
 
+pub fn check_is_owner_or_keeper(deps: Deps, sender: Addr) -> Result<(), ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.owner != sender && config.keeper != Some(sender) {
+        Err(ContractError::Unauthorized {})
+    } else {
+        Ok(())
+    }
+}
+
 pub fn validate_pool_route(
     _deps: Deps,
     _input_denom: String,
@@ -20,4 +57,404 @@ pub fn validate_pool_route(
 ) -> Result<(), ContractError> {
     // FIXME: try simulating
     Ok(())
-}
\ No newline at end of file
+}
+
+/// the route must go from input_info to output_info with each operation starting at the previous ask
+pub fn check_route_assets(
+    route: &[SwapOperation],
+    input_info: &AssetInfo,
+    output_info: &AssetInfo,
+) -> Result<(), ContractError> {
+    let mut current = input_info.clone();
+    for operation in route {
+        if operation.get_offer_asset_info() != current {
+            return Err(ContractError::InvalidPoolRoute {
+                reason: format!("operation does not offer {}", current),
+            });
+        }
+        current = operation.get_target_asset_info();
+    }
+    if route.is_empty() || current.ne(output_info) {
+        return Err(ContractError::InvalidPoolRoute {
+            reason: format!("route does not end with {}", output_info),
+        });
+    }
+    Ok(())
+}
+
+/// offer_amount converted at the oracle twap of the window less slippage_percentage,
+/// the oracle is the one of the factory of the router and must price both assets,
+/// with a pushed rate or a fallback price, Orai is always priced
+pub fn query_twap_minimum_receive(
+    querier: &QuerierWrapper,
+    router: &RouterController,
+    input_info: &AssetInfo,
+    output_info: &AssetInfo,
+    offer_amount: Uint128,
+    window_seconds: Option<u64>,
+    slippage_percentage: Decimal,
+) -> Result<Uint128, ContractError> {
+    if slippage_percentage > Decimal::one() {
+        return Err(ContractError::InvalidSlippage {
+            slippage: slippage_percentage,
+        });
+    }
+    let router_config: RouterConfigResponse =
+        querier.query_wasm_smart(router.addr(), &RouterQueryMsg::Config {})?;
+    let factory_config: FactoryConfigResponse =
+        querier.query_wasm_smart(router_config.factory_addr, &FactoryQueryMsg::Config {})?;
+    let oracle = OracleContract(factory_config.oracle_addr);
+    for asset_info in [input_info, output_info] {
+        if oracle.query_asset_price(querier, asset_info).is_err() {
+            return Err(ContractError::OracleRateNotFound {
+                denom: asset_info.to_string(),
+            });
+        }
+    }
+    let twap = oracle
+        .query_exchange_rate_twap(
+            querier,
+            input_info,
+            output_info,
+            window_seconds.unwrap_or(DEFAULT_TWAP_WINDOW_SECONDS),
+        )
+        .map_err(|err| ContractError::OracleTwapNotFound {
+            input_denom: input_info.to_string(),
+            output_denom: output_info.to_string(),
+            reason: err.to_string(),
+        })?;
+    Ok(offer_amount * (twap.exchange_rate * (Decimal::one() - slippage_percentage)))
+}
+
+/// the routes set by the owner, then the discovered ones not set already
+pub fn load_pool_routes(
+    storage: &dyn Storage,
+    input_info: &AssetInfo,
+    output_info: &AssetInfo,
+) -> StdResult<Vec<Vec<SwapOperation>>> {
+    let key = (input_info.to_string(), output_info.to_string());
+    let mut pool_routes = ROUTING_TABLE
+        .may_load(storage, (&key.0, &key.1))?
+        .unwrap_or_default();
+    for route in DISCOVERED_ROUTES
+        .may_load(storage, (&key.0, &key.1))?
+        .unwrap_or_default()
+    {
+        if !pool_routes.contains(&route) {
+            pool_routes.push(route);
+        }
+    }
+    if pool_routes.is_empty() {
+        return Err(StdError::generic_err(format!(
+            "No route found from {} to {}",
+            key.0, key.1
+        )));
+    }
+    Ok(pool_routes)
+}
+
+/// translate the operations to the universal swap memo, pairs are looked up on the factories of the router
+pub fn to_smart_swap_operations(
+    querier: &QuerierWrapper,
+    router: &RouterController,
+    operations: &[SwapOperation],
+) -> StdResult<Vec<SmartSwapOperation>> {
+    let router_config: RouterConfigResponse =
+        querier.query_wasm_smart(router.addr(), &RouterQueryMsg::Config {})?;
+    operations
+        .iter()
+        .map(|operation| {
+            let pool_id = match operation {
+                SwapOperation::OraiSwap {
+                    offer_asset_info,
+                    ask_asset_info,
+                } => {
+                    let query_msg = FactoryQueryMsg::Pair {
+                        asset_infos: [offer_asset_info.clone(), ask_asset_info.clone()],
+                    };
+                    let pair_info: PairInfo = querier
+                        .query_wasm_smart(&router_config.factory_addr, &query_msg)
+                        .or_else(|_| {
+                            querier.query_wasm_smart(&router_config.factory_addr_v2, &query_msg)
+                        })?;
+                    pair_info.contract_addr.into_string()
+                }
+                SwapOperation::OrderbookMarket { orderbook_addr, .. } => orderbook_addr.to_string(),
+                SwapOperation::Convert { .. } => router_config
+                    .converter
+                    .clone()
+                    .map(|converter| converter.into_string())
+                    .unwrap_or_default(),
+            };
+            Ok(SmartSwapOperation {
+                pool_id,
+                denom_in: operation.get_offer_asset_info().to_string(),
+                denom_out: operation.get_target_asset_info().to_string(),
+            })
+        })
+        .collect()
+}
+
+fn query_all_pairs(
+    querier: &QuerierWrapper,
+    factory_addr: &Addr,
+) -> StdResult<Vec<[AssetInfo; 2]>> {
+    let mut pairs = vec![];
+    let mut start_after = None;
+    loop {
+        let res: PairsResponse = querier.query_wasm_smart(
+            factory_addr,
+            &FactoryQueryMsg::Pairs {
+                start_after,
+                limit: Some(PAIRS_PAGE_LIMIT),
+            },
+        )?;
+        let page_len = res.pairs.len();
+        start_after = res.pairs.last().map(|pair| pair.asset_infos.clone());
+        pairs.extend(res.pairs.into_iter().map(|pair| pair.asset_infos));
+        if page_len < PAIRS_PAGE_LIMIT as usize {
+            return Ok(pairs);
+        }
+    }
+}
+
+fn query_all_v3_pools(querier: &QuerierWrapper, oraiswap_v3: &Addr) -> StdResult<Vec<PoolKey>> {
+    let mut pool_keys = vec![];
+    let mut start_after = None;
+    loop {
+        let res: Vec<PoolWithPoolKey> = querier.query_wasm_smart(
+            oraiswap_v3,
+            &SwapV3QueryMsg::Pools {
+                limit: Some(PAIRS_PAGE_LIMIT),
+                start_after,
+            },
+        )?;
+        let page_len = res.len();
+        start_after = res.last().map(|pool| pool.pool_key.clone());
+        pool_keys.extend(res.into_iter().map(|pool| pool.pool_key));
+        if page_len < PAIRS_PAGE_LIMIT as usize {
+            return Ok(pool_keys);
+        }
+    }
+}
+
+// pool of the route graph, the pool key is set for v3 pools
+struct RoutePool {
+    asset_infos: [AssetInfo; 2],
+    pool_key: Option<PoolKey>,
+}
+
+// pools indexed by each of their assets, so a path only walks the pools it can go through
+struct RouteGraph {
+    pools: Vec<RoutePool>,
+    adjacency: BTreeMap<String, Vec<usize>>,
+}
+
+impl RouteGraph {
+    fn new(pools: Vec<RoutePool>) -> Self {
+        let mut adjacency: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        for (index, pool) in pools.iter().enumerate() {
+            for asset_info in &pool.asset_infos {
+                adjacency
+                    .entry(asset_info.to_string())
+                    .or_default()
+                    .push(index);
+            }
+        }
+        Self { pools, adjacency }
+    }
+
+    fn pools_of(&self, asset_info: &AssetInfo) -> impl Iterator<Item = &RoutePool> {
+        self.adjacency
+            .get(&asset_info.to_string())
+            .into_iter()
+            .flatten()
+            .map(|index| &self.pools[*index])
+    }
+}
+
+// paths of exactly hops operations, until there are MAX_ROUTE_CANDIDATES paths
+fn collect_paths(
+    graph: &RouteGraph,
+    current: &AssetInfo,
+    output_info: &AssetInfo,
+    hops: u32,
+    visited: &mut Vec<AssetInfo>,
+    path: &mut Vec<MixedSwapOperation>,
+    paths: &mut Vec<Vec<MixedSwapOperation>>,
+) {
+    if current.eq(output_info) {
+        if path.len() as u32 == hops {
+            paths.push(path.clone());
+        }
+        return;
+    }
+    if path.len() as u32 >= hops {
+        return;
+    }
+    for pool in graph.pools_of(current) {
+        if paths.len() >= MAX_ROUTE_CANDIDATES {
+            return;
+        }
+        let [a, b] = &pool.asset_infos;
+        let (next, x_to_y) = if a.eq(current) {
+            (b, true)
+        } else if b.eq(current) {
+            (a, false)
+        } else {
+            continue;
+        };
+        if visited.contains(next) {
+            continue;
+        }
+        visited.push(next.clone());
+        path.push(match &pool.pool_key {
+            Some(pool_key) => MixedSwapOperation::SwapV3 {
+                pool_key: pool_key.clone(),
+                x_to_y,
+                sqrt_price_limit: None,
+                by_amount_in: true,
+            },
+            None => MixedSwapOperation::OraiSwap {
+                offer_asset_info: current.clone(),
+                ask_asset_info: next.clone(),
+            },
+        });
+        collect_paths(graph, next, output_info, hops, visited, path, paths);
+        path.pop();
+        visited.pop();
+    }
+}
+
+/// enumerate the paths of at most max_hops over the pairs of both factories of the router,
+/// and over the v3 pools when a mixed router is set, simulate the MAX_ROUTE_CANDIDATES shortest
+/// ones with the probe amount and return those that can be swapped, best return first.
+/// the paths through a v3 pool are simulated by the mixed router, the others by the router
+pub fn find_routes(
+    deps: Deps,
+    config: &Config,
+    input_info: &AssetInfo,
+    output_info: &AssetInfo,
+    max_hops: u32,
+) -> StdResult<FindRoutesResponse> {
+    if max_hops == 0 || max_hops > MAX_HOPS {
+        return Err(StdError::generic_err(format!(
+            "max_hops must be between 1 and {}",
+            MAX_HOPS
+        )));
+    }
+    if input_info.eq(output_info) {
+        return Err(StdError::generic_err(
+            "input and output assets are the same",
+        ));
+    }
+
+    let router = &config.router_contract;
+    let router_config: RouterConfigResponse = deps
+        .querier
+        .query_wasm_smart(router.addr(), &RouterQueryMsg::Config {})?;
+    let mut edges = query_all_pairs(&deps.querier, &router_config.factory_addr)?;
+    if router_config.factory_addr_v2 != router_config.factory_addr {
+        for pair in query_all_pairs(&deps.querier, &router_config.factory_addr_v2)? {
+            let reversed = [pair[1].clone(), pair[0].clone()];
+            if !edges.contains(&pair) && !edges.contains(&reversed) {
+                edges.push(pair);
+            }
+        }
+    }
+    let mut pools: Vec<RoutePool> = edges
+        .into_iter()
+        .map(|asset_infos| RoutePool {
+            asset_infos,
+            pool_key: None,
+        })
+        .collect();
+    if let Some(mixed_router) = &config.mixed_router {
+        let mixed_router_config: MixedRouterConfigResponse = deps
+            .querier
+            .query_wasm_smart(mixed_router.addr(), &MixedRouterQueryMsg::Config {})?;
+        for pool_key in query_all_v3_pools(&deps.querier, &mixed_router_config.oraiswap_v3)? {
+            pools.push(RoutePool {
+                asset_infos: [
+                    AssetInfo::from_denom(deps.api, &pool_key.token_x),
+                    AssetInfo::from_denom(deps.api, &pool_key.token_y),
+                ],
+                pool_key: Some(pool_key),
+            });
+        }
+    }
+
+    let graph = RouteGraph::new(pools);
+    let mut paths = vec![];
+    for hops in 1..=max_hops {
+        collect_paths(
+            &graph,
+            input_info,
+            output_info,
+            hops,
+            &mut vec![input_info.clone()],
+            &mut vec![],
+            &mut paths,
+        );
+    }
+
+    let mut routes = vec![];
+    let mut v3_routes = vec![];
+    for operations in paths {
+        let is_v3 = operations
+            .iter()
+            .any(|operation| matches!(operation, MixedSwapOperation::SwapV3 { .. }));
+        if is_v3 {
+            // the v3 pools are only listed with a mixed router
+            let Some(mixed_router) = &config.mixed_router else {
+                continue;
+            };
+            if let Ok(res) =
+                mixed_router.simulate_swap(&deps.querier, PROBE_OFFER_AMOUNT, operations.clone())
+            {
+                if !res.amount.is_zero() {
+                    v3_routes.push(V3RouteCandidate {
+                        operations,
+                        return_amount: res.amount,
+                    });
+                }
+            }
+            continue;
+        }
+
+        let operations: Vec<SwapOperation> = operations
+            .into_iter()
+            .filter_map(|operation| match operation {
+                MixedSwapOperation::OraiSwap {
+                    offer_asset_info,
+                    ask_asset_info,
+                } => Some(SwapOperation::OraiSwap {
+                    offer_asset_info,
+                    ask_asset_info,
+                }),
+                _ => None,
+            })
+            .collect();
+        if let Ok(res) = router.simulate_swap(&deps.querier, PROBE_OFFER_AMOUNT, operations.clone())
+        {
+            if !res.amount.is_zero() {
+                routes.push(RouteCandidate {
+                    operations,
+                    return_amount: res.amount,
+                });
+            }
+        }
+    }
+    // shorter route first on equal returns
+    routes.sort_by(|a, b| {
+        b.return_amount
+            .cmp(&a.return_amount)
+            .then(a.operations.len().cmp(&b.operations.len()))
+    });
+    v3_routes.sort_by(|a, b| {
+        b.return_amount
+            .cmp(&a.return_amount)
+            .then(a.operations.len().cmp(&b.operations.len()))
+    });
+    Ok(FindRoutesResponse { routes, v3_routes })
+}
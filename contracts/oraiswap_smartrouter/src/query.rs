@@ -1,11 +1,15 @@
-use cosmwasm_std::{Deps, StdError, StdResult, Uint128};
+use cosmwasm_std::{Decimal, Decimal256, Deps, StdError, StdResult, Uint128};
 
-use crate::state::{CONFIG, ROUTING_TABLE};
+use crate::helpers::{find_routes, load_pool_routes, to_smart_swap_operations};
+use crate::state::{CONFIG, DISCOVERED_ROUTES, DISCOVERED_V3_ROUTES, ROUTING_TABLE};
+use crate::ContractError;
 use oraiswap::{
     asset::AssetInfo,
+    router::SwapOperation,
     smartrouter::{
-        GetConfigResponse, GetRouteResponse, GetRoutesResponse, GetSmartRouteResponse,
-        SmartRouteMode,
+        FindRoutesResponse, GetConfigResponse, GetRouteResponse, GetRoutesResponse,
+        GetSmartRouteResponse, GetSmartSplitRouteResponse, GetV3RoutesResponse,
+        SmartRouteCandidate, SmartRouteError, SmartRouteMode, SmartSwapRoute, SwapVenue,
     },
 };
 
@@ -15,6 +19,8 @@ pub fn query_config(deps: Deps) -> StdResult<GetConfigResponse> {
     Ok(GetConfigResponse {
         owner: state.owner.into_string(),
         router: state.router_contract.addr(),
+        keeper: state.keeper.map(|keeper| keeper.into_string()),
+        mixed_router: state.mixed_router.map(|mixed_router| mixed_router.addr()),
     })
 }
 
@@ -45,54 +51,275 @@ pub fn query_routes(
     })
 }
 
+pub fn query_discovered_routes(
+    deps: Deps,
+    input_token: &str,
+    output_token: &str,
+) -> StdResult<GetRoutesResponse> {
+    Ok(GetRoutesResponse {
+        pool_routes: DISCOVERED_ROUTES
+            .may_load(deps.storage, (input_token, output_token))?
+            .unwrap_or_default(),
+    })
+}
+
+pub fn query_discovered_v3_routes(
+    deps: Deps,
+    input_token: &str,
+    output_token: &str,
+) -> StdResult<GetV3RoutesResponse> {
+    Ok(GetV3RoutesResponse {
+        pool_routes: DISCOVERED_V3_ROUTES
+            .may_load(deps.storage, (input_token, output_token))?
+            .unwrap_or_default(),
+    })
+}
+
+pub fn query_find_routes(
+    deps: Deps,
+    input_info: AssetInfo,
+    output_info: AssetInfo,
+    max_hops: u32,
+) -> StdResult<FindRoutesResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    find_routes(deps, &config, &input_info, &output_info, max_hops)
+}
+
+// the first one wins on equal scores
+fn max_score<'a>(
+    candidates: impl DoubleEndedIterator<Item = &'a SmartRouteCandidate>,
+) -> Option<&'a SmartRouteCandidate> {
+    candidates.rev().max_by_key(|candidate| candidate.score)
+}
+
 pub fn query_smart_route(
     deps: Deps,
     input_info: AssetInfo,
     output_info: AssetInfo,
     offer_amount: Uint128,
     route_mode: Option<SmartRouteMode>,
-) -> StdResult<GetSmartRouteResponse> {
+) -> Result<GetSmartRouteResponse, ContractError> {
     let config = CONFIG.load(deps.storage)?;
     let router = config.router_contract;
     let route_mode = route_mode.unwrap_or(SmartRouteMode::MaxMinimumReceive);
-    let pool_routes = ROUTING_TABLE.load(
-        deps.storage,
-        (&input_info.to_string(), &output_info.to_string()),
-    )?;
+    let hop_factor = match &route_mode {
+        SmartRouteMode::MaxOutputWithHopPenalty { penalty_per_hop } => {
+            if *penalty_per_hop > Decimal::one() {
+                return Err(StdError::generic_err("penalty_per_hop must not exceed 1").into());
+            }
+            Decimal256::one() - Decimal256::from(*penalty_per_hop)
+        }
+        _ => Decimal256::one(),
+    };
+    let pool_routes = load_pool_routes(deps.storage, &input_info, &output_info)?;
+
+    let mut candidates: Vec<SmartRouteCandidate> = vec![];
+    let mut errors: Vec<SmartRouteError> = vec![];
+    for (route_index, route) in pool_routes.into_iter().enumerate() {
+        let simulate_result = match route_mode {
+            SmartRouteMode::MinPriceImpact => router
+                .simulate_swap_detailed(&deps.querier, offer_amount, route.clone())
+                .map(|res| (res.amount, Some(res.price_impact))),
+            _ => router
+                .simulate_swap(&deps.querier, offer_amount, route.clone())
+                .map(|res| (res.amount, None)),
+        };
+        let (return_amount, price_impact) = match simulate_result {
+            Ok(result) => result,
+            Err(err) => {
+                errors.push(SmartRouteError {
+                    route_index,
+                    swap_ops: route,
+                    error: err.to_string(),
+                });
+                continue;
+            }
+        };
+        let return_amount_256 = Decimal256::from_ratio(return_amount, 1u128);
+        let score = match &route_mode {
+            SmartRouteMode::MaxMinimumReceive | SmartRouteMode::PreferVenue(_) => return_amount_256,
+            SmartRouteMode::MinHops => Decimal256::from_ratio(route.len() as u128, 1u128),
+            SmartRouteMode::MinPriceImpact => Decimal256::from(price_impact.unwrap_or_default()),
+            SmartRouteMode::MaxOutputWithHopPenalty { .. } => {
+                return_amount_256
+                    * hop_factor
+                        .checked_pow(route.len() as u32)
+                        .map_err(StdError::from)?
+            }
+        };
+        candidates.push(SmartRouteCandidate {
+            route_index,
+            swap_ops: route,
+            return_amount,
+            price_impact,
+            score,
+        });
+    }
+
+    // a route returning nothing is never picked
+    let selectable = candidates
+        .iter()
+        .filter(|candidate| !candidate.return_amount.is_zero());
+    let best = match &route_mode {
+        SmartRouteMode::PreferVenue(venues) => {
+            let uses_venues = |candidate: &&SmartRouteCandidate| {
+                candidate
+                    .swap_ops
+                    .iter()
+                    .all(|operation| venues.contains(&SwapVenue::of(operation)))
+            };
+            max_score(selectable.clone().filter(uses_venues)).or_else(|| max_score(selectable))
+        }
+        SmartRouteMode::MinHops | SmartRouteMode::MinPriceImpact => selectable.min_by(|a, b| {
+            a.score
+                .cmp(&b.score)
+                .then(b.return_amount.cmp(&a.return_amount))
+        }),
+        SmartRouteMode::MaxMinimumReceive | SmartRouteMode::MaxOutputWithHopPenalty { .. } => {
+            max_score(selectable)
+        }
+    };
+
+    let Some(best) = best else {
+        return Err(ContractError::NoSmartRoute { errors });
+    };
+
+    Ok(GetSmartRouteResponse {
+        swap_ops: best.swap_ops.clone(),
+        actual_minimum_receive: best.return_amount,
+        score: Some(best.score),
+        candidates: Some(candidates.clone()),
+        errors: Some(errors),
+    })
+}
+
+// number of chunks the offer amount is allocated in
+const SPLIT_STEPS: u128 = 10;
+
+// pools a route swaps through, a pair is the same pool whichever way it is crossed
+fn route_pools(route: &[SwapOperation]) -> Vec<String> {
+    route
+        .iter()
+        .map(|operation| {
+            let mut assets = [
+                operation.get_offer_asset_info().to_string(),
+                operation.get_target_asset_info().to_string(),
+            ];
+            assets.sort();
+            let [a, b] = assets;
+            match operation {
+                SwapOperation::OraiSwap { .. } => format!("pair:{}:{}", a, b),
+                SwapOperation::OrderbookMarket { orderbook_addr, .. } => {
+                    format!("orderbook:{}:{}:{}", orderbook_addr, a, b)
+                }
+                SwapOperation::Convert { .. } => format!("convert:{}:{}", a, b),
+            }
+        })
+        .collect()
+}
+
+/// allocate offer_amount chunk by chunk to the route with the best marginal return,
+/// using at most max_splits routes. each route is simulated alone, so a route sharing a pool
+/// with an allocated one is skipped instead of counting the liquidity of that pool twice
+pub fn query_smart_split_route(
+    deps: Deps,
+    input_info: AssetInfo,
+    output_info: AssetInfo,
+    offer_amount: Uint128,
+    max_splits: u32,
+) -> StdResult<GetSmartSplitRouteResponse> {
+    if max_splits == 0 {
+        return Err(StdError::generic_err("max_splits must be greater than 0"));
+    }
+    let config = CONFIG.load(deps.storage)?;
+    let router = config.router_contract;
+    let pool_routes = load_pool_routes(deps.storage, &input_info, &output_info)?;
+    let pools_of_routes: Vec<Vec<String>> =
+        pool_routes.iter().map(|route| route_pools(route)).collect();
+
+    let steps = SPLIT_STEPS.min(offer_amount.u128()).max(1);
+    let chunk = offer_amount.u128() / steps;
+    let remainder = offer_amount.u128() % steps;
+
+    // (allocated offer, simulated return) of every route
+    let mut allocations = vec![(Uint128::zero(), Uint128::zero()); pool_routes.len()];
     let mut simulate_swap_errors: String = String::from("");
-    let mut route_simulate_result: (usize, Uint128) = (
-        0usize,          // wanted route index
-        Uint128::zero(), // actual minimum receive
-    );
-    for (index, route) in pool_routes.iter().enumerate() {
-        match router.simulate_swap(&deps.querier, offer_amount, route.clone()) {
-            Ok(simulate_result) => {
-                let prev_route_minimum_receive = route_simulate_result.1;
-                match route_mode {
-                    SmartRouteMode::MaxMinimumReceive => {
-                        route_simulate_result.1 =
-                            route_simulate_result.1.max(simulate_result.amount);
-                    }
+    for step in 0..steps {
+        // the remainder goes with the first chunks
+        let step_amount = Uint128::from(chunk + u128::from(step < remainder));
+        let used_splits = allocations
+            .iter()
+            .filter(|(offer, _)| !offer.is_zero())
+            .count();
+
+        // (route index, return after adding the chunk, marginal return)
+        let mut best: Option<(usize, Uint128, Uint128)> = None;
+        for (index, route) in pool_routes.iter().enumerate() {
+            let (allocated, returned) = allocations[index];
+            if allocated.is_zero() {
+                if used_splits >= max_splits as usize {
+                    continue;
                 }
-                if prev_route_minimum_receive.ne(&route_simulate_result.1) {
-                    route_simulate_result.0 = index;
+                let shares_pool = allocations.iter().zip(&pools_of_routes).any(
+                    |((allocated_offer, _), allocated_pools)| {
+                        !allocated_offer.is_zero()
+                            && allocated_pools
+                                .iter()
+                                .any(|pool| pools_of_routes[index].contains(pool))
+                    },
+                );
+                if shares_pool {
+                    continue;
                 }
             }
-            Err(err) => {
-                println!("err: {:?}", err);
-                simulate_swap_errors = simulate_swap_errors + &err.to_string() + ";";
-                continue;
+            match router.simulate_swap(&deps.querier, allocated + step_amount, route.clone()) {
+                Ok(simulate_result) => {
+                    let marginal = simulate_result.amount.saturating_sub(returned);
+                    let is_better = match best {
+                        Some((_, _, best_marginal)) => marginal > best_marginal,
+                        None => true,
+                    };
+                    if is_better {
+                        best = Some((index, simulate_result.amount, marginal));
+                    }
+                }
+                Err(err) => {
+                    simulate_swap_errors = simulate_swap_errors + &err.to_string() + ";";
+                }
             }
         }
+
+        let Some((index, returned, _)) = best else {
+            return Err(StdError::generic_err(format!(
+                "Could not allocate the offer amount to any route. Err: {:?}",
+                simulate_swap_errors
+            )));
+        };
+        allocations[index].0 += step_amount;
+        allocations[index].1 = returned;
     }
-    if route_simulate_result.1.is_zero() {
+
+    let actual_minimum_receive: Uint128 = allocations.iter().map(|(_, returned)| *returned).sum();
+    if actual_minimum_receive.is_zero() {
         return Err(StdError::generic_err(format!(
-            "Minimum receive of simulate smart route is 0. Err: {:?}",
+            "Minimum receive of simulate smart split route is 0. Err: {:?}",
             simulate_swap_errors
         )));
     }
-    Ok(GetSmartRouteResponse {
-        swap_ops: pool_routes[route_simulate_result.0].to_owned(),
-        actual_minimum_receive: route_simulate_result.1,
+
+    let mut routes = vec![];
+    for (route, (allocated, _)) in pool_routes.iter().zip(allocations) {
+        if allocated.is_zero() {
+            continue;
+        }
+        routes.push(SmartSwapRoute {
+            offer_amount: allocated,
+            operations: to_smart_swap_operations(&deps.querier, &router, route)?,
+        });
+    }
+
+    Ok(GetSmartSplitRouteResponse {
+        routes,
+        actual_minimum_receive,
     })
 }
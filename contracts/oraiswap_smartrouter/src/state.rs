@@ -2,16 +2,27 @@ use cosmwasm_schema::cw_serde;
 
 use cosmwasm_std::{Addr, StdResult, Storage};
 use cw_storage_plus::{Item, Map};
+use oraiswap::mixed_router::{MixedRouterController, SwapOperation as MixedSwapOperation};
 use oraiswap::router::{RouterController, SwapOperation};
 
 #[cw_serde]
 pub struct Config {
     pub owner: Addr,
     pub router_contract: RouterController,
+    /// allowed to refresh the discovered routes besides the owner
+    pub keeper: Option<Addr>,
+    /// simulates the routes through v3 pools, they are not discovered when unset
+    pub mixed_router: Option<MixedRouterController>,
 }
 
 pub const CONFIG: Item<Config> = Item::new("config");
 pub const ROUTING_TABLE: Map<(&str, &str), Vec<Vec<SwapOperation>>> = Map::new("routing_table");
+/// routes found on the factory pairs by RefreshRoutes, best simulated return first
+pub const DISCOVERED_ROUTES: Map<(&str, &str), Vec<Vec<SwapOperation>>> =
+    Map::new("discovered_routes");
+/// routes through v3 pools found by RefreshRoutes, best simulated return first
+pub const DISCOVERED_V3_ROUTES: Map<(&str, &str), Vec<Vec<MixedSwapOperation>>> =
+    Map::new("discovered_v3_routes");
 
 pub fn store_route(
     storage: &mut dyn Storage,
@@ -39,6 +50,16 @@ pub fn store_route(
                 offer_asset_info: ask_asset_info,
                 ask_asset_info: offer_asset_info,
             },
+            SwapOperation::OrderbookMarket {
+                orderbook_addr,
+                asset_infos: [offer_asset_info, ask_asset_info],
+                slippage,
+            } => SwapOperation::OrderbookMarket {
+                orderbook_addr,
+                asset_infos: [ask_asset_info, offer_asset_info],
+                slippage,
+            },
+            SwapOperation::Convert { from, to } => SwapOperation::Convert { from: to, to: from },
         });
     }
     if !reversed_ops.is_empty() {
@@ -59,6 +59,8 @@ fn proper_update_state() {
     let msg = ExecuteMsg::UpdateConfig {
         new_owner: Some(good_addr.clone()),
         new_router: None,
+        new_keeper: None,
+        new_mixed_router: None,
     };
     contract::execute(deps.as_mut(), mock_env(), other_info, msg).unwrap_err();
 
@@ -66,6 +68,8 @@ fn proper_update_state() {
     let msg = ExecuteMsg::UpdateConfig {
         new_owner: Some(good_addr.clone()),
         new_router: Some("new_router".to_string()),
+        new_keeper: Some("keeper".to_string()),
+        new_mixed_router: Some("mixed_router".to_string()),
     };
     contract::execute(deps.as_mut(), mock_env(), owner_info, msg).unwrap();
 
@@ -74,6 +78,8 @@ fn proper_update_state() {
             .unwrap();
     assert_eq!(good_addr, res.owner);
     assert_eq!(res.router, "new_router");
+    assert_eq!(res.keeper, Some("keeper".to_string()));
+    assert_eq!(res.mixed_router, Some("mixed_router".to_string()));
 }
 
 #[test]
@@ -1,18 +1,30 @@
-
-use cosmwasm_std::{Addr, Coin, Decimal, Uint128};
+use cosmwasm_std::{Addr, Coin, Decimal, Decimal256, Uint128};
 use oraiswap::asset::{Asset, AssetInfo, ORAI_DENOM};
 use oraiswap::create_entry_points_testing;
+use oraiswap::mixed_router::{
+    InstantiateMsg as MixedRouterInstantiateMsg, SwapOperation as MixedSwapOperation,
+};
 use oraiswap::router::SwapOperation;
 use oraiswap::smartrouter::{
-    ExecuteMsg, GetSmartRouteResponse, InstantiateMsg, QueryMsg, SmartRouteMode,
+    ExecuteMsg, FindRoutesResponse, GetRoutesResponse, GetSmartRouteResponse,
+    GetSmartSplitRouteResponse, GetV3RoutesResponse, InstantiateMsg, QueryMsg, Slippage,
+    SmartRouteMode, SmartSwapOperation, SmartSwapRoute, SwapVenue,
 };
+use oraiswap::universal_swap_memo::memo::SmartSwapExactAssetIn;
+use oraiswap_v3::liquidity::Liquidity;
+use oraiswap_v3::msg::ExecuteMsg as OraiswapV3ExecuteMsg;
+use oraiswap_v3::percentage::Percentage;
+use oraiswap_v3::sqrt_price::{calculate_sqrt_price, SqrtPrice};
+use oraiswap_v3::{FeeTier, PoolKey, MAX_TICK, MIN_TICK};
 
-use oraiswap::testing::{MockApp, ATOM_DENOM};
+use oraiswap::testing::{MockApp, APP_OWNER, ATOM_DENOM};
 
-#[test]
-fn get_smart_router_test() {
-    // fixture
-    let owner = "addr0000";
+const OWNER: &str = "addr0000";
+
+// orai/oraix, oraix/usdc and orai/usdc pools with the smart router on top,
+// returns the app, the smart router and the orai, oraix and usdc infos
+fn setup_smart_router() -> (MockApp, Addr, AssetInfo, AssetInfo, AssetInfo) {
+    let owner = OWNER;
     let oraix_token = "ORAIX";
     let usdc_token = "USDC";
     let orai_info = AssetInfo::NativeToken {
@@ -210,6 +222,14 @@ fn get_smart_router_test() {
         )
         .unwrap();
 
+    (app, smart_router_addr, orai_info, oraix_info, usdc_info)
+}
+
+#[test]
+fn get_smart_router_test() {
+    let owner = OWNER;
+    let (mut app, smart_router_addr, orai_info, oraix_info, usdc_info) = setup_smart_router();
+
     app.execute(
         Addr::unchecked(owner),
         smart_router_addr.clone(),
@@ -293,9 +313,9 @@ fn get_smart_router_test() {
             },
         )
         .unwrap_err();
-    assert!(err.to_string().contains(
-        "Querier contract error: Generic error: Minimum receive of simulate smart route is 0."
-    ));
+    assert!(err
+        .to_string()
+        .contains("Querier contract error: Minimum receive of simulate smart route is 0."));
     assert!(err
         .to_string()
         .contains("Querier contract error: type: oraiswap::asset::PairInfoRaw"));
@@ -315,3 +335,725 @@ fn get_smart_router_test() {
     // we expect the actual min receive of mode NearestMinimumReceive to be nearer to the expected_min_receive than the mode FurthestMinimumReceive
     assert_eq!(max_minimum_receive.swap_ops.len(), 1);
 }
+
+#[test]
+fn find_and_refresh_routes_test() {
+    let owner = OWNER;
+    let keeper = "keeper";
+    let (mut app, smart_router_addr, orai_info, oraix_info, usdc_info) = setup_smart_router();
+
+    // two candidates within 2 hops, the direct pool returns more
+    let res: FindRoutesResponse = app
+        .query(
+            smart_router_addr.clone(),
+            &QueryMsg::FindRoutes {
+                input_info: orai_info.clone(),
+                output_info: usdc_info.clone(),
+                max_hops: 2,
+            },
+        )
+        .unwrap();
+    assert_eq!(res.routes.len(), 2);
+    // no mixed router, no v3 pools
+    assert!(res.v3_routes.is_empty());
+    assert_eq!(
+        res.routes[0].operations,
+        vec![SwapOperation::OraiSwap {
+            offer_asset_info: orai_info.clone(),
+            ask_asset_info: usdc_info.clone(),
+        }]
+    );
+    assert_eq!(
+        res.routes[1].operations,
+        vec![
+            SwapOperation::OraiSwap {
+                offer_asset_info: orai_info.clone(),
+                ask_asset_info: oraix_info.clone(),
+            },
+            SwapOperation::OraiSwap {
+                offer_asset_info: oraix_info.clone(),
+                ask_asset_info: usdc_info.clone(),
+            },
+        ]
+    );
+    assert!(res.routes[0].return_amount > res.routes[1].return_amount);
+
+    let res: FindRoutesResponse = app
+        .query(
+            smart_router_addr.clone(),
+            &QueryMsg::FindRoutes {
+                input_info: orai_info.clone(),
+                output_info: usdc_info.clone(),
+                max_hops: 1,
+            },
+        )
+        .unwrap();
+    assert_eq!(res.routes.len(), 1);
+
+    let res: Result<FindRoutesResponse, _> = app.query(
+        smart_router_addr.clone(),
+        &QueryMsg::FindRoutes {
+            input_info: orai_info.clone(),
+            output_info: usdc_info.clone(),
+            max_hops: 0,
+        },
+    );
+    assert!(res
+        .unwrap_err()
+        .to_string()
+        .contains("max_hops must be between 1 and 3"));
+
+    // no route for usdc -> oraix yet
+    let res: Result<GetSmartRouteResponse, _> = app.query(
+        smart_router_addr.clone(),
+        &QueryMsg::GetSmartRoute {
+            input_info: usdc_info.clone(),
+            output_info: oraix_info.clone(),
+            offer_amount: Uint128::from(100u128),
+            route_mode: None,
+        },
+    );
+    assert!(res.unwrap_err().to_string().contains("No route found"));
+
+    // only the owner or the keeper can refresh
+    let refresh_msg = ExecuteMsg::RefreshRoutes {
+        input_info: usdc_info.clone(),
+        output_info: oraix_info.clone(),
+        max_hops: 2,
+    };
+    app.execute(
+        Addr::unchecked(keeper),
+        smart_router_addr.clone(),
+        &refresh_msg,
+        &[],
+    )
+    .unwrap_err();
+    app.execute(
+        Addr::unchecked(owner),
+        smart_router_addr.clone(),
+        &ExecuteMsg::UpdateConfig {
+            new_owner: None,
+            new_router: None,
+            new_keeper: Some(keeper.to_string()),
+            new_mixed_router: None,
+        },
+        &[],
+    )
+    .unwrap();
+    app.execute(
+        Addr::unchecked(keeper),
+        smart_router_addr.clone(),
+        &refresh_msg,
+        &[],
+    )
+    .unwrap();
+
+    let res: GetRoutesResponse = app
+        .query(
+            smart_router_addr.clone(),
+            &QueryMsg::GetDiscoveredRoutes {
+                input_info: usdc_info.clone(),
+                output_info: oraix_info.clone(),
+            },
+        )
+        .unwrap();
+    assert_eq!(res.pool_routes.len(), 2);
+
+    // the smart route now picks among the discovered routes
+    let res: GetSmartRouteResponse = app
+        .query(
+            smart_router_addr.clone(),
+            &QueryMsg::GetSmartRoute {
+                input_info: usdc_info.clone(),
+                output_info: oraix_info.clone(),
+                offer_amount: Uint128::from(100u128),
+                route_mode: None,
+            },
+        )
+        .unwrap();
+    // 100 usdc buys more oraix through the deeper orai pools than through the direct pool
+    assert_eq!(res.swap_ops.len(), 2);
+}
+
+#[test]
+fn find_routes_through_v3_pools_test() {
+    let owner = OWNER;
+    let (mut app, smart_router_addr, orai_info, _, usdc_info) = setup_smart_router();
+
+    // a usdc/v3x pool on v3 only
+    let v3x_token = "V3X";
+    let v3x_addr = app.create_token(v3x_token);
+    let v3x_info = AssetInfo::Token {
+        contract_addr: v3x_addr.clone(),
+    };
+    let usdc_addr = app.get_token_addr("USDC").unwrap();
+    app.set_token_balances(&[
+        (v3x_token, &[(owner, 1000000000000u128)]),
+        ("USDC", &[(owner, 1000000000000u128)]),
+    ])
+    .unwrap();
+
+    app.create_v3(Box::new(create_entry_points_testing!(oraiswap_v3)));
+    let v3_addr = app.v3_addr.clone();
+    let fee_tier = FeeTier::new(Percentage(100), 10).unwrap();
+    app.execute(
+        Addr::unchecked(APP_OWNER),
+        v3_addr.clone(),
+        &OraiswapV3ExecuteMsg::AddFeeTier { fee_tier },
+        &[],
+    )
+    .unwrap();
+    app.execute(
+        Addr::unchecked(APP_OWNER),
+        v3_addr.clone(),
+        &OraiswapV3ExecuteMsg::CreatePool {
+            token_0: usdc_addr.to_string(),
+            token_1: v3x_addr.to_string(),
+            fee_tier,
+            init_sqrt_price: calculate_sqrt_price(0).unwrap(),
+            init_tick: 0,
+        },
+        &[],
+    )
+    .unwrap();
+    let pool_key = PoolKey::new(usdc_addr.to_string(), v3x_addr.to_string(), fee_tier).unwrap();
+    for token in ["USDC", v3x_token] {
+        app.approve_token(token, owner, v3_addr.as_str(), u128::MAX)
+            .unwrap();
+    }
+    app.execute(
+        Addr::unchecked(owner),
+        v3_addr.clone(),
+        &OraiswapV3ExecuteMsg::CreatePosition {
+            pool_key: pool_key.clone(),
+            lower_tick: -20,
+            upper_tick: 10,
+            liquidity_delta: Liquidity(1000000000000000000),
+            slippage_limit_lower: SqrtPrice::from_tick(MIN_TICK).unwrap(),
+            slippage_limit_upper: SqrtPrice::from_tick(MAX_TICK).unwrap(),
+        },
+        &[],
+    )
+    .unwrap();
+
+    let find_msg = QueryMsg::FindRoutes {
+        input_info: orai_info.clone(),
+        output_info: v3x_info.clone(),
+        max_hops: 2,
+    };
+    // the v3 pools are not listed without a mixed router
+    let res: FindRoutesResponse = app.query(smart_router_addr.clone(), &find_msg).unwrap();
+    assert!(res.routes.is_empty());
+    assert!(res.v3_routes.is_empty());
+
+    let code_id = app.upload(Box::new(create_entry_points_testing!(
+        oraiswap_mixed_router
+    )));
+    let mixed_router_addr = app
+        .instantiate(
+            code_id,
+            Addr::unchecked(owner),
+            &MixedRouterInstantiateMsg {
+                factory_addr: app.factory_addr.clone(),
+                factory_addr_v2: app.factory_addr.clone(),
+                oraiswap_v3: v3_addr.clone(),
+                converter: None,
+            },
+            &[],
+            "mixed-router",
+        )
+        .unwrap();
+    app.execute(
+        Addr::unchecked(owner),
+        smart_router_addr.clone(),
+        &ExecuteMsg::UpdateConfig {
+            new_owner: None,
+            new_router: None,
+            new_keeper: None,
+            new_mixed_router: Some(mixed_router_addr.to_string()),
+        },
+        &[],
+    )
+    .unwrap();
+
+    // orai -> usdc on the pair, then usdc -> v3x on the v3 pool
+    let route = vec![
+        MixedSwapOperation::OraiSwap {
+            offer_asset_info: orai_info.clone(),
+            ask_asset_info: usdc_info.clone(),
+        },
+        MixedSwapOperation::SwapV3 {
+            x_to_y: pool_key.token_x == usdc_addr.as_str(),
+            pool_key,
+            sqrt_price_limit: None,
+            by_amount_in: true,
+        },
+    ];
+    let res: FindRoutesResponse = app.query(smart_router_addr.clone(), &find_msg).unwrap();
+    assert!(res.routes.is_empty());
+    assert_eq!(res.v3_routes.len(), 1);
+    assert_eq!(res.v3_routes[0].operations, route);
+    assert!(!res.v3_routes[0].return_amount.is_zero());
+
+    app.execute(
+        Addr::unchecked(owner),
+        smart_router_addr.clone(),
+        &ExecuteMsg::RefreshRoutes {
+            input_info: orai_info.clone(),
+            output_info: v3x_info.clone(),
+            max_hops: 2,
+        },
+        &[],
+    )
+    .unwrap();
+    let res: GetV3RoutesResponse = app
+        .query(
+            smart_router_addr.clone(),
+            &QueryMsg::GetDiscoveredV3Routes {
+                input_info: orai_info,
+                output_info: v3x_info,
+            },
+        )
+        .unwrap();
+    assert_eq!(res.pool_routes, vec![route]);
+}
+
+#[test]
+fn get_smart_split_route_test() {
+    let (mut app, smart_router_addr, orai_info, oraix_info, usdc_info) = setup_smart_router();
+    let owner = OWNER;
+    // orai -> usdc directly and through oraix
+    for pool_route in [
+        vec![SwapOperation::OraiSwap {
+            offer_asset_info: orai_info.clone(),
+            ask_asset_info: usdc_info.clone(),
+        }],
+        vec![
+            SwapOperation::OraiSwap {
+                offer_asset_info: orai_info.clone(),
+                ask_asset_info: oraix_info.clone(),
+            },
+            SwapOperation::OraiSwap {
+                offer_asset_info: oraix_info.clone(),
+                ask_asset_info: usdc_info.clone(),
+            },
+        ],
+    ] {
+        app.execute(
+            Addr::unchecked(owner),
+            smart_router_addr.clone(),
+            &ExecuteMsg::SetRoute {
+                input_info: orai_info.clone(),
+                output_info: usdc_info.clone(),
+                pool_route,
+            },
+            &[],
+        )
+        .unwrap();
+    }
+    let offer_amount = Uint128::from(5000u128);
+
+    let single: GetSmartRouteResponse = app
+        .query(
+            smart_router_addr.clone(),
+            &QueryMsg::GetSmartRoute {
+                input_info: orai_info.clone(),
+                output_info: usdc_info.clone(),
+                offer_amount,
+                route_mode: None,
+            },
+        )
+        .unwrap();
+
+    // a single split is the best single route
+    let res: GetSmartSplitRouteResponse = app
+        .query(
+            smart_router_addr.clone(),
+            &QueryMsg::GetSmartSplitRoute {
+                input_info: orai_info.clone(),
+                output_info: usdc_info.clone(),
+                offer_amount,
+                max_splits: 1,
+            },
+        )
+        .unwrap();
+    let orai_usdc_pair = app
+        .query_pair([orai_info.clone(), usdc_info.clone()])
+        .unwrap();
+    assert_eq!(
+        res.routes,
+        vec![SmartSwapRoute {
+            offer_amount,
+            operations: vec![SmartSwapOperation {
+                pool_id: orai_usdc_pair.contract_addr.to_string(),
+                denom_in: orai_info.to_string(),
+                denom_out: usdc_info.to_string(),
+            }],
+        }]
+    );
+    assert_eq!(res.actual_minimum_receive, single.actual_minimum_receive);
+
+    // splitting never returns less and allocates the whole offer
+    let res: GetSmartSplitRouteResponse = app
+        .query(
+            smart_router_addr.clone(),
+            &QueryMsg::GetSmartSplitRoute {
+                input_info: orai_info.clone(),
+                output_info: usdc_info.clone(),
+                offer_amount,
+                max_splits: 2,
+            },
+        )
+        .unwrap();
+    assert!(res.routes.len() <= 2);
+    assert!(res.actual_minimum_receive >= single.actual_minimum_receive);
+    assert_eq!(
+        res.routes
+            .iter()
+            .map(|route| route.offer_amount)
+            .sum::<Uint128>(),
+        offer_amount
+    );
+
+    // plugs into the memo
+    let memo_swap: SmartSwapExactAssetIn = res.clone().into();
+    assert_eq!(memo_swap.routes.len(), res.routes.len());
+    assert_eq!(
+        memo_swap.routes[0].offer_amount,
+        res.routes[0].offer_amount.to_string()
+    );
+
+    // a route through the pools of another one is not split into, its simulation would count
+    // the liquidity of the shared pools twice
+    app.execute(
+        Addr::unchecked(owner),
+        smart_router_addr.clone(),
+        &ExecuteMsg::SetRoute {
+            input_info: orai_info.clone(),
+            output_info: usdc_info.clone(),
+            pool_route: vec![
+                SwapOperation::OraiSwap {
+                    offer_asset_info: orai_info.clone(),
+                    ask_asset_info: usdc_info.clone(),
+                },
+                SwapOperation::OraiSwap {
+                    offer_asset_info: usdc_info.clone(),
+                    ask_asset_info: oraix_info.clone(),
+                },
+                SwapOperation::OraiSwap {
+                    offer_asset_info: oraix_info.clone(),
+                    ask_asset_info: usdc_info.clone(),
+                },
+            ],
+        },
+        &[],
+    )
+    .unwrap();
+    let res: GetSmartSplitRouteResponse = app
+        .query(
+            smart_router_addr.clone(),
+            &QueryMsg::GetSmartSplitRoute {
+                input_info: orai_info.clone(),
+                output_info: usdc_info.clone(),
+                offer_amount,
+                max_splits: 3,
+            },
+        )
+        .unwrap();
+    for (index, route) in res.routes.iter().enumerate() {
+        for other in &res.routes[index + 1..] {
+            assert!(route.operations.iter().all(|op| other
+                .operations
+                .iter()
+                .all(|other_op| other_op.pool_id != op.pool_id)));
+        }
+    }
+
+    let res: Result<GetSmartSplitRouteResponse, _> = app.query(
+        smart_router_addr.clone(),
+        &QueryMsg::GetSmartSplitRoute {
+            input_info: orai_info.clone(),
+            output_info: usdc_info.clone(),
+            offer_amount,
+            max_splits: 0,
+        },
+    );
+    assert!(res
+        .unwrap_err()
+        .to_string()
+        .contains("max_splits must be greater than 0"));
+}
+
+#[test]
+fn swap_test() {
+    let (mut app, smart_router_addr, orai_info, oraix_info, usdc_info) = setup_smart_router();
+    let owner = OWNER;
+    app.execute(
+        Addr::unchecked(owner),
+        smart_router_addr.clone(),
+        &ExecuteMsg::SetRoute {
+            input_info: orai_info.clone(),
+            output_info: usdc_info.clone(),
+            pool_route: vec![SwapOperation::OraiSwap {
+                offer_asset_info: orai_info.clone(),
+                ask_asset_info: usdc_info.clone(),
+            }],
+        },
+        &[],
+    )
+    .unwrap();
+    // the orai/usdc pool holds 1000 orai and 5000 usdc
+    app.execute(
+        Addr::unchecked(APP_OWNER),
+        app.oracle_addr.clone(),
+        &oraiswap::oracle::ExecuteMsg::UpdateExchangeRate {
+            asset_info: usdc_info.clone(),
+            exchange_rate: Decimal::from_ratio(5u128, 1u128),
+        },
+        &[],
+    )
+    .unwrap();
+    let input_coin = Coin {
+        denom: ORAI_DENOM.to_string(),
+        amount: Uint128::from(10u128),
+    };
+
+    // funds must match the input coin
+    app.execute(
+        Addr::unchecked(owner),
+        smart_router_addr.clone(),
+        &ExecuteMsg::Swap {
+            input_coin: input_coin.clone(),
+            output_denom: usdc_info.to_string(),
+            slippage: Slippage::MinOutputAmount(Uint128::zero()),
+            route: None,
+        },
+        &[],
+    )
+    .unwrap_err();
+    let err = app
+        .execute(
+            Addr::unchecked(owner),
+            smart_router_addr.clone(),
+            &ExecuteMsg::Swap {
+                input_coin: input_coin.clone(),
+                output_denom: usdc_info.to_string(),
+                slippage: Slippage::MinOutputAmount(Uint128::zero()),
+                route: None,
+            },
+            &[Coin {
+                denom: ORAI_DENOM.to_string(),
+                amount: input_coin.amount + Uint128::one(),
+            }],
+        )
+        .unwrap_err();
+    assert!(err.root_cause().to_string().contains("Insufficient Funds"));
+
+    // the given route must end with the output
+    let err = app
+        .execute(
+            Addr::unchecked(owner),
+            smart_router_addr.clone(),
+            &ExecuteMsg::Swap {
+                input_coin: input_coin.clone(),
+                output_denom: usdc_info.to_string(),
+                slippage: Slippage::MinOutputAmount(Uint128::zero()),
+                route: Some(vec![SwapOperation::OraiSwap {
+                    offer_asset_info: orai_info.clone(),
+                    ask_asset_info: oraix_info.clone(),
+                }]),
+            },
+            &[input_coin.clone()],
+        )
+        .unwrap_err();
+    assert!(err.root_cause().to_string().contains("Invalid Pool Route"));
+
+    // oraix has no oracle rate to derive the minimum receive from
+    let err = app
+        .execute(
+            Addr::unchecked(owner),
+            smart_router_addr.clone(),
+            &ExecuteMsg::Swap {
+                input_coin: input_coin.clone(),
+                output_denom: oraix_info.to_string(),
+                slippage: Slippage::Twap {
+                    window_seconds: None,
+                    slippage_percentage: Decimal::percent(5),
+                },
+                route: Some(vec![SwapOperation::OraiSwap {
+                    offer_asset_info: orai_info.clone(),
+                    ask_asset_info: oraix_info.clone(),
+                }]),
+            },
+            &[input_coin.clone()],
+        )
+        .unwrap_err();
+    assert!(err
+        .root_cause()
+        .to_string()
+        .contains(&format!("No oracle rate for {}", oraix_info)));
+
+    // 10 orai at the twap of 5 is 50 usdc, the pool returns less
+    app.execute(
+        Addr::unchecked(owner),
+        smart_router_addr.clone(),
+        &ExecuteMsg::Swap {
+            input_coin: input_coin.clone(),
+            output_denom: usdc_info.to_string(),
+            slippage: Slippage::Twap {
+                window_seconds: None,
+                slippage_percentage: Decimal::zero(),
+            },
+            route: None,
+        },
+        &[input_coin.clone()],
+    )
+    .unwrap_err();
+
+    let balance_before = app
+        .query_token_balances(owner)
+        .unwrap()
+        .into_iter()
+        .find(|coin| coin.denom.eq("USDC"))
+        .unwrap()
+        .amount;
+    app.execute(
+        Addr::unchecked(owner),
+        smart_router_addr.clone(),
+        &ExecuteMsg::Swap {
+            input_coin: input_coin.clone(),
+            output_denom: usdc_info.to_string(),
+            slippage: Slippage::Twap {
+                window_seconds: Some(600),
+                slippage_percentage: Decimal::percent(5),
+            },
+            route: None,
+        },
+        &[input_coin.clone()],
+    )
+    .unwrap();
+    let balance_after = app
+        .query_token_balances(owner)
+        .unwrap()
+        .into_iter()
+        .find(|coin| coin.denom.eq("USDC"))
+        .unwrap()
+        .amount;
+    // at least 50 usdc less 5%
+    assert!(balance_after - balance_before >= Uint128::from(47u128));
+}
+
+#[test]
+fn smart_route_modes_test() {
+    let (mut app, smart_router_addr, orai_info, oraix_info, usdc_info) = setup_smart_router();
+    let owner = OWNER;
+    let random_info = AssetInfo::NativeToken {
+        denom: "random-denom".to_string(),
+    };
+    // usdc -> oraix directly, through orai and through a missing pair
+    for pool_route in [
+        vec![SwapOperation::OraiSwap {
+            offer_asset_info: usdc_info.clone(),
+            ask_asset_info: oraix_info.clone(),
+        }],
+        vec![
+            SwapOperation::OraiSwap {
+                offer_asset_info: usdc_info.clone(),
+                ask_asset_info: orai_info.clone(),
+            },
+            SwapOperation::OraiSwap {
+                offer_asset_info: orai_info.clone(),
+                ask_asset_info: oraix_info.clone(),
+            },
+        ],
+        vec![
+            SwapOperation::OraiSwap {
+                offer_asset_info: usdc_info.clone(),
+                ask_asset_info: random_info.clone(),
+            },
+            SwapOperation::OraiSwap {
+                offer_asset_info: random_info.clone(),
+                ask_asset_info: oraix_info.clone(),
+            },
+        ],
+    ] {
+        app.execute(
+            Addr::unchecked(owner),
+            smart_router_addr.clone(),
+            &ExecuteMsg::SetRoute {
+                input_info: usdc_info.clone(),
+                output_info: oraix_info.clone(),
+                pool_route,
+            },
+            &[],
+        )
+        .unwrap();
+    }
+    let query_smart_route = |route_mode: SmartRouteMode| -> GetSmartRouteResponse {
+        app.query(
+            smart_router_addr.clone(),
+            &QueryMsg::GetSmartRoute {
+                input_info: usdc_info.clone(),
+                output_info: oraix_info.clone(),
+                offer_amount: Uint128::from(100u128),
+                route_mode: Some(route_mode),
+            },
+        )
+        .unwrap()
+    };
+
+    // the deeper pools through orai return more
+    let res = query_smart_route(SmartRouteMode::MaxMinimumReceive);
+    assert_eq!(res.swap_ops.len(), 2);
+    assert_eq!(
+        res.score,
+        Some(Decimal256::from_ratio(res.actual_minimum_receive, 1u128))
+    );
+    let candidates = res.candidates.unwrap();
+    assert_eq!(
+        candidates
+            .iter()
+            .map(|candidate| candidate.route_index)
+            .collect::<Vec<_>>(),
+        vec![0, 1]
+    );
+    assert!(candidates[0].return_amount < candidates[1].return_amount);
+    let errors = res.errors.unwrap();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].route_index, 2);
+    assert_eq!(errors[0].swap_ops.len(), 2);
+
+    let res = query_smart_route(SmartRouteMode::MinHops);
+    assert_eq!(res.swap_ops.len(), 1);
+    assert_eq!(res.score, Some(Decimal256::one()));
+
+    // the direct pool moves its price the most
+    let res = query_smart_route(SmartRouteMode::MinPriceImpact);
+    assert_eq!(res.swap_ops.len(), 2);
+    let candidates = res.candidates.unwrap();
+    assert!(candidates[0].price_impact > candidates[1].price_impact);
+    assert_eq!(
+        res.score,
+        Some(Decimal256::from(candidates[1].price_impact.unwrap()))
+    );
+
+    // a steep hop penalty favors the direct pool
+    let res = query_smart_route(SmartRouteMode::MaxOutputWithHopPenalty {
+        penalty_per_hop: Decimal::percent(60),
+    });
+    assert_eq!(res.swap_ops.len(), 1);
+    assert_eq!(
+        res.score,
+        Some(Decimal256::from_ratio(res.actual_minimum_receive, 1u128) * Decimal256::percent(40))
+    );
+    let res = query_smart_route(SmartRouteMode::MaxOutputWithHopPenalty {
+        penalty_per_hop: Decimal::percent(1),
+    });
+    assert_eq!(res.swap_ops.len(), 2);
+
+    // no route only uses the orderbook, the best of all is used
+    let res = query_smart_route(SmartRouteMode::PreferVenue(vec![SwapVenue::Orderbook]));
+    assert_eq!(res.swap_ops.len(), 2);
+    let res = query_smart_route(SmartRouteMode::PreferVenue(vec![SwapVenue::OraiSwap]));
+    assert_eq!(res.swap_ops.len(), 2);
+}
@@ -1,24 +1,72 @@
 use cosmwasm_std::entry_point;
-use cosmwasm_std::{Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult};
+use cosmwasm_std::{
+    attr, from_json, to_json_binary, to_json_vec, Addr, Api, Binary, CanonicalAddr, Deps, DepsMut,
+    Env, MessageInfo, Response, StdResult, Uint128,
+};
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256};
 
-use cw20::Cw20ExecuteMsg;
-use cw20_base::ContractError;
+use cw20::{AllowanceResponse, BalanceResponse, Cw20ExecuteMsg};
+use cw20_base::state::{ALLOWANCES, ALLOWANCES_SPENDER, BALANCES, TOKEN_INFO};
 use cw20_base::{
     contract::{
         execute as cw20_execute, instantiate as cw20_instantiate, migrate as cw20_migrate,
         query as cw20_query,
     },
-    msg::{InstantiateMsg, MigrateMsg, QueryMsg},
+    msg::{InstantiateMsg as Cw20InstantiateMsg, MigrateMsg as Cw20MigrateMsg},
+};
+use oraiswap::token::{
+    ComplianceConfigResponse, ExecuteMsg, FrozenResponse, InstantiateMsg, MigrateMsg,
+    PermitNonceResponse, PermitPayload, QueryMsg, TotalSupplyResponse,
+};
+
+use crate::error::ContractError;
+use crate::state::{
+    ComplianceConfig, BALANCE_SNAPSHOTS, COMPLIANCE_CONFIG, FROZEN, PERMIT_NONCES,
+    TOTAL_SUPPLY_SNAPSHOT,
 };
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
-    cw20_instantiate(deps, env, info, msg)
+    let compliance_config = ComplianceConfig {
+        admin: msg
+            .admin
+            .map(|admin| deps.api.addr_validate(&admin))
+            .transpose()?,
+        burner: msg
+            .burner
+            .map(|burner| deps.api.addr_validate(&burner))
+            .transpose()?,
+        paused: false,
+    };
+    COMPLIANCE_CONFIG.save(deps.storage, &compliance_config)?;
+
+    let accounts = msg
+        .initial_balances
+        .iter()
+        .map(|coin| coin.address.clone())
+        .collect();
+    let res = cw20_instantiate(
+        deps.branch(),
+        env.clone(),
+        info,
+        Cw20InstantiateMsg {
+            name: msg.name,
+            symbol: msg.symbol,
+            decimals: msg.decimals,
+            initial_balances: msg.initial_balances,
+            mint: msg.mint,
+            marketing: msg.marketing,
+        },
+    )?;
+    update_snapshots(deps, &env, accounts, true)?;
+
+    Ok(res)
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -26,19 +74,423 @@ pub fn execute(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    match msg {
+        ExecuteMsg::Permit {
+            owner_pubkey,
+            spender,
+            amount,
+            expires,
+            nonce,
+            signature,
+        } => {
+            let payload = PermitPayload {
+                chain_id: env.block.chain_id.clone(),
+                contract: env.contract.address.to_string(),
+                spender,
+                amount,
+                expires,
+                nonce,
+            };
+            execute_permit(deps, env, owner_pubkey, payload, signature)
+        }
+        ExecuteMsg::Freeze { address } => execute_freeze(deps, info, address, true),
+        ExecuteMsg::Unfreeze { address } => execute_freeze(deps, info, address, false),
+        ExecuteMsg::Pause {} => execute_pause(deps, info, true),
+        ExecuteMsg::Unpause {} => execute_pause(deps, info, false),
+        ExecuteMsg::UpdateComplianceRoles { admin, burner } => {
+            execute_update_compliance_roles(deps, info, admin, burner)
+        }
+        ExecuteMsg::BurnFrozen { address, amount } => {
+            execute_burn_frozen(deps, env, info, address, amount)
+        }
+        // the remaining messages share the cw20-base encoding
+        _ => execute_cw20(deps, env, info, from_json(to_json_binary(&msg)?)?),
+    }
+}
+
+pub fn execute_cw20(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
     msg: Cw20ExecuteMsg,
 ) -> Result<Response, ContractError> {
-    cw20_execute(deps, env, info, msg)
+    // accounts whose balance may change, and whether the total supply may change
+    let (accounts, supply_changed) = match &msg {
+        Cw20ExecuteMsg::Transfer { recipient, .. } => {
+            (vec![info.sender.to_string(), recipient.clone()], false)
+        }
+        Cw20ExecuteMsg::Send { contract, .. } => {
+            (vec![info.sender.to_string(), contract.clone()], false)
+        }
+        Cw20ExecuteMsg::Burn { .. } => (vec![info.sender.to_string()], true),
+        Cw20ExecuteMsg::Mint { recipient, .. } => (vec![recipient.clone()], true),
+        Cw20ExecuteMsg::TransferFrom {
+            owner, recipient, ..
+        } => (vec![owner.clone(), recipient.clone()], false),
+        Cw20ExecuteMsg::SendFrom {
+            owner, contract, ..
+        } => (vec![owner.clone(), contract.clone()], false),
+        Cw20ExecuteMsg::BurnFrom { owner, .. } => (vec![owner.clone()], true),
+        _ => (vec![], false),
+    };
+
+    if !accounts.is_empty() {
+        assert_not_restricted(deps.as_ref(), &info.sender, &accounts)?;
+    }
+
+    seed_snapshots(deps.branch(), &accounts)?;
+    let res = cw20_execute(deps.branch(), env.clone(), info, msg)?;
+    update_snapshots(deps, &env, accounts, supply_changed)?;
+
+    Ok(res)
+}
+
+/// balances cannot change while paused, nor involve a frozen sender or account
+fn assert_not_restricted(
+    deps: Deps,
+    sender: &Addr,
+    accounts: &[String],
+) -> Result<(), ContractError> {
+    let compliance_config = COMPLIANCE_CONFIG
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    if compliance_config.paused {
+        return Err(ContractError::Paused {});
+    }
+
+    if FROZEN.has(deps.storage, sender) {
+        return Err(ContractError::Frozen {
+            address: sender.to_string(),
+        });
+    }
+    for account in accounts {
+        let address = deps.api.addr_validate(account)?;
+        if FROZEN.has(deps.storage, &address) {
+            return Err(ContractError::Frozen {
+                address: address.to_string(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+pub fn execute_freeze(
+    deps: DepsMut,
+    info: MessageInfo,
+    address: String,
+    frozen: bool,
+) -> Result<Response, ContractError> {
+    let compliance_config = COMPLIANCE_CONFIG
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+
+    // check authorized
+    if compliance_config.admin.ne(&Some(info.sender)) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let address = deps.api.addr_validate(&address)?;
+    if frozen {
+        FROZEN.save(deps.storage, &address, &true)?;
+    } else {
+        FROZEN.remove(deps.storage, &address);
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", if frozen { "freeze" } else { "unfreeze" }),
+        attr("address", address),
+    ]))
+}
+
+pub fn execute_pause(
+    deps: DepsMut,
+    info: MessageInfo,
+    paused: bool,
+) -> Result<Response, ContractError> {
+    let mut compliance_config = COMPLIANCE_CONFIG
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+
+    // check authorized
+    if compliance_config.admin.ne(&Some(info.sender)) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    compliance_config.paused = paused;
+    COMPLIANCE_CONFIG.save(deps.storage, &compliance_config)?;
+
+    Ok(Response::new().add_attribute("action", if paused { "pause" } else { "unpause" }))
+}
+
+pub fn execute_update_compliance_roles(
+    deps: DepsMut,
+    info: MessageInfo,
+    admin: Option<String>,
+    burner: Option<String>,
+) -> Result<Response, ContractError> {
+    let mut compliance_config = COMPLIANCE_CONFIG
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+
+    // check authorized
+    if compliance_config.admin.ne(&Some(info.sender)) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if let Some(admin) = admin {
+        compliance_config.admin = Some(deps.api.addr_validate(&admin)?);
+    }
+    if let Some(burner) = burner {
+        compliance_config.burner = Some(deps.api.addr_validate(&burner)?);
+    }
+    COMPLIANCE_CONFIG.save(deps.storage, &compliance_config)?;
+
+    Ok(Response::new().add_attribute("action", "update_compliance_roles"))
+}
+
+pub fn execute_burn_frozen(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    address: String,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let compliance_config = COMPLIANCE_CONFIG
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+
+    // check authorized
+    if compliance_config.burner.ne(&Some(info.sender)) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let address = deps.api.addr_validate(&address)?;
+    if !FROZEN.has(deps.storage, &address) {
+        return Err(ContractError::NotFrozen {
+            address: address.to_string(),
+        });
+    }
+
+    let accounts = vec![address.to_string()];
+    seed_snapshots(deps.branch(), &accounts)?;
+    BALANCES.update(deps.storage, &address, |balance| -> StdResult<_> {
+        Ok(balance.unwrap_or_default().checked_sub(amount)?)
+    })?;
+    TOKEN_INFO.update(deps.storage, |mut token_info| -> StdResult<_> {
+        token_info.total_supply = token_info.total_supply.checked_sub(amount)?;
+        Ok(token_info)
+    })?;
+    update_snapshots(deps, &env, accounts, true)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "burn_frozen"),
+        attr("address", address),
+        attr("amount", amount),
+    ]))
+}
+
+/// set the allowance signed by the owner of owner_pubkey, replacing the current one
+pub fn execute_permit(
+    deps: DepsMut,
+    env: Env,
+    owner_pubkey: Binary,
+    payload: PermitPayload,
+    signature: Binary,
+) -> Result<Response, ContractError> {
+    let message_hash = Sha256::digest(to_json_vec(&payload)?);
+    if !deps
+        .api
+        .secp256k1_verify(&message_hash, &signature, &owner_pubkey)
+        .unwrap_or(false)
+    {
+        return Err(ContractError::InvalidSignature {});
+    }
+
+    if payload.expires.is_expired(&env.block) {
+        return Err(ContractError::PermitExpired {});
+    }
+
+    let owner = pubkey_to_address(deps.api, &owner_pubkey)?;
+    let expected = PERMIT_NONCES
+        .may_load(deps.storage, &owner)?
+        .unwrap_or_default();
+    if payload.nonce != expected {
+        return Err(ContractError::InvalidNonce { expected });
+    }
+    PERMIT_NONCES.save(deps.storage, &owner, &(expected + 1))?;
+
+    let spender = deps.api.addr_validate(&payload.spender)?;
+    if spender == owner {
+        return Err(cw20_base::ContractError::CannotSetOwnAccount {}.into());
+    }
+
+    if payload.amount.is_zero() {
+        ALLOWANCES.remove(deps.storage, (&owner, &spender));
+        ALLOWANCES_SPENDER.remove(deps.storage, (&spender, &owner));
+    } else {
+        let allowance = AllowanceResponse {
+            allowance: payload.amount,
+            expires: payload.expires,
+        };
+        ALLOWANCES.save(deps.storage, (&owner, &spender), &allowance)?;
+        ALLOWANCES_SPENDER.save(deps.storage, (&spender, &owner), &allowance)?;
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "permit"),
+        attr("owner", owner),
+        attr("spender", spender),
+        attr("amount", payload.amount),
+        attr("nonce", payload.nonce.to_string()),
+    ]))
+}
+
+/// cosmos address of a compressed secp256k1 public key
+pub fn pubkey_to_address(api: &dyn Api, pubkey: &[u8]) -> StdResult<Addr> {
+    let hash = Ripemd160::digest(Sha256::digest(pubkey));
+    api.addr_humanize(&CanonicalAddr::from(hash.as_slice()))
+}
+
+/// accounts holding a balance from before the snapshots were enabled get it checkpointed
+/// at height 0, so earlier heights do not read as zero once their balance changes
+fn seed_snapshots(deps: DepsMut, accounts: &[String]) -> StdResult<()> {
+    for account in accounts {
+        let address = deps.api.addr_validate(account)?;
+        if BALANCE_SNAPSHOTS
+            .may_load(deps.storage, &address)?
+            .is_none()
+        {
+            let balance = BALANCES
+                .may_load(deps.storage, &address)?
+                .unwrap_or_default();
+            BALANCE_SNAPSHOTS.save(deps.storage, &address, &balance, 0)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// checkpoint the current balances of accounts and the total supply at the current height
+fn update_snapshots(
+    deps: DepsMut,
+    env: &Env,
+    accounts: Vec<String>,
+    supply_changed: bool,
+) -> StdResult<()> {
+    let height = env.block.height;
+    for account in accounts {
+        let address = deps.api.addr_validate(&account)?;
+        let balance = BALANCES
+            .may_load(deps.storage, &address)?
+            .unwrap_or_default();
+        BALANCE_SNAPSHOTS.save(deps.storage, &address, &balance, height)?;
+    }
+
+    if supply_changed {
+        let total_supply = TOKEN_INFO.load(deps.storage)?.total_supply;
+        TOTAL_SUPPLY_SNAPSHOT.save(deps.storage, &total_supply, height)?;
+    }
+
+    Ok(())
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    cw20_query(deps, env, msg)
+    match msg {
+        QueryMsg::BalanceAt { address, height } => {
+            to_json_binary(&query_balance_at(deps, address, height)?)
+        }
+        QueryMsg::TotalSupplyAt { height } => to_json_binary(&query_total_supply_at(deps, height)?),
+        QueryMsg::PermitNonce { owner } => to_json_binary(&query_permit_nonce(deps, owner)?),
+        QueryMsg::Frozen { address } => to_json_binary(&query_frozen(deps, address)?),
+        QueryMsg::ComplianceConfig {} => to_json_binary(&query_compliance_config(deps)?),
+        // the remaining queries share the cw20-base encoding
+        _ => cw20_query(deps, env, from_json(to_json_binary(&msg)?)?),
+    }
+}
+
+pub fn query_balance_at(deps: Deps, address: String, height: u64) -> StdResult<BalanceResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let balance = match BALANCE_SNAPSHOTS.may_load(deps.storage, &address)? {
+        Some(_) => BALANCE_SNAPSHOTS
+            .may_load_at_height(deps.storage, &address, height)?
+            .unwrap_or_default(),
+        // never checkpointed, the balance has not changed since snapshots were enabled
+        None => BALANCES
+            .may_load(deps.storage, &address)?
+            .unwrap_or_default(),
+    };
+
+    Ok(BalanceResponse { balance })
+}
+
+pub fn query_total_supply_at(deps: Deps, height: u64) -> StdResult<TotalSupplyResponse> {
+    let total_supply = TOTAL_SUPPLY_SNAPSHOT
+        .may_load_at_height(deps.storage, height)?
+        .unwrap_or_default();
+
+    Ok(TotalSupplyResponse { total_supply })
+}
+
+pub fn query_permit_nonce(deps: Deps, owner: String) -> StdResult<PermitNonceResponse> {
+    let owner = deps.api.addr_validate(&owner)?;
+    let nonce = PERMIT_NONCES
+        .may_load(deps.storage, &owner)?
+        .unwrap_or_default();
+
+    Ok(PermitNonceResponse { nonce })
+}
+
+pub fn query_frozen(deps: Deps, address: String) -> StdResult<FrozenResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let compliance_config = COMPLIANCE_CONFIG
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+
+    Ok(FrozenResponse {
+        frozen: FROZEN.has(deps.storage, &address),
+        paused: compliance_config.paused,
+    })
+}
+
+pub fn query_compliance_config(deps: Deps) -> StdResult<ComplianceConfigResponse> {
+    let compliance_config = COMPLIANCE_CONFIG
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+
+    Ok(ComplianceConfigResponse {
+        admin: compliance_config.admin,
+        burner: compliance_config.burner,
+        paused: compliance_config.paused,
+    })
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(deps: DepsMut, env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
-    cw20_migrate(deps, env, msg)
+pub fn migrate(mut deps: DepsMut, env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+    let res = cw20_migrate(deps.branch(), env, Cw20MigrateMsg {})?;
+
+    // the migrator is the contract admin, so it may set the compliance roles
+    let mut compliance_config = COMPLIANCE_CONFIG
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    if let Some(admin) = msg.admin {
+        compliance_config.admin = Some(deps.api.addr_validate(&admin)?);
+    }
+    if let Some(burner) = msg.burner {
+        compliance_config.burner = Some(deps.api.addr_validate(&burner)?);
+    }
+    COMPLIANCE_CONFIG.save(deps.storage, &compliance_config)?;
+
+    // earlier heights read the total supply at migration
+    if TOTAL_SUPPLY_SNAPSHOT.may_load(deps.storage)?.is_none() {
+        let total_supply = TOKEN_INFO.load(deps.storage)?.total_supply;
+        TOTAL_SUPPLY_SNAPSHOT.save(deps.storage, &total_supply, 0)?;
+    }
+
+    Ok(res)
 }
 
 #[test]
@@ -48,3 +500,432 @@ pub fn test() {
     let code_id = app.upload(contract);
     println!("contract code id {}", code_id);
 }
+
+#[test]
+fn balance_and_total_supply_snapshots() {
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+    use cw20::Cw20Coin;
+
+    let mut deps = mock_dependencies();
+    let mut env = mock_env();
+    let start = env.block.height;
+
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("creator", &[]),
+        InstantiateMsg {
+            name: "oraiswap token".to_string(),
+            symbol: "ORAIX".to_string(),
+            decimals: 6,
+            initial_balances: vec![Cw20Coin {
+                address: "addr0000".to_string(),
+                amount: Uint128::from(1000u128),
+            }],
+            mint: None,
+            marketing: None,
+            admin: None,
+            burner: None,
+        },
+    )
+    .unwrap();
+
+    env.block.height += 1;
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &[]),
+        ExecuteMsg::Transfer {
+            recipient: "addr0001".to_string(),
+            amount: Uint128::from(100u128),
+        },
+    )
+    .unwrap();
+
+    env.block.height += 1;
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &[]),
+        ExecuteMsg::Burn {
+            amount: Uint128::from(50u128),
+        },
+    )
+    .unwrap();
+
+    let balance_at = |address: &str, height: u64| -> u128 {
+        let res: BalanceResponse = from_json(
+            query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::BalanceAt {
+                    address: address.to_string(),
+                    height,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        res.balance.u128()
+    };
+    // balances at the beginning of each block
+    assert_eq!(balance_at("addr0000", start + 1), 1000);
+    assert_eq!(balance_at("addr0000", start + 2), 900);
+    assert_eq!(balance_at("addr0000", start + 3), 850);
+    assert_eq!(balance_at("addr0001", start + 1), 0);
+    assert_eq!(balance_at("addr0001", start + 2), 100);
+
+    let total_supply_at = |height: u64| -> u128 {
+        let res: TotalSupplyResponse = from_json(
+            query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::TotalSupplyAt { height },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        res.total_supply.u128()
+    };
+    assert_eq!(total_supply_at(start), 0);
+    assert_eq!(total_supply_at(start + 2), 1000);
+    assert_eq!(total_supply_at(start + 3), 950);
+
+    // cw20 queries are still served
+    let res: BalanceResponse = from_json(
+        query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Balance {
+                address: "addr0000".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.balance, Uint128::from(850u128));
+}
+
+#[test]
+fn permit_sets_allowance() {
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info, MockApi};
+    use cw20::{Cw20Coin, Expiration};
+    use k256::ecdsa::{signature::hazmat::PrehashSigner, Signature, SigningKey};
+
+    let mut deps = mock_dependencies();
+    // bech32 addresses, so the owner can be derived from its public key
+    deps.api = MockApi::default().with_prefix("orai");
+    let env = mock_env();
+
+    let signing_key = SigningKey::from_slice(&[7u8; 32]).unwrap();
+    let owner_pubkey = Binary::from(
+        signing_key
+            .verifying_key()
+            .to_encoded_point(true)
+            .as_bytes()
+            .to_vec(),
+    );
+    let owner = pubkey_to_address(&deps.api, &owner_pubkey).unwrap();
+    let spender = deps.api.addr_make("spender");
+    let relayer = deps.api.addr_make("relayer");
+    let creator = deps.api.addr_make("creator");
+
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(creator.as_str(), &[]),
+        InstantiateMsg {
+            name: "oraiswap token".to_string(),
+            symbol: "ORAIX".to_string(),
+            decimals: 6,
+            initial_balances: vec![Cw20Coin {
+                address: owner.to_string(),
+                amount: Uint128::from(1000u128),
+            }],
+            mint: None,
+            marketing: None,
+            admin: None,
+            burner: None,
+        },
+    )
+    .unwrap();
+
+    let sign = |nonce: u64, amount: u128| -> Binary {
+        let payload = PermitPayload {
+            chain_id: env.block.chain_id.clone(),
+            contract: env.contract.address.to_string(),
+            spender: spender.to_string(),
+            amount: Uint128::from(amount),
+            expires: Expiration::Never {},
+            nonce,
+        };
+        let message_hash = Sha256::digest(to_json_vec(&payload).unwrap());
+        let signature: Signature = signing_key.sign_prehash(&message_hash).unwrap();
+        Binary::from(signature.to_bytes().to_vec())
+    };
+    let permit = |nonce: u64, amount: u128, signature: Binary| ExecuteMsg::Permit {
+        owner_pubkey: owner_pubkey.clone(),
+        spender: spender.to_string(),
+        amount: Uint128::from(amount),
+        expires: Expiration::Never {},
+        nonce,
+        signature,
+    };
+
+    // anyone can relay the permit
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(relayer.as_str(), &[]),
+        permit(0, 500, sign(0, 500)),
+    )
+    .unwrap();
+
+    // the payload must match the signature
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(relayer.as_str(), &[]),
+        permit(1, 900, sign(1, 500)),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::InvalidSignature {});
+
+    // a permit cannot be replayed
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(relayer.as_str(), &[]),
+        permit(0, 500, sign(0, 500)),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::InvalidNonce { expected: 1 });
+
+    let res: PermitNonceResponse = from_json(
+        query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::PermitNonce {
+                owner: owner.to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.nonce, 1);
+
+    // the spender can use the allowance right away
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(spender.as_str(), &[]),
+        ExecuteMsg::TransferFrom {
+            owner: owner.to_string(),
+            recipient: spender.to_string(),
+            amount: Uint128::from(300u128),
+        },
+    )
+    .unwrap();
+    let res: AllowanceResponse = from_json(
+        query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Allowance {
+                owner: owner.to_string(),
+                spender: spender.to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.allowance, Uint128::from(200u128));
+}
+
+#[test]
+fn freeze_pause_and_burn_frozen() {
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+    use cw20::{Cw20Coin, TokenInfoResponse};
+
+    let mut deps = mock_dependencies();
+    let env = mock_env();
+
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("creator", &[]),
+        InstantiateMsg {
+            name: "oraiswap token".to_string(),
+            symbol: "ORAIX".to_string(),
+            decimals: 6,
+            initial_balances: vec![
+                Cw20Coin {
+                    address: "addr0000".to_string(),
+                    amount: Uint128::from(1000u128),
+                },
+                Cw20Coin {
+                    address: "addr0001".to_string(),
+                    amount: Uint128::from(1000u128),
+                },
+            ],
+            mint: None,
+            marketing: None,
+            admin: Some("admin".to_string()),
+            burner: Some("burner".to_string()),
+        },
+    )
+    .unwrap();
+
+    let transfer = |recipient: &str| ExecuteMsg::Transfer {
+        recipient: recipient.to_string(),
+        amount: Uint128::from(100u128),
+    };
+
+    // only the admin can freeze
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &[]),
+        ExecuteMsg::Freeze {
+            address: "addr0001".to_string(),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("admin", &[]),
+        ExecuteMsg::Freeze {
+            address: "addr0001".to_string(),
+        },
+    )
+    .unwrap();
+
+    // a frozen address can neither send nor receive
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0001", &[]),
+        transfer("addr0000"),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Frozen {
+            address: "addr0001".to_string()
+        }
+    );
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &[]),
+        transfer("addr0001"),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Frozen {
+            address: "addr0001".to_string()
+        }
+    );
+
+    // only the burner can burn from a frozen address
+    let burn_frozen = |address: &str| ExecuteMsg::BurnFrozen {
+        address: address.to_string(),
+        amount: Uint128::from(400u128),
+    };
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("admin", &[]),
+        burn_frozen("addr0001"),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("burner", &[]),
+        burn_frozen("addr0000"),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::NotFrozen {
+            address: "addr0000".to_string()
+        }
+    );
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("burner", &[]),
+        burn_frozen("addr0001"),
+    )
+    .unwrap();
+
+    let res: TokenInfoResponse =
+        from_json(query(deps.as_ref(), env.clone(), QueryMsg::TokenInfo {}).unwrap()).unwrap();
+    assert_eq!(res.total_supply, Uint128::from(1600u128));
+    let res: BalanceResponse = from_json(
+        query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Balance {
+                address: "addr0001".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.balance, Uint128::from(600u128));
+
+    // pause stops every transfer until unpaused
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("admin", &[]),
+        ExecuteMsg::Pause {},
+    )
+    .unwrap();
+    let res: FrozenResponse = from_json(
+        query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Frozen {
+                address: "addr0001".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res,
+        FrozenResponse {
+            frozen: true,
+            paused: true
+        }
+    );
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &[]),
+        transfer("addr0002"),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Paused {});
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("admin", &[]),
+        ExecuteMsg::Unpause {},
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &[]),
+        transfer("addr0002"),
+    )
+    .unwrap();
+}
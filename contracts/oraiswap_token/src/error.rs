@@ -0,0 +1,32 @@
+use cosmwasm_std::StdError;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ContractError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("{0}")]
+    Cw20(#[from] cw20_base::ContractError),
+
+    #[error("Invalid permit signature")]
+    InvalidSignature {},
+
+    #[error("Invalid permit nonce, expected {expected}")]
+    InvalidNonce { expected: u64 },
+
+    #[error("Permit expired")]
+    PermitExpired {},
+
+    #[error("Unauthorized")]
+    Unauthorized {},
+
+    #[error("Transfers are paused")]
+    Paused {},
+
+    #[error("Address {address} is frozen")]
+    Frozen { address: String },
+
+    #[error("Address {address} is not frozen")]
+    NotFrozen { address: String },
+}
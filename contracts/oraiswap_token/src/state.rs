@@ -0,0 +1,36 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Uint128};
+use cw_storage_plus::{Item, Map, SnapshotItem, SnapshotMap, Strategy};
+
+/// Balance checkpoints by block height, mirroring cw20-base BALANCES
+pub const BALANCE_SNAPSHOTS: SnapshotMap<&Addr, Uint128> = SnapshotMap::new(
+    "balance_snapshots",
+    "balance_snapshots__checkpoints",
+    "balance_snapshots__changelog",
+    Strategy::EveryBlock,
+);
+
+/// Total supply checkpoints by block height, mirroring cw20-base TOKEN_INFO
+pub const TOTAL_SUPPLY_SNAPSHOT: SnapshotItem<Uint128> = SnapshotItem::new(
+    "total_supply_snapshot",
+    "total_supply_snapshot__checkpoints",
+    "total_supply_snapshot__changelog",
+    Strategy::EveryBlock,
+);
+
+/// Nonce expected by the next permit of each owner
+pub const PERMIT_NONCES: Map<&Addr, u64> = Map::new("permit_nonces");
+
+pub const COMPLIANCE_CONFIG: Item<ComplianceConfig> = Item::new("compliance_config");
+/// Frozen addresses cannot send nor receive tokens
+pub const FROZEN: Map<&Addr, bool> = Map::new("frozen");
+
+#[cw_serde]
+#[derive(Default)]
+pub struct ComplianceConfig {
+    // can freeze addresses and pause transfers
+    pub admin: Option<Addr>,
+    // can burn the balance of frozen addresses
+    pub burner: Option<Addr>,
+    pub paused: bool,
+}
@@ -40,7 +40,9 @@ impl Asset {
                 Ok(Uint128::from(0u64))
             } else {
                 // get oracle params from oracle contract
-                let tax_rate = oracle_contract.query_tax_rate(querier)?.rate;
+                let tax_rate = oracle_contract
+                    .query_denom_tax_rate(querier, denom.to_string())?
+                    .rate;
                 let tax_cap = oracle_contract
                     .query_tax_cap(querier, denom.to_string())?
                     .cap;
@@ -55,6 +57,25 @@ impl Asset {
         }
     }
 
+    /// compute_tax for a transfer into recipient, nothing is charged if recipient is tax exempt
+    pub fn compute_tax_to(
+        &self,
+        oracle_contract: &OracleContract,
+        querier: &QuerierWrapper,
+        recipient: &Addr,
+    ) -> StdResult<Uint128> {
+        let tax_amount = self.compute_tax(oracle_contract, querier)?;
+        if tax_amount.is_zero()
+            || !oracle_contract
+                .query_tax_exempt(querier, recipient.to_string())?
+                .exempt
+        {
+            return Ok(tax_amount);
+        }
+
+        Ok(Uint128::zero())
+    }
+
     /// create a CosmosMsg send message to receiver
     pub fn into_msg(
         &self,
@@ -78,7 +99,11 @@ impl Asset {
                 let send_amount = if let Some(oracle_contract) = oracle_contract {
                     coin(
                         self.amount
-                            .checked_sub(self.compute_tax(oracle_contract, querier)?)?
+                            .checked_sub(self.compute_tax_to(
+                                oracle_contract,
+                                querier,
+                                &recipient,
+                            )?)?
                             .into(),
                         denom,
                     )
@@ -1,9 +1,13 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
 
-use cosmwasm_std::{Addr, Decimal};
+use cosmwasm_std::{
+    to_json_binary, Addr, Coin, CosmosMsg, Decimal, QuerierWrapper, StdError, StdResult, Uint128,
+    WasmMsg,
+};
 
 use crate::asset::AssetInfo;
-use cw20::Cw20ReceiveMsg;
+use crate::math::Converter128;
+use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
 
 #[cw_serde]
 pub struct TokenInfo {
@@ -76,3 +80,134 @@ pub struct ConfigResponse {
 pub struct ConvertInfoResponse {
     pub token_ratio: TokenRatio,
 }
+
+/// Conversion is how the converter turns one asset into another,
+/// reverse means the pair is registered from the ask asset
+#[cw_serde]
+pub struct Conversion {
+    pub token_ratio: TokenRatio,
+    pub reverse: bool,
+}
+
+impl Conversion {
+    /// amount of the ask asset received for amount of the offer asset
+    pub fn convert(&self, amount: Uint128) -> StdResult<Uint128> {
+        if self.reverse {
+            amount.checked_div_decimal(self.token_ratio.ratio)
+        } else {
+            Ok(amount * self.token_ratio.ratio)
+        }
+    }
+
+    /// amount of the offer asset required to receive amount of the ask asset
+    pub fn convert_reverse(&self, amount: Uint128) -> StdResult<Uint128> {
+        let ratio = if self.reverse {
+            Decimal::one()
+                .checked_div(self.token_ratio.ratio)
+                .map_err(|err| StdError::generic_err(err.to_string()))?
+        } else {
+            self.token_ratio.ratio
+        };
+        amount
+            .checked_div_ceil(ratio)
+            .map_err(|err| StdError::generic_err(err.to_string()))
+    }
+
+    /// price of the offer asset in the ask asset
+    pub fn price(&self) -> StdResult<Decimal> {
+        if self.reverse {
+            Decimal::one()
+                .checked_div(self.token_ratio.ratio)
+                .map_err(|err| StdError::generic_err(err.to_string()))
+        } else {
+            Ok(self.token_ratio.ratio)
+        }
+    }
+}
+
+#[cw_serde]
+pub struct ConverterController(pub Addr);
+
+impl ConverterController {
+    pub fn addr(&self) -> Addr {
+        self.0.clone()
+    }
+
+    /// find the registered pair converting from into to, in either direction
+    pub fn query_conversion(
+        &self,
+        querier: &QuerierWrapper,
+        from: &AssetInfo,
+        to: &AssetInfo,
+    ) -> StdResult<Conversion> {
+        let query_token_ratio = |asset_info: &AssetInfo| {
+            querier
+                .query_wasm_smart::<ConvertInfoResponse>(
+                    self.addr(),
+                    &QueryMsg::ConvertInfo {
+                        asset_info: asset_info.clone(),
+                    },
+                )
+                .map(|res| res.token_ratio)
+        };
+
+        if let Ok(token_ratio) = query_token_ratio(from) {
+            if token_ratio.info.eq(to) {
+                return Ok(Conversion {
+                    token_ratio,
+                    reverse: false,
+                });
+            }
+        }
+        if let Ok(token_ratio) = query_token_ratio(to) {
+            if token_ratio.info.eq(from) {
+                return Ok(Conversion {
+                    token_ratio,
+                    reverse: true,
+                });
+            }
+        }
+
+        Err(StdError::generic_err(format!(
+            "no converter pair for {} and {}",
+            from, to
+        )))
+    }
+
+    /// convert amount of from into to, the converted asset is paid to the sender
+    pub fn convert_msg(
+        &self,
+        conversion: &Conversion,
+        from: AssetInfo,
+        to: AssetInfo,
+        amount: Uint128,
+    ) -> StdResult<CosmosMsg> {
+        let msg: CosmosMsg = match from {
+            AssetInfo::NativeToken { denom } => WasmMsg::Execute {
+                contract_addr: self.addr().to_string(),
+                msg: if conversion.reverse {
+                    to_json_binary(&ExecuteMsg::ConvertReverse { from_asset: to })?
+                } else {
+                    to_json_binary(&ExecuteMsg::Convert {})?
+                },
+                funds: vec![Coin { denom, amount }],
+            }
+            .into(),
+            AssetInfo::Token { contract_addr } => WasmMsg::Execute {
+                contract_addr: contract_addr.to_string(),
+                msg: to_json_binary(&Cw20ExecuteMsg::Send {
+                    contract: self.addr().to_string(),
+                    amount,
+                    msg: if conversion.reverse {
+                        to_json_binary(&Cw20HookMsg::ConvertReverse { from: to })?
+                    } else {
+                        to_json_binary(&Cw20HookMsg::Convert {})?
+                    },
+                })?,
+                funds: vec![],
+            }
+            .into(),
+        };
+        Ok(msg)
+    }
+}
@@ -54,6 +54,15 @@ pub enum ContractError {
     #[error("must provide operations")]
     NoSwapOperation {},
 
+    #[error("Split routes must spend exactly the offer amount")]
+    InvalidSplitRoutes {},
+
+    #[error("Required offer amount {offer_amount} exceeds max offer {max_offer}")]
+    MaxOfferExceeded {
+        max_offer: Uint128,
+        offer_amount: Uint128,
+    },
+
     #[error("invalid cw20 hook message")]
     InvalidCw20HookMessage {},
 
@@ -113,4 +122,25 @@ pub enum ContractError {
 
     #[error("Contract paused")]
     Paused {},
+
+    #[error("Quorum must be greater than zero")]
+    InvalidQuorum {},
+
+    #[error("Exchange rate change of {denom} exceeds the limit")]
+    RateChangeExceeded { denom: String },
+
+    #[error("No pending exchange rate for {denom}")]
+    NoPendingExchangeRate { denom: String },
+
+    #[error("Invalid price source")]
+    InvalidPriceSource {},
+
+    #[error("Fallback price of {denom} was already observed in this block")]
+    FallbackPriceObserved { denom: String },
+
+    #[error("Total affiliate fee of {total_bps} bps exceeds the max of {max_bps} bps")]
+    AffiliateFeeExceeded {
+        total_bps: Uint128,
+        max_bps: Uint128,
+    },
 }
@@ -1,4 +1,4 @@
-use cosmwasm_std::{Decimal, StdError, StdResult, Uint128};
+use cosmwasm_std::{Decimal, Decimal256, StdError, StdResult, Uint128, Uint256};
 
 pub trait Converter128 {
     fn checked_div_decimal(&self, denominator: Decimal) -> StdResult<Self>
@@ -41,3 +41,23 @@ impl DecimalPlaces for Decimal {
             .map_err(|err| StdError::generic_err(err.to_string()))
     }
 }
+
+/// relative shortfall of return_amount versus offer_amount converted at spot_price,
+/// zero when nothing is lost
+pub fn compute_price_impact(
+    offer_amount: Uint128,
+    return_amount: Uint128,
+    spot_price: Decimal256,
+) -> StdResult<Decimal> {
+    let expected_amount = Uint256::from(offer_amount) * spot_price;
+    let return_amount = Uint256::from(return_amount);
+    if expected_amount.is_zero() || return_amount >= expected_amount {
+        return Ok(Decimal::zero());
+    }
+
+    Decimal::try_from(Decimal256::from_ratio(
+        expected_amount - return_amount,
+        expected_amount,
+    ))
+    .map_err(|err| StdError::generic_err(err.to_string()))
+}
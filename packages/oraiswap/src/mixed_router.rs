@@ -1,18 +1,23 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
 
 use cosmwasm_std::{
-    coin, to_json_binary, Addr, Api, CosmosMsg, QuerierWrapper, StdResult, Uint128, WasmMsg,
+    coin, to_json_binary, Addr, Api, Binary, CosmosMsg, Decimal, Decimal256, QuerierWrapper,
+    StdError, StdResult, Uint128, WasmMsg,
 };
 use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
-use oraiswap_v3::PoolKey;
+use oraiswap_v3::percentage::Percentage;
+use oraiswap_v3::sqrt_price::SqrtPrice;
+use oraiswap_v3::{FeeTier, PoolKey};
 
-use crate::asset::AssetInfo;
+use crate::asset::{Asset, AssetInfo};
+use crate::universal_swap_memo::memo;
 
 #[cw_serde]
 pub struct InstantiateMsg {
     pub factory_addr: Addr,
     pub factory_addr_v2: Addr,
     pub oraiswap_v3: Addr,
+    pub converter: Option<Addr>,
 }
 
 #[cw_serde]
@@ -25,9 +30,19 @@ pub enum SwapOperation {
         offer_asset_info: AssetInfo,
         ask_asset_info: AssetInfo,
     },
+    /// the swap stops at sqrt_price_limit, the unused offer is refunded to the sender,
+    /// swapping by amount out is only supported on a single operation,
+    /// where the ask amount is the minimum receive
     SwapV3 {
         pool_key: PoolKey,
         x_to_y: bool,
+        sqrt_price_limit: Option<SqrtPrice>,
+        by_amount_in: bool,
+    },
+    // convert between two representations of an asset with the converter
+    Convert {
+        from: AssetInfo,
+        to: AssetInfo,
     },
 }
 
@@ -35,12 +50,63 @@ impl SwapOperation {
     pub fn get_target_asset_info(&self, api: &dyn Api) -> AssetInfo {
         match self {
             SwapOperation::OraiSwap { ask_asset_info, .. } => ask_asset_info.clone(),
-            SwapOperation::SwapV3 { pool_key, x_to_y } => match x_to_y {
+            SwapOperation::SwapV3 {
+                pool_key, x_to_y, ..
+            } => match x_to_y {
                 true => AssetInfo::from_denom(api, &pool_key.token_y),
                 false => AssetInfo::from_denom(api, &pool_key.token_x),
             },
+            SwapOperation::Convert { to, .. } => to.clone(),
         }
     }
+
+    /// translate an operation of a universal swap memo, pool_id is either a v3 pool key
+    /// as token_x-token_y-fee-tick_spacing or the address of a v2 pair
+    pub fn from_memo(api: &dyn Api, operation: &memo::SwapOperation) -> StdResult<Self> {
+        let Some(pool_key) = parse_pool_key(&operation.pool_id) else {
+            return Ok(SwapOperation::OraiSwap {
+                offer_asset_info: AssetInfo::from_denom(api, &operation.denom_in),
+                ask_asset_info: AssetInfo::from_denom(api, &operation.denom_out),
+            });
+        };
+
+        let x_to_y = if pool_key.token_x == operation.denom_in
+            && pool_key.token_y == operation.denom_out
+        {
+            true
+        } else if pool_key.token_y == operation.denom_in && pool_key.token_x == operation.denom_out
+        {
+            false
+        } else {
+            return Err(StdError::generic_err(format!(
+                "denoms of the operation do not match pool {}",
+                operation.pool_id
+            )));
+        };
+
+        Ok(SwapOperation::SwapV3 {
+            pool_key,
+            x_to_y,
+            sqrt_price_limit: None,
+            by_amount_in: true,
+        })
+    }
+}
+
+fn parse_pool_key(pool_id: &str) -> Option<PoolKey> {
+    let mut parts = pool_id.rsplitn(3, '-');
+    let tick_spacing = parts.next()?.parse::<u16>().ok()?;
+    let fee = parts.next()?.parse::<u64>().ok()?;
+    let (token_x, token_y) = parts.next()?.split_once('-')?;
+
+    Some(PoolKey {
+        token_x: token_x.to_string(),
+        token_y: token_y.to_string(),
+        fee_tier: FeeTier {
+            fee: Percentage(fee),
+            tick_spacing,
+        },
+    })
 }
 
 #[cw_serde]
@@ -52,29 +118,67 @@ pub enum ExecuteMsg {
         minimum_receive: Option<Uint128>,
         to: Option<Addr>,
         affiliates: Option<Vec<Affiliate>>,
+        /// run on the output instead of the transfer to `to`,
+        /// the output is transferred to `to` when it fails
+        post_swap_action: Option<PostSwapAction>,
+    },
+
+    /// Execute the user swap and the post swap action of a universal swap memo,
+    /// the offer is refunded to the recovery address when they fail
+    ExecuteMemo {
+        memo: Binary,
     },
 
     /// Internal use
-    /// Swap all offer tokens to ask token
+    /// Swap all offer tokens to ask token, or only amount of them,
+    /// amount is the ask amount of a v3 swap by amount out
     ExecuteSwapOperation {
         operation: SwapOperation,
         to: Option<Addr>,
         sender: Addr,
+        amount: Option<Uint128>,
     },
     /// Internal use
-    /// Check the swap amount is exceed minimum_receive
+    /// Execute the routes of a memo, every route spends its offer amount
+    ExecuteMemoRoutes {
+        routes: Vec<SwapRoute>,
+        minimum_receive: Uint128,
+        post_swap_action: PostSwapAction,
+        sender: Addr,
+        recovery_addr: Addr,
+    },
+    /// Internal use
+    /// Check the swap amount is exceed minimum_receive,
+    /// the residuals of the other assets of the swap are refunded to its sender
     AssertMinimumReceiveAndTransfer {
         asset_info: AssetInfo,
         minimum_receive: Uint128,
         receiver: Addr,
         affiliates: Vec<Affiliate>,
+        /// run on the output instead of the transfer to receiver,
+        /// the output is transferred to receiver when it fails
+        post_swap_action: Option<PostSwapAction>,
     },
     UpdateConfig {
         factory_addr: Option<String>,
         factory_addr_v2: Option<String>,
         oraiswap_v3: Option<String>,
+        converter: Option<String>,
+        ics20_contract: Option<String>,
+        /// cap of the total basis points fee of the affiliates of a swap
+        max_affiliate_fee_bps: Option<Uint128>,
         owner: Option<String>,
     },
+    /// send the balances orphaned by past swaps to recipient, or to the owner
+    SweepDust {
+        asset_infos: Vec<AssetInfo>,
+        recipient: Option<String>,
+    },
+    /// accrue the affiliate fees of the sender in the router instead of receiving them with every swap
+    RegisterAffiliate {},
+    DeregisterAffiliate {},
+    /// pay out the accrued affiliate fees of the sender
+    ClaimAffiliateFees {},
 }
 
 #[cw_serde]
@@ -84,9 +188,102 @@ pub enum Cw20HookMsg {
         minimum_receive: Option<Uint128>,
         to: Option<String>,
         affiliates: Option<Vec<Affiliate>>,
+        post_swap_action: Option<PostSwapAction>,
+    },
+    ExecuteMemo {
+        memo: Binary,
     },
 }
 
+#[cw_serde]
+pub struct SwapRoute {
+    pub offer_amount: Uint128,
+    pub operations: Vec<SwapOperation>,
+}
+
+/// what is done with the output of a swap
+#[cw_serde]
+pub enum PostSwapAction {
+    Transfer {
+        to_address: String,
+    },
+    /// execute the contract with the output as funds, or send the output to it with msg
+    ContractCall {
+        contract_address: String,
+        msg: Binary,
+    },
+    /// ibc transfer of a native output, timeout in seconds
+    IbcTransfer {
+        channel_id: String,
+        to_address: String,
+        timeout: Option<u64>,
+    },
+    /// transfer the output to the remote chain through the ics20 contract of the config
+    IbcWasmTransfer {
+        local_channel_id: String,
+        remote_address: String,
+        remote_denom: String,
+        timeout: Option<u64>,
+        memo: Option<String>,
+    },
+}
+
+/// transfer message of the ics20 contract, sent as the msg of a cw20 Send
+/// or wrapped in TransferToRemote with the funds
+#[cw_serde]
+pub struct TransferBackMsg {
+    pub local_channel_id: String,
+    pub remote_address: String,
+    pub remote_denom: String,
+    pub timeout: Option<u64>,
+    pub memo: Option<String>,
+}
+
+#[cw_serde]
+pub enum Ics20ExecuteMsg {
+    TransferToRemote(TransferBackMsg),
+}
+
+impl TryFrom<memo::PostAction> for PostSwapAction {
+    type Error = StdError;
+
+    fn try_from(action: memo::PostAction) -> StdResult<Self> {
+        if let Some(contract_call) = action.contract_call {
+            return Ok(PostSwapAction::ContractCall {
+                contract_address: contract_call.contract_address,
+                msg: Binary::from_base64(&contract_call.msg)?,
+            });
+        }
+        if let Some(transfer) = action.ibc_transfer_msg {
+            // ibc transfers of this cosmwasm version have no memo
+            if !transfer.memo.is_empty() {
+                return Err(StdError::generic_err("ibc transfer memo is not supported"));
+            }
+            return Ok(PostSwapAction::IbcTransfer {
+                channel_id: transfer.source_channel,
+                to_address: transfer.receiver,
+                timeout: None,
+            });
+        }
+        if let Some(transfer) = action.ibc_wasm_transfer_msg {
+            return Ok(PostSwapAction::IbcWasmTransfer {
+                local_channel_id: transfer.local_channel_id,
+                remote_address: transfer.remote_address,
+                remote_denom: transfer.remote_denom,
+                timeout: transfer.timeout,
+                memo: transfer.memo,
+            });
+        }
+        if let Some(transfer) = action.transfer_msg {
+            return Ok(PostSwapAction::Transfer {
+                to_address: transfer.to_address,
+            });
+        }
+
+        Err(StdError::generic_err("unsupported post swap action"))
+    }
+}
+
 #[cw_serde]
 #[derive(QueryResponses)]
 pub enum QueryMsg {
@@ -97,6 +294,21 @@ pub enum QueryMsg {
         offer_amount: Uint128,
         operations: Vec<SwapOperation>,
     },
+    /// same as SimulateSwapOperations, with the breakdown of every hop
+    #[returns(SimulateSwapOperationsDetailedResponse)]
+    SimulateSwapOperationsDetailed {
+        offer_amount: Uint128,
+        operations: Vec<SwapOperation>,
+    },
+    /// fees accrued by the affiliate
+    #[returns(AffiliateFeesResponse)]
+    AffiliateFees { address: String },
+    /// affiliates accruing their fees
+    #[returns(AffiliatesResponse)]
+    Affiliates {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
 }
 
 // We define a custom struct for each query response
@@ -105,6 +317,19 @@ pub struct ConfigResponse {
     pub factory_addr: Addr,
     pub factory_addr_v2: Addr,
     pub oraiswap_v3: Addr,
+    pub converter: Option<Addr>,
+    pub ics20_contract: Option<Addr>,
+    pub max_affiliate_fee_bps: Uint128,
+}
+
+#[cw_serde]
+pub struct AffiliateFeesResponse {
+    pub fees: Vec<Asset>,
+}
+
+#[cw_serde]
+pub struct AffiliatesResponse {
+    pub affiliates: Vec<Addr>,
 }
 
 // We define a custom struct for each query response
@@ -113,6 +338,51 @@ pub struct SimulateSwapOperationsResponse {
     pub amount: Uint128,
 }
 
+#[cw_serde]
+pub struct SimulateSwapOperationsDetailedResponse {
+    pub amount: Uint128,
+    pub hops: Vec<HopSimulation>,
+    /// shortfall of amount versus the offer amount converted at the spot price of the route
+    pub price_impact: Decimal,
+}
+
+#[cw_serde]
+pub enum SwapVenue {
+    OraiSwap {
+        pair_addr: Addr,
+        /// the pair is listed by factory_addr_v2 instead of factory_addr
+        from_factory_v2: bool,
+    },
+    SwapV3 {
+        pool_key: PoolKey,
+    },
+    Convert {
+        converter: Addr,
+    },
+}
+
+#[cw_serde]
+pub struct HopSimulation {
+    pub venue: SwapVenue,
+    pub offer_asset_info: AssetInfo,
+    pub ask_asset_info: AssetInfo,
+    /// amount sent to the venue, before tax
+    pub offer_amount: Uint128,
+    /// amount received from the venue, after tax
+    pub return_amount: Uint128,
+    pub spread_amount: Uint128,
+    /// pair commission or v3 pool fee
+    pub commission_amount: Uint128,
+    /// oracle tax charged on the offer and on the return
+    pub tax_amount: Uint128,
+    /// spot price of the offer asset in the ask asset before the swap
+    pub price_before: Decimal256,
+    /// spot price of the offer asset in the ask asset after the swap
+    pub price_after: Decimal256,
+    /// initialized ticks crossed by a v3 swap
+    pub ticks_crossed: Option<u32>,
+}
+
 #[cw_serde]
 pub struct MixedRouterController(pub String);
 
@@ -144,6 +414,7 @@ impl MixedRouterController {
                         minimum_receive,
                         to: swap_to.map(|to| to.into_string()),
                         affiliates,
+                        post_swap_action: None,
                     })?,
                 })?,
                 funds: vec![],
@@ -156,6 +427,7 @@ impl MixedRouterController {
                     minimum_receive,
                     to: swap_to,
                     affiliates,
+                    post_swap_action: None,
                 })?,
                 funds: vec![coin(amount.u128(), denom)],
             }
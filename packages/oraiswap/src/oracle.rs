@@ -6,6 +6,8 @@ use cosmwasm_std::{
     Uint128, WasmMsg,
 };
 
+use crate::asset::{AssetInfo, ORAI_DENOM};
+
 #[cw_serde]
 pub struct InstantiateMsg {
     /// name of the NFT contract, can use default
@@ -16,26 +18,86 @@ pub struct InstantiateMsg {
     pub max_rate: Option<Decimal>,
 }
 
+/// Rates, tax caps and the other settings of an asset are stored by its AssetInfo::to_vec key,
+/// so native and ibc/ denoms are keyed by the denom and cw20 tokens by their canonical address
 #[cw_serde]
 pub enum ExecuteMsg {
     UpdateAdmin {
         admin: Addr,
     },
     UpdateExchangeRate {
-        denom: String,
+        asset_info: AssetInfo,
         exchange_rate: Decimal,
     },
     DeleteExchangeRate {
-        denom: String,
+        asset_info: AssetInfo,
     },
     UpdateTaxCap {
-        denom: String,
+        asset_info: AssetInfo,
         cap: Uint128,
     },
     // RateMax: 1%
     UpdateTaxRate {
         rate: Decimal,
     },
+    /// override the global tax rate for asset_info, None to remove the override
+    UpdateDenomTaxRate {
+        asset_info: AssetInfo,
+        rate: Option<Decimal>,
+    },
+    /// transfers into a tax exempt address are not taxed
+    AddTaxExempt {
+        address: Addr,
+    },
+    RemoveTaxExempt {
+        address: Addr,
+    },
+    AddFeeder {
+        feeder: Addr,
+    },
+    RemoveFeeder {
+        feeder: Addr,
+    },
+    /// max_age = 0 disables the staleness check
+    UpdateFeedConfig {
+        quorum: Option<u32>,
+        max_age: Option<u64>,
+        reject_stale: Option<bool>,
+    },
+    /// submitted by a whitelisted feeder, the median is stored once quorum is reached
+    FeedExchangeRate {
+        asset_info: AssetInfo,
+        exchange_rate: Decimal,
+    },
+    /// None removes the circuit breaker of asset_info
+    UpdateRateLimit {
+        asset_info: AssetInfo,
+        rate_limit: Option<RateLimit>,
+    },
+    /// confirmers can confirm or reject pending exchange rates along with the admin
+    AddConfirmer {
+        confirmer: Addr,
+    },
+    RemoveConfirmer {
+        confirmer: Addr,
+    },
+    /// must be sent by someone other than the proposer of the pending rate
+    ConfirmExchangeRate {
+        asset_info: AssetInfo,
+    },
+    RejectExchangeRate {
+        asset_info: AssetInfo,
+    },
+    /// used when asset_info has no fresh pushed rate, None removes the fallback
+    UpdateFallbackPrice {
+        asset_info: AssetInfo,
+        fallback: Option<FallbackPrice>,
+    },
+    /// record the cumulative prices of the fallback source of asset_info, at most once per block,
+    /// only the admin or a feeder can observe
+    ObserveFallbackPrice {
+        asset_info: AssetInfo,
+    },
 }
 
 /// QueryMsg is defines available query datas
@@ -50,16 +112,27 @@ pub enum QueryMsg {
 
 #[cw_serde]
 #[derive(QueryResponses)]
+/// denom is a native denom, only native coins are taxed
 pub enum OracleTreasuryQuery {
+    /// rate of denom if overridden, the global rate otherwise
     #[returns(TaxRateResponse)]
-    TaxRate {},
+    TaxRate { denom: Option<String> },
     #[returns(TaxCapResponse)]
     TaxCap { denom: String },
+    #[returns(TaxExemptResponse)]
+    TaxExempt { address: String },
+    /// all tax caps, ascending by storage key
+    #[returns(AllTaxCapsResponse)]
+    AllTaxCaps {
+        start_after: Option<AssetInfo>,
+        limit: Option<u32>,
+    },
 }
 
 #[cw_serde]
 #[derive(QueryResponses)]
 pub enum OracleExchangeQuery {
+    /// denoms are native denoms, use AssetExchangeRate for cw20 tokens
     #[returns(ExchangeRateResponse)]
     ExchangeRate {
         base_denom: Option<String>,
@@ -70,6 +143,37 @@ pub enum OracleExchangeQuery {
         base_denom: Option<String>,
         quote_denoms: Vec<String>,
     },
+    /// like ExchangeRate, along with the source, update time and staleness of the rate
+    #[returns(AssetExchangeRateResponse)]
+    AssetExchangeRate {
+        base_asset_info: Option<AssetInfo>,
+        quote_asset_info: AssetInfo,
+    },
+    /// accepted rates of asset_info to Orai, ascending by update time
+    #[returns(ExchangeRateHistoryResponse)]
+    ExchangeRateHistory {
+        asset_info: AssetInfo,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    #[returns(ExchangeRateTwapResponse)]
+    ExchangeRateTwap {
+        base_asset_info: Option<AssetInfo>,
+        quote_asset_info: AssetInfo,
+        window_seconds: u64,
+    },
+    #[returns(RateLimit)]
+    RateLimit { asset_info: AssetInfo },
+    #[returns(Option<PendingExchangeRateResponse>)]
+    PendingExchangeRate { asset_info: AssetInfo },
+    /// all stored rates to Orai, ascending by storage key
+    #[returns(AllExchangeRatesResponse)]
+    AllExchangeRates {
+        start_after: Option<AssetInfo>,
+        limit: Option<u32>,
+    },
+    #[returns(Option<FallbackPrice>)]
+    FallbackPrice { asset_info: AssetInfo },
 }
 
 #[cw_serde]
@@ -79,6 +183,8 @@ pub enum OracleContractQuery {
     ContractInfo {},
     #[returns(cosmwasm_std::Coin)]
     RewardPool { denom: String },
+    #[returns(FeedConfigResponse)]
+    FeedConfig {},
 }
 
 /// TaxRateResponse is data format returned from TreasuryRequest::TaxRate query
@@ -93,6 +199,26 @@ pub struct TaxCapResponse {
     pub cap: Uint128,
 }
 
+#[cw_serde]
+pub struct TaxCapItem {
+    pub asset_info: AssetInfo,
+    pub cap: Uint128,
+    /// last update time in seconds, None for caps set before it was tracked
+    pub last_updated: Option<u64>,
+}
+
+/// AllTaxCapsResponse is data format returned from TreasuryRequest::AllTaxCaps query
+#[cw_serde]
+pub struct AllTaxCapsResponse {
+    pub items: Vec<TaxCapItem>,
+}
+
+/// TaxExemptResponse is data format returned from TreasuryRequest::TaxExempt query
+#[cw_serde]
+pub struct TaxExemptResponse {
+    pub exempt: bool,
+}
+
 /// ExchangeRateItem is data format returned from OracleRequest::ExchangeRates query
 #[cw_serde]
 pub struct ExchangeRateItem {
@@ -114,6 +240,109 @@ pub struct ExchangeRateResponse {
     pub item: ExchangeRateItem,
 }
 
+/// AssetExchangeRateItem is data format returned from OracleRequest::AssetExchangeRate query
+#[cw_serde]
+pub struct AssetExchangeRateItem {
+    pub asset_info: AssetInfo,
+    pub exchange_rate: Decimal,
+    /// source of the rate of asset_info
+    pub source: ExchangeRateSource,
+    /// oldest update time in seconds of the rates used, None for untracked rates like orai
+    pub last_updated: Option<u64>,
+    /// true when last_updated is older than the configured max_age
+    pub stale: bool,
+}
+
+/// AssetExchangeRateResponse is data format returned from OracleRequest::AssetExchangeRate query
+#[cw_serde]
+pub struct AssetExchangeRateResponse {
+    pub base_asset_info: AssetInfo,
+    pub base_source: ExchangeRateSource,
+    pub item: AssetExchangeRateItem,
+}
+
+/// AllExchangeRatesResponse is data format returned from OracleRequest::AllExchangeRates query,
+/// the rates are relative to Orai
+#[cw_serde]
+pub struct AllExchangeRatesResponse {
+    pub items: Vec<AssetExchangeRateItem>,
+}
+
+/// ExchangeRateHistoryItem is an accepted rate of denom to Orai at timestamp (in seconds)
+#[cw_serde]
+pub struct ExchangeRateHistoryItem {
+    pub timestamp: u64,
+    pub exchange_rate: Decimal,
+}
+
+/// ExchangeRateHistoryResponse is data format returned from OracleRequest::ExchangeRateHistory query
+#[cw_serde]
+pub struct ExchangeRateHistoryResponse {
+    pub asset_info: AssetInfo,
+    pub items: Vec<ExchangeRateHistoryItem>,
+}
+
+/// ExchangeRateTwapResponse is data format returned from OracleRequest::ExchangeRateTwap query
+#[cw_serde]
+pub struct ExchangeRateTwapResponse {
+    pub base_asset_info: AssetInfo,
+    pub quote_asset_info: AssetInfo,
+    pub window_seconds: u64,
+    pub exchange_rate: Decimal,
+}
+
+/// RateLimit is the circuit breaker of an asset, changes are relative to the current rate
+#[cw_serde]
+#[derive(Default)]
+pub struct RateLimit {
+    // max change of a single update
+    pub max_change: Option<Decimal>,
+    // max change compared to the rate one hour ago
+    pub max_change_per_hour: Option<Decimal>,
+    // queue updates beyond the limits for confirmation instead of rejecting them
+    pub require_confirmation: bool,
+}
+
+/// PriceSource is an on-chain source of the rate of an asset to Orai
+#[cw_serde]
+pub enum PriceSource {
+    /// pair of the asset and orai
+    Pair { pair_addr: Addr },
+    /// factory pairs along asset_infos, from the asset to orai
+    Route {
+        factory_addr: Addr,
+        asset_infos: Vec<AssetInfo>,
+    },
+}
+
+/// FallbackPrice is used when an asset has no fresh pushed rate
+#[cw_serde]
+pub struct FallbackPrice {
+    pub source: PriceSource,
+    // average the pair prices since the observation starting this window instead of using the
+    // current reserves
+    pub twap_window: Option<u64>,
+}
+
+#[cw_serde]
+pub enum ExchangeRateSource {
+    /// pushed by the admin or the feeders
+    Pushed,
+    /// current reserves of the fallback pairs
+    PairReserves,
+    /// time-weighted average of the observed fallback prices
+    PairTwap,
+}
+
+/// PendingExchangeRateResponse is data format returned from OracleRequest::PendingExchangeRate query
+#[cw_serde]
+pub struct PendingExchangeRateResponse {
+    pub asset_info: AssetInfo,
+    pub exchange_rate: Decimal,
+    pub proposer: Addr,
+    pub timestamp: u64,
+}
+
 /// ContractInfo is data format stored
 #[cw_serde]
 pub struct ContractInfo {
@@ -139,6 +368,36 @@ pub struct ContractInfoResponse {
     pub max_rate: Decimal,
 }
 
+/// FeedConfig is the stored configuration of the feeder set
+#[cw_serde]
+pub struct FeedConfig {
+    // number of fresh feeder submissions required to update a rate
+    pub quorum: u32,
+    // maximum age in seconds of a rate before it is considered stale
+    pub max_age: Option<u64>,
+    // fail exchange rate queries instead of flagging stale rates
+    pub reject_stale: bool,
+}
+
+impl Default for FeedConfig {
+    fn default() -> Self {
+        FeedConfig {
+            quorum: 1,
+            max_age: None,
+            reject_stale: false,
+        }
+    }
+}
+
+/// FeedConfigResponse is data format returned from OracleContractQuery::FeedConfig query
+#[cw_serde]
+pub struct FeedConfigResponse {
+    pub feeders: Vec<Addr>,
+    pub quorum: u32,
+    pub max_age: Option<u64>,
+    pub reject_stale: bool,
+}
+
 /// We currently take no arguments for migrations
 #[cw_serde]
 pub struct MigrateMsg {}
@@ -194,7 +453,31 @@ impl OracleContract {
     }
 
     pub fn query_tax_rate(&self, querier: &QuerierWrapper) -> StdResult<TaxRateResponse> {
-        let request = QueryMsg::Treasury(OracleTreasuryQuery::TaxRate {});
+        let request = QueryMsg::Treasury(OracleTreasuryQuery::TaxRate { denom: None });
+
+        self.query(querier, request)
+    }
+
+    pub fn query_denom_tax_rate<T: Into<String>>(
+        &self,
+        querier: &QuerierWrapper,
+        denom: T,
+    ) -> StdResult<TaxRateResponse> {
+        let request = QueryMsg::Treasury(OracleTreasuryQuery::TaxRate {
+            denom: Some(denom.into()),
+        });
+
+        self.query(querier, request)
+    }
+
+    pub fn query_tax_exempt<T: Into<String>>(
+        &self,
+        querier: &QuerierWrapper,
+        address: T,
+    ) -> StdResult<TaxExemptResponse> {
+        let request = QueryMsg::Treasury(OracleTreasuryQuery::TaxExempt {
+            address: address.into(),
+        });
 
         self.query(querier, request)
     }
@@ -214,6 +497,45 @@ impl OracleContract {
         self.query(querier, request)
     }
 
+    pub fn query_asset_exchange_rate(
+        &self,
+        querier: &QuerierWrapper,
+        base_asset_info: &AssetInfo,
+        quote_asset_info: &AssetInfo,
+    ) -> StdResult<AssetExchangeRateResponse> {
+        let request = QueryMsg::Exchange(OracleExchangeQuery::AssetExchangeRate {
+            base_asset_info: Some(base_asset_info.clone()),
+            quote_asset_info: quote_asset_info.clone(),
+        });
+
+        self.query(querier, request)
+    }
+
+    /// price of asset_info in Orai
+    pub fn query_asset_price(
+        &self,
+        querier: &QuerierWrapper,
+        asset_info: &AssetInfo,
+    ) -> StdResult<Decimal> {
+        let orai_info = AssetInfo::NativeToken {
+            denom: ORAI_DENOM.to_string(),
+        };
+        self.query_asset_exchange_rate(querier, asset_info, &orai_info)
+            .map(|res| res.item.exchange_rate)
+    }
+
+    /// prices of asset_infos in Orai, in the same order
+    pub fn query_asset_prices(
+        &self,
+        querier: &QuerierWrapper,
+        asset_infos: &[AssetInfo],
+    ) -> StdResult<Vec<Decimal>> {
+        asset_infos
+            .iter()
+            .map(|asset_info| self.query_asset_price(querier, asset_info))
+            .collect()
+    }
+
     pub fn query_exchange_rates<T: Into<String>>(
         &self,
         querier: &QuerierWrapper,
@@ -228,6 +550,61 @@ impl OracleContract {
         self.query(querier, request)
     }
 
+    pub fn query_all_tax_caps(
+        &self,
+        querier: &QuerierWrapper,
+        start_after: Option<AssetInfo>,
+        limit: Option<u32>,
+    ) -> StdResult<AllTaxCapsResponse> {
+        let request = QueryMsg::Treasury(OracleTreasuryQuery::AllTaxCaps { start_after, limit });
+
+        self.query(querier, request)
+    }
+
+    pub fn query_all_exchange_rates(
+        &self,
+        querier: &QuerierWrapper,
+        start_after: Option<AssetInfo>,
+        limit: Option<u32>,
+    ) -> StdResult<AllExchangeRatesResponse> {
+        let request =
+            QueryMsg::Exchange(OracleExchangeQuery::AllExchangeRates { start_after, limit });
+
+        self.query(querier, request)
+    }
+
+    pub fn query_exchange_rate_history(
+        &self,
+        querier: &QuerierWrapper,
+        asset_info: &AssetInfo,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<ExchangeRateHistoryResponse> {
+        let request = QueryMsg::Exchange(OracleExchangeQuery::ExchangeRateHistory {
+            asset_info: asset_info.clone(),
+            start_after,
+            limit,
+        });
+
+        self.query(querier, request)
+    }
+
+    pub fn query_exchange_rate_twap(
+        &self,
+        querier: &QuerierWrapper,
+        base_asset_info: &AssetInfo,
+        quote_asset_info: &AssetInfo,
+        window_seconds: u64,
+    ) -> StdResult<ExchangeRateTwapResponse> {
+        let request = QueryMsg::Exchange(OracleExchangeQuery::ExchangeRateTwap {
+            base_asset_info: Some(base_asset_info.clone()),
+            quote_asset_info: quote_asset_info.clone(),
+            window_seconds,
+        });
+
+        self.query(querier, request)
+    }
+
     pub fn query_contract_info<T: Into<String>>(
         &self,
         querier: &QuerierWrapper,
@@ -236,6 +613,24 @@ impl OracleContract {
 
         self.query(querier, request)
     }
+
+    pub fn query_feed_config(&self, querier: &QuerierWrapper) -> StdResult<FeedConfigResponse> {
+        let request = QueryMsg::Contract(OracleContractQuery::FeedConfig {});
+
+        self.query(querier, request)
+    }
+
+    pub fn query_fallback_price(
+        &self,
+        querier: &QuerierWrapper,
+        asset_info: &AssetInfo,
+    ) -> StdResult<Option<FallbackPrice>> {
+        let request = QueryMsg::Exchange(OracleExchangeQuery::FallbackPrice {
+            asset_info: asset_info.clone(),
+        });
+
+        self.query(querier, request)
+    }
 }
 
 impl ToString for OracleContract {
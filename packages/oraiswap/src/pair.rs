@@ -96,6 +96,8 @@ pub enum QueryMsg {
     TraderIsWhitelisted { trader: Addr },
     #[returns(String)]
     Admin {},
+    #[returns(CumulativePricesResponse)]
+    CumulativePrices {},
 }
 
 // We define a custom struct for each query response
@@ -126,6 +128,16 @@ pub struct ReverseSimulationResponse {
     pub commission_amount: Uint128,
 }
 
+/// CumulativePricesResponse returns the time-weighted price accumulators as of the current block
+/// with the reserves they are accumulating, price0 is asset_infos[0] priced in asset_infos[1]
+#[cw_serde]
+pub struct CumulativePricesResponse {
+    pub assets: [Asset; 2],
+    pub price0_cumulative: Decimal256,
+    pub price1_cumulative: Decimal256,
+    pub timestamp: u64,
+}
+
 /// We currently take no arguments for migrations
 #[cw_serde]
 pub struct MigrateMsg {
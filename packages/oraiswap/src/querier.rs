@@ -1,7 +1,8 @@
 use crate::asset::{Asset, AssetInfo, PairInfo};
 use crate::factory::{ConfigResponse, QueryMsg as FactoryQueryMsg};
 use crate::pair::{
-    PairResponse, QueryMsg as PairQueryMsg, ReverseSimulationResponse, SimulationResponse,
+    CumulativePricesResponse, PairResponse, PoolResponse, QueryMsg as PairQueryMsg,
+    ReverseSimulationResponse, SimulationResponse,
 };
 
 use cosmwasm_std::{Addr, QuerierWrapper, StdResult, Uint128};
@@ -90,6 +91,17 @@ pub fn query_pair_info_from_pair(
     Ok(res.info)
 }
 
+pub fn query_pool(querier: &QuerierWrapper, pair_contract: Addr) -> StdResult<PoolResponse> {
+    querier.query_wasm_smart(pair_contract, &PairQueryMsg::Pool {})
+}
+
+pub fn query_cumulative_prices(
+    querier: &QuerierWrapper,
+    pair_contract: Addr,
+) -> StdResult<CumulativePricesResponse> {
+    querier.query_wasm_smart(pair_contract, &PairQueryMsg::CumulativePrices {})
+}
+
 // upper bound key by 1, for Order::Ascending
 pub fn calc_range_start(start_after: Option<Vec<u8>>) -> Option<Vec<u8>> {
     start_after.map(|mut input| {
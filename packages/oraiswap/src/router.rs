@@ -1,8 +1,8 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
 
 use cosmwasm_std::{
-    coin, to_json_binary, Addr, Binary, CosmosMsg, QuerierWrapper, StdError, StdResult, Uint128,
-    WasmMsg,
+    coin, to_json_binary, Addr, Binary, CosmosMsg, Decimal, QuerierWrapper, StdError, StdResult,
+    Uint128, WasmMsg,
 };
 use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
 use prost::Message;
@@ -13,10 +13,14 @@ use crate::{asset::AssetInfo, universal_swap_memo::Memo};
 pub struct InstantiateMsg {
     pub factory_addr: Addr,
     pub factory_addr_v2: Addr,
+    pub converter: Option<Addr>,
 }
 
 #[cw_serde]
-pub struct MigrateMsg {}
+pub struct MigrateMsg {
+    /// None keeps the current converter
+    pub converter: Option<Addr>,
+}
 
 #[cw_serde]
 pub enum SwapOperation {
@@ -25,16 +29,53 @@ pub enum SwapOperation {
         offer_asset_info: AssetInfo,
         ask_asset_info: AssetInfo,
     },
+    // market order on the orderbook, asset_infos is [offer, ask]
+    OrderbookMarket {
+        orderbook_addr: Addr,
+        asset_infos: [AssetInfo; 2],
+        slippage: Option<Decimal>,
+    },
+    // convert between two representations of an asset with the converter
+    Convert {
+        from: AssetInfo,
+        to: AssetInfo,
+    },
 }
 
 impl SwapOperation {
+    pub fn get_offer_asset_info(&self) -> AssetInfo {
+        match self {
+            SwapOperation::OraiSwap {
+                offer_asset_info, ..
+            } => offer_asset_info.clone(),
+            SwapOperation::OrderbookMarket { asset_infos, .. } => asset_infos[0].clone(),
+            SwapOperation::Convert { from, .. } => from.clone(),
+        }
+    }
+
     pub fn get_target_asset_info(&self) -> AssetInfo {
         match self {
             SwapOperation::OraiSwap { ask_asset_info, .. } => ask_asset_info.clone(),
+            SwapOperation::OrderbookMarket { asset_infos, .. } => asset_infos[1].clone(),
+            SwapOperation::Convert { to, .. } => to.clone(),
         }
     }
 }
 
+/// offer of one route of a split swap
+#[cw_serde]
+pub enum SplitOffer {
+    Amount(Uint128),
+    /// share of the total offer amount
+    Percent(Decimal),
+}
+
+#[cw_serde]
+pub struct SplitRoute {
+    pub offer: SplitOffer,
+    pub operations: Vec<SwapOperation>,
+}
+
 #[cw_serde]
 pub enum ExecuteMsg {
     Receive(Cw20ReceiveMsg),
@@ -44,13 +85,35 @@ pub enum ExecuteMsg {
         minimum_receive: Option<Uint128>,
         to: Option<Addr>,
     },
+    /// Execute multiple BuyOperation receiving exactly ask_amount,
+    /// only the required offer amount is taken and the rest is refunded
+    ExecuteSwapOperationsExactOut {
+        operations: Vec<SwapOperation>,
+        ask_amount: Uint128,
+        max_offer: Uint128,
+    },
+    /// Execute several routes from the same offer asset to the same ask asset,
+    /// minimum_receive applies to the sum of the routes
+    ExecuteSplitSwap {
+        routes: Vec<SplitRoute>,
+        minimum_receive: Option<Uint128>,
+        to: Option<Addr>,
+    },
 
     /// Internal use
-    /// Swap all offer tokens to ask token
+    /// Swap all offer tokens to ask token, or only amount if given
     ExecuteSwapOperation {
         operation: SwapOperation,
         to: Option<Addr>,
         sender: Addr,
+        amount: Option<Uint128>,
+    },
+    /// Internal use
+    /// Send the balance of asset_info above retained_amount to receiver
+    TransferExcess {
+        asset_info: AssetInfo,
+        retained_amount: Uint128,
+        receiver: Addr,
     },
     /// Internal use
     /// Check the swap amount is exceed minimum_receive
@@ -69,6 +132,16 @@ pub enum Cw20HookMsg {
         minimum_receive: Option<Uint128>,
         to: Option<String>,
     },
+    ExecuteSwapOperationsExactOut {
+        operations: Vec<SwapOperation>,
+        ask_amount: Uint128,
+        max_offer: Uint128,
+    },
+    ExecuteSplitSwap {
+        routes: Vec<SplitRoute>,
+        minimum_receive: Option<Uint128>,
+        to: Option<String>,
+    },
 }
 
 #[cw_serde]
@@ -81,6 +154,18 @@ pub enum QueryMsg {
         offer_amount: Uint128,
         operations: Vec<SwapOperation>,
     },
+    /// same as SimulateSwapOperations, with the breakdown of every hop
+    #[returns(SimulateSwapOperationsDetailedResponse)]
+    SimulateSwapOperationsDetailed {
+        offer_amount: Uint128,
+        operations: Vec<SwapOperation>,
+    },
+    /// offer amount required to receive ask_amount through the operations
+    #[returns(SimulateSwapOperationsResponse)]
+    SimulateReverseSwapOperations {
+        ask_amount: Uint128,
+        operations: Vec<SwapOperation>,
+    },
 }
 
 // We define a custom struct for each query response
@@ -88,6 +173,7 @@ pub enum QueryMsg {
 pub struct ConfigResponse {
     pub factory_addr: Addr,
     pub factory_addr_v2: Addr,
+    pub converter: Option<Addr>,
 }
 
 // We define a custom struct for each query response
@@ -96,6 +182,34 @@ pub struct SimulateSwapOperationsResponse {
     pub amount: Uint128,
 }
 
+#[cw_serde]
+pub struct SimulateSwapOperationsDetailedResponse {
+    pub amount: Uint128,
+    pub hops: Vec<HopSimulation>,
+    /// shortfall of amount versus the offer amount converted at the spot price of the route
+    pub price_impact: Decimal,
+}
+
+#[cw_serde]
+pub struct HopSimulation {
+    pub offer_asset_info: AssetInfo,
+    pub ask_asset_info: AssetInfo,
+    /// amount sent to the pair, before tax
+    pub offer_amount: Uint128,
+    /// amount received from the pair, after tax
+    pub return_amount: Uint128,
+    pub spread_amount: Uint128,
+    pub commission_amount: Uint128,
+    /// oracle tax charged on the offer and on the return
+    pub tax_amount: Uint128,
+    /// unfilled offer returned to the sender, orderbook only
+    pub refund_amount: Uint128,
+    /// pair, orderbook or converter
+    pub pair_addr: Addr,
+    /// the pair is listed by factory_addr_v2 instead of factory_addr
+    pub from_factory_v2: bool,
+}
+
 #[cw_serde]
 pub struct RouterController(pub String);
 
@@ -163,6 +277,36 @@ impl RouterController {
             },
         )
     }
+
+    pub fn simulate_swap_detailed(
+        &self,
+        querier: &QuerierWrapper,
+        offer_amount: Uint128,
+        operations: Vec<SwapOperation>,
+    ) -> StdResult<SimulateSwapOperationsDetailedResponse> {
+        querier.query_wasm_smart(
+            self.addr(),
+            &QueryMsg::SimulateSwapOperationsDetailed {
+                offer_amount,
+                operations,
+            },
+        )
+    }
+
+    pub fn simulate_reverse_swap(
+        &self,
+        querier: &QuerierWrapper,
+        ask_amount: Uint128,
+        operations: Vec<SwapOperation>,
+    ) -> StdResult<SimulateSwapOperationsResponse> {
+        querier.query_wasm_smart(
+            self.addr(),
+            &QueryMsg::SimulateReverseSwapOperations {
+                ask_amount,
+                operations,
+            },
+        )
+    }
 }
 
 impl Memo {
@@ -1,10 +1,41 @@
-use crate::{asset::AssetInfo, router::SwapOperation};
+use crate::{
+    asset::AssetInfo, mixed_router::SwapOperation as MixedSwapOperation, router::SwapOperation,
+    universal_swap_memo::memo,
+};
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::Uint128;
+use cosmwasm_std::{Coin, Decimal, Decimal256, Uint128};
 
 #[cw_serde]
 pub enum SmartRouteMode {
     MaxMinimumReceive,
+    /// fewest operations, the highest return breaks ties
+    MinHops,
+    /// lowest price impact, the highest return breaks ties
+    MinPriceImpact,
+    /// highest return scaled by (1 - penalty_per_hop) for every operation
+    MaxOutputWithHopPenalty {
+        penalty_per_hop: Decimal,
+    },
+    /// highest return among the routes only using the listed venues,
+    /// any route when none of them can be simulated
+    PreferVenue(Vec<SwapVenue>),
+}
+
+#[cw_serde]
+pub enum SwapVenue {
+    OraiSwap,
+    Orderbook,
+    Convert,
+}
+
+impl SwapVenue {
+    pub fn of(operation: &SwapOperation) -> Self {
+        match operation {
+            SwapOperation::OraiSwap { .. } => SwapVenue::OraiSwap,
+            SwapOperation::OrderbookMarket { .. } => SwapVenue::Orderbook,
+            SwapOperation::Convert { .. } => SwapVenue::Convert,
+        }
+    }
 }
 
 #[cw_serde]
@@ -16,20 +47,24 @@ pub struct InstantiateMsg {
 #[cw_serde]
 pub struct MigrateMsg {}
 
-// #[cw_serde]
-// pub enum Slippage {
-//     Twap {
-//         window_seconds: Option<u64>,
-//         slippage_percentage: Decimal,
-//     },
-//     MinOutputAmount(Uint128),
-// }
+#[cw_serde]
+pub enum Slippage {
+    /// minimum receive is the input converted at the oracle twap of the window,
+    /// less slippage_percentage, both assets must be priced by the oracle
+    Twap {
+        window_seconds: Option<u64>,
+        slippage_percentage: Decimal,
+    },
+    MinOutputAmount(Uint128),
+}
 
 #[cw_serde]
 pub enum ExecuteMsg {
     UpdateConfig {
         new_owner: Option<String>,
         new_router: Option<String>,
+        new_keeper: Option<String>,
+        new_mixed_router: Option<String>,
     },
     SetRoute {
         input_info: AssetInfo,
@@ -40,12 +75,22 @@ pub enum ExecuteMsg {
         input_info: AssetInfo,
         output_info: AssetInfo,
         route_index: usize,
-    }, // Swap {
-       //     input_coin: Coin,
-       //     output_denom: String,
-       //     slippage: Slippage,
-       //     route: Option<Vec<SwapOperation>>,
-       // },
+    },
+    /// owner or keeper, replace the discovered routes of the pair with the candidates found on the factories
+    /// and the v3 pools
+    RefreshRoutes {
+        input_info: AssetInfo,
+        output_info: AssetInfo,
+        max_hops: u32,
+    },
+    /// swap input_coin, sent as the only funds, through the router to the sender,
+    /// the smart route is used when route is None
+    Swap {
+        input_coin: Coin,
+        output_denom: String,
+        slippage: Slippage,
+        route: Option<Vec<SwapOperation>>,
+    },
 }
 
 #[cw_serde]
@@ -71,6 +116,34 @@ pub enum QueryMsg {
         offer_amount: Uint128,
         route_mode: Option<SmartRouteMode>,
     },
+    /// offer_amount split over at most max_splits routes to maximize the total return
+    #[returns(GetSmartSplitRouteResponse)]
+    GetSmartSplitRoute {
+        input_info: AssetInfo,
+        output_info: AssetInfo,
+        offer_amount: Uint128,
+        max_splits: u32,
+    },
+    /// candidate routes found on the factory pairs, and on the v3 pools when a mixed router is set,
+    /// best simulated return first, only the shortest paths are simulated past a fixed number
+    #[returns(FindRoutesResponse)]
+    FindRoutes {
+        input_info: AssetInfo,
+        output_info: AssetInfo,
+        max_hops: u32,
+    },
+    /// routes cached by the last RefreshRoutes
+    #[returns(GetRoutesResponse)]
+    GetDiscoveredRoutes {
+        input_info: AssetInfo,
+        output_info: AssetInfo,
+    },
+    /// routes through v3 pools cached by the last RefreshRoutes
+    #[returns(GetV3RoutesResponse)]
+    GetDiscoveredV3Routes {
+        input_info: AssetInfo,
+        output_info: AssetInfo,
+    },
 }
 
 // Response for GetOwner query
@@ -78,6 +151,8 @@ pub enum QueryMsg {
 pub struct GetConfigResponse {
     pub owner: String,
     pub router: String,
+    pub keeper: Option<String>,
+    pub mixed_router: Option<String>,
 }
 
 // Response for GetRoutes query
@@ -97,6 +172,106 @@ pub struct GetRouteResponse {
 pub struct GetSmartRouteResponse {
     pub swap_ops: Vec<SwapOperation>,
     pub actual_minimum_receive: Uint128,
+    /// score of the chosen route under the route mode
+    #[serde(default)]
+    pub score: Option<Decimal256>,
+    /// every route simulated successfully
+    #[serde(default)]
+    pub candidates: Option<Vec<SmartRouteCandidate>>,
+    /// every route that failed to simulate
+    #[serde(default)]
+    pub errors: Option<Vec<SmartRouteError>>,
+}
+
+#[cw_serde]
+pub struct SmartRouteCandidate {
+    /// index in the routes of the pair, set ones first then discovered ones
+    pub route_index: usize,
+    pub swap_ops: Vec<SwapOperation>,
+    pub return_amount: Uint128,
+    /// only simulated for MinPriceImpact
+    pub price_impact: Option<Decimal>,
+    /// the return for MaxMinimumReceive and PreferVenue, the hops for MinHops,
+    /// the price impact for MinPriceImpact and the penalized return for MaxOutputWithHopPenalty
+    pub score: Decimal256,
+}
+
+#[cw_serde]
+pub struct SmartRouteError {
+    pub route_index: usize,
+    pub swap_ops: Vec<SwapOperation>,
+    pub error: String,
+}
+
+/// operation of the universal swap memo, pool_id is the pair, orderbook or converter address
+#[cw_serde]
+pub struct SmartSwapOperation {
+    pub pool_id: String,
+    pub denom_in: String,
+    pub denom_out: String,
+}
+
+/// route of the universal swap memo
+#[cw_serde]
+pub struct SmartSwapRoute {
+    pub offer_amount: Uint128,
+    pub operations: Vec<SmartSwapOperation>,
+}
+
+#[cw_serde]
+pub struct GetSmartSplitRouteResponse {
+    /// routes of the SmartSwapExactAssetIn memo
+    pub routes: Vec<SmartSwapRoute>,
+    pub actual_minimum_receive: Uint128,
+}
+
+impl From<GetSmartSplitRouteResponse> for memo::SmartSwapExactAssetIn {
+    fn from(res: GetSmartSplitRouteResponse) -> Self {
+        memo::SmartSwapExactAssetIn {
+            routes: res
+                .routes
+                .into_iter()
+                .map(|route| memo::Route {
+                    offer_amount: route.offer_amount.to_string(),
+                    operations: route
+                        .operations
+                        .into_iter()
+                        .map(|operation| memo::SwapOperation {
+                            pool_id: operation.pool_id,
+                            denom_in: operation.denom_in,
+                            denom_out: operation.denom_out,
+                        })
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cw_serde]
+pub struct RouteCandidate {
+    pub operations: Vec<SwapOperation>,
+    /// return of the route for the probe offer amount
+    pub return_amount: Uint128,
+}
+
+/// route through at least one v3 pool, swapped by the mixed router
+#[cw_serde]
+pub struct V3RouteCandidate {
+    pub operations: Vec<MixedSwapOperation>,
+    /// return of the route for the probe offer amount
+    pub return_amount: Uint128,
+}
+
+#[cw_serde]
+pub struct FindRoutesResponse {
+    pub routes: Vec<RouteCandidate>,
+    pub v3_routes: Vec<V3RouteCandidate>,
+}
+
+#[cw_serde]
+pub struct GetV3RoutesResponse {
+    pub pool_routes: Vec<Vec<MixedSwapOperation>>,
 }
 
 // Response for Swap
@@ -109,6 +109,7 @@ impl MockApp {
                 &crate::router::InstantiateMsg {
                     factory_addr: factory_addr.clone(),
                     factory_addr_v2: factory_addr.clone(),
+                    converter: None,
                 },
                 &[],
                 "router",
@@ -220,7 +221,9 @@ impl MockApp {
                     Addr::unchecked(APP_OWNER),
                     contract_addr.clone(),
                     &crate::oracle::ExecuteMsg::UpdateTaxCap {
-                        denom: denom.to_string(),
+                        asset_info: AssetInfo::NativeToken {
+                            denom: denom.to_string(),
+                        },
                         cap: Uint128::from(*cap),
                     },
                     &[],
@@ -0,0 +1,199 @@
+use cosmwasm_schema::{cw_serde, QueryResponses};
+use cosmwasm_std::{Addr, Binary, Uint128};
+use cw20::{
+    AllAccountsResponse, AllAllowancesResponse, AllSpenderAllowancesResponse, AllowanceResponse,
+    BalanceResponse, Cw20Coin, DownloadLogoResponse, Expiration, Logo, MarketingInfoResponse,
+    MinterResponse, TokenInfoResponse,
+};
+use cw20_base::msg::InstantiateMarketingInfo;
+
+/// InstantiateMsg is the cw20-base instantiate message with the compliance roles
+#[cw_serde]
+pub struct InstantiateMsg {
+    pub name: String,
+    pub symbol: String,
+    pub decimals: u8,
+    pub initial_balances: Vec<Cw20Coin>,
+    pub mint: Option<MinterResponse>,
+    pub marketing: Option<InstantiateMarketingInfo>,
+    /// can freeze addresses and pause transfers
+    pub admin: Option<String>,
+    /// can burn the balance of frozen addresses
+    pub burner: Option<String>,
+}
+
+/// MigrateMsg sets the compliance roles of tokens created without them
+#[cw_serde]
+pub struct MigrateMsg {
+    pub admin: Option<String>,
+    pub burner: Option<String>,
+}
+
+/// ExecuteMsg is the cw20-base execute set extended with signed permits and compliance controls
+#[cw_serde]
+pub enum ExecuteMsg {
+    Transfer {
+        recipient: String,
+        amount: Uint128,
+    },
+    Burn {
+        amount: Uint128,
+    },
+    Send {
+        contract: String,
+        amount: Uint128,
+        msg: Binary,
+    },
+    IncreaseAllowance {
+        spender: String,
+        amount: Uint128,
+        expires: Option<Expiration>,
+    },
+    DecreaseAllowance {
+        spender: String,
+        amount: Uint128,
+        expires: Option<Expiration>,
+    },
+    TransferFrom {
+        owner: String,
+        recipient: String,
+        amount: Uint128,
+    },
+    SendFrom {
+        owner: String,
+        contract: String,
+        amount: Uint128,
+        msg: Binary,
+    },
+    BurnFrom {
+        owner: String,
+        amount: Uint128,
+    },
+    Mint {
+        recipient: String,
+        amount: Uint128,
+    },
+    UpdateMinter {
+        new_minter: Option<String>,
+    },
+    UpdateMarketing {
+        project: Option<String>,
+        description: Option<String>,
+        marketing: Option<String>,
+    },
+    UploadLogo(Logo),
+    /// set the allowance of spender over the tokens of the owner of owner_pubkey,
+    /// signature is the secp256k1 signature of sha256(json(PermitPayload)) by the owner,
+    /// so anyone can relay it
+    Permit {
+        owner_pubkey: Binary,
+        spender: String,
+        amount: Uint128,
+        expires: Expiration,
+        nonce: u64,
+        signature: Binary,
+    },
+    /// frozen addresses can neither send nor receive tokens
+    Freeze {
+        address: String,
+    },
+    Unfreeze {
+        address: String,
+    },
+    /// stop every balance change except BurnFrozen
+    Pause {},
+    Unpause {},
+    /// None keeps the current role
+    UpdateComplianceRoles {
+        admin: Option<String>,
+        burner: Option<String>,
+    },
+    /// burn from a frozen address, only by the burner
+    BurnFrozen {
+        address: String,
+        amount: Uint128,
+    },
+}
+
+/// PermitPayload is the message signed by the owner for a permit
+#[cw_serde]
+pub struct PermitPayload {
+    pub chain_id: String,
+    pub contract: String,
+    pub spender: String,
+    pub amount: Uint128,
+    pub expires: Expiration,
+    pub nonce: u64,
+}
+
+/// QueryMsg is the cw20-base query set extended with snapshots and permit nonces
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum QueryMsg {
+    #[returns(BalanceResponse)]
+    Balance { address: String },
+    #[returns(TokenInfoResponse)]
+    TokenInfo {},
+    #[returns(MinterResponse)]
+    Minter {},
+    #[returns(AllowanceResponse)]
+    Allowance { owner: String, spender: String },
+    #[returns(AllAllowancesResponse)]
+    AllAllowances {
+        owner: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    #[returns(AllSpenderAllowancesResponse)]
+    AllSpenderAllowances {
+        spender: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    #[returns(AllAccountsResponse)]
+    AllAccounts {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    #[returns(MarketingInfoResponse)]
+    MarketingInfo {},
+    #[returns(DownloadLogoResponse)]
+    DownloadLogo {},
+    /// balance of address at the beginning of the block at height
+    #[returns(BalanceResponse)]
+    BalanceAt { address: String, height: u64 },
+    /// total supply at the beginning of the block at height
+    #[returns(TotalSupplyResponse)]
+    TotalSupplyAt { height: u64 },
+    /// nonce expected by the next permit of owner
+    #[returns(PermitNonceResponse)]
+    PermitNonce { owner: String },
+    #[returns(FrozenResponse)]
+    Frozen { address: String },
+    #[returns(ComplianceConfigResponse)]
+    ComplianceConfig {},
+}
+
+#[cw_serde]
+pub struct TotalSupplyResponse {
+    pub total_supply: Uint128,
+}
+
+#[cw_serde]
+pub struct PermitNonceResponse {
+    pub nonce: u64,
+}
+
+#[cw_serde]
+pub struct FrozenResponse {
+    pub frozen: bool,
+    /// transfers are paused for everyone
+    pub paused: bool,
+}
+
+#[cw_serde]
+pub struct ComplianceConfigResponse {
+    pub admin: Option<Addr>,
+    pub burner: Option<Addr>,
+    pub paused: bool,
+}